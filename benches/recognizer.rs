@@ -0,0 +1,185 @@
+//! Benchmarks of building automata, stepping through them and running whole
+//! sessions. Run with `cargo bench`; Criterion keeps the last results under
+//! `target/criterion` and reports the change against them.
+
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::generate::{generate_grammar, generate_input, GeneratorConfig};
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_text, Grammar};
+use automate_refuse_de_nier::tools::rng::Rng;
+use automate_refuse_de_nier::tools::{feed_lines, parse_grammar_file, DFAConfig, Recognizer, DFA};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use std::hint::black_box;
+
+/// Keys of the synthetic moves, as many as a pad has buttons and directions
+const KEYS: [&str; 12] = [
+    "a",
+    "s",
+    "d",
+    "w",
+    "j",
+    "k",
+    "l",
+    "i",
+    "u",
+    "o",
+    "LeftShift",
+    "Space",
+];
+
+/// `count` moves of 2 to 8 keys, seeded so that every run builds the same
+/// automaton
+fn synthetic_moves(count: usize) -> Vec<(Vec<Symbol>, String)> {
+    let mut rng = Rng::new(count as u64);
+    (0..count)
+        .map(|i| {
+            let sequence = (0..rng.between(2, 8))
+                .map(|_| Symbol::new(KEYS[rng.below(KEYS.len())]).unwrap())
+                .collect();
+            (sequence, format!("Move {}", i))
+        })
+        .collect()
+}
+
+fn from_moves(c: &mut Criterion) {
+    let mut group = c.benchmark_group("from_moves");
+    for count in [1_000, 5_000, 20_000] {
+        let moves = synthetic_moves(count);
+        group.throughput(Throughput::Elements(count as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &moves, |b, moves| {
+            b.iter(|| DFA::from_moves(moves.clone()))
+        });
+    }
+    group.finish();
+}
+
+fn step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("step");
+    let config = DFAConfig::default();
+    for count in [10, 1_000, 20_000] {
+        let dfa = DFA::from_moves(synthetic_moves(count));
+        let mut rng = Rng::new(1);
+        let keys: Vec<Symbol> = (0..10_000)
+            .map(|_| Symbol::new(KEYS[rng.below(KEYS.len())]).unwrap())
+            .collect();
+        group.throughput(Throughput::Elements(keys.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(count), &keys, |b, keys| {
+            b.iter(|| {
+                let mut state = dfa.start_state();
+                for key in keys {
+                    // Sequences that break start over, as recognizers do
+                    state = match dfa.step(state, key, "", &config) {
+                        (Some(next), matches) => {
+                            black_box(matches);
+                            next
+                        }
+                        (None, _) => dfa.start_state(),
+                    };
+                }
+                state.clone()
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Lines players would type: moves of the grammar played by any player,
+/// between lines of keys mashed at random
+fn session_input(grammar: &Grammar, lines: usize) -> String {
+    let mut rng = Rng::new(lines as u64);
+    let keys: Vec<&Symbol> = grammar
+        .mappings
+        .keys()
+        .chain(grammar.player_keys.keys())
+        .collect();
+    let mut text = String::new();
+    for line in 0..lines {
+        let typed: Vec<&str> = if line % 2 == 0 {
+            let played = &grammar.moves[rng.below(grammar.moves.len())];
+            let player = rng.between(1, grammar.player_count());
+            played
+                .sequence
+                .iter()
+                .map(|key| bound_key(grammar, player, key))
+                .collect()
+        } else {
+            (0..rng.between(1, 6))
+                .map(|_| keys[rng.below(keys.len())].as_str())
+                .collect()
+        };
+        text.push_str(&typed.join(" "));
+        text.push('\n');
+    }
+    text
+}
+
+/// Key `player` presses for the player 1 key `key`
+fn bound_key<'a>(grammar: &'a Grammar, player: usize, key: &'a Symbol) -> &'a str {
+    grammar
+        .player_keys
+        .iter()
+        .find(|(_, (bound_player, bound))| *bound_player == player && bound == key)
+        .map_or(key.as_str(), |(bound, _)| bound.as_str())
+}
+
+fn session(c: &mut Criterion) {
+    let mut group = c.benchmark_group("session");
+    let config = DFAConfig::default();
+    for path in [
+        "grammars/mk9_with_moves.gmr",
+        "grammars/mk9_two_players.gmr",
+    ] {
+        let grammar = parse_grammar_file(path).expect("bundled grammar");
+        let dfa = DFA::from_grammar(&grammar);
+        let input = session_input(&grammar, 1_000);
+        group.throughput(Throughput::Bytes(input.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(path), &input, |b, input| {
+            b.iter(|| {
+                let mut recognizers: Vec<Recognizer> = (1..=grammar.player_count())
+                    .map(|player| Recognizer::for_player(&dfa, player))
+                    .collect();
+                let mut matches = 0;
+                feed_lines(&grammar, input.as_bytes(), |key, token| {
+                    let (player, key) = grammar
+                        .resolve_key(key.as_str())
+                        .map_or((1, key), |(player, key)| (player, key));
+                    let event = recognizers[player - 1].feed(&dfa, key.clone(), token, &config);
+                    matches += event.matches.len();
+                })
+                .unwrap();
+                matches
+            })
+        });
+    }
+    group.finish();
+}
+
+/// Parsing and recognizing with the grammar of a full roster
+fn roster(c: &mut Criterion) {
+    let mut group = c.benchmark_group("roster");
+    let grammar = generate_grammar(&GeneratorConfig::default()).unwrap();
+    let text = grammar.to_gmr();
+    group.throughput(Throughput::Bytes(text.len() as u64));
+    group.bench_function("parse", |b| {
+        b.iter(|| parse_grammar_text(&text, "roster.gmr").unwrap())
+    });
+
+    let dfa = DFA::from_grammar(&grammar);
+    let config = DFAConfig::default();
+    let input = generate_input(&grammar, 1_000, 1).unwrap();
+    group.throughput(Throughput::Bytes(input.text.len() as u64));
+    group.bench_function("session", |b| {
+        b.iter(|| {
+            let mut recognizer = Recognizer::new(&dfa);
+            let mut matches = 0;
+            feed_lines(&grammar, input.text.as_bytes(), |key, token| {
+                matches += recognizer.feed(&dfa, key, token, &config).matches.len();
+            })
+            .unwrap();
+            matches
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, from_moves, step, session, roster);
+criterion_main!(benches);
@@ -1,171 +1,836 @@
+use automate_refuse_de_nier::tools::cli::{
+    self, CliError, Command, DrillOptions, ExportFormat, GenerateOptions, MatchOptions, RunOptions,
+    StatsOptions, EXIT_GRAMMAR, EXIT_IO, EXIT_RUNTIME, EXIT_UNFORMATTED, EXIT_USAGE,
+};
+use automate_refuse_de_nier::tools::clock::{FrameClock, DEFAULT_FPS};
+use automate_refuse_de_nier::tools::dfa::{Symbol, DEFAULT_CONTINUATION_WINDOW};
+use automate_refuse_de_nier::tools::document::GrammarFormat;
+use automate_refuse_de_nier::tools::drill::{self, Drill};
+use automate_refuse_de_nier::tools::export;
+use automate_refuse_de_nier::tools::format;
+use automate_refuse_de_nier::tools::generate::{generate_grammar, generate_input};
+use automate_refuse_de_nier::tools::history::InputHistory;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_text, Grammar, MoveDef, MoveMetadata};
+use automate_refuse_de_nier::tools::reload::GrammarWatcher;
+use automate_refuse_de_nier::tools::stats::{self, AttemptRecord, StatsStore, StatsTracker};
+use automate_refuse_de_nier::tools::ui::{self, UiModel};
+use automate_refuse_de_nier::tools::{
+    feed_lines, parse_grammar_file, run_console_mode, run_drill_console, run_drill_loop,
+    run_input_loop, DFAConfig, Recognizer, StepEvent, Theme, TokenHandler, DFA,
+};
 use std::env;
-use std::process;
-use std::sync::{Arc, Mutex};
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-mod tools;
-use tools::{parse_grammar_file, run_console_mode, run_input_loop, DFAConfig, DFA};
+fn main() -> ExitCode {
+    let mut args = env::args();
+    let program = args
+        .next()
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string());
+    let args: Vec<String> = args.collect();
 
-/// Simple token buffer for tracking recent input
-struct TokenBuffer {
-    tokens: Vec<char>,
-    max_length: usize,
-}
+    if args.is_empty() {
+        eprint!("{}", cli::usage(&program));
+        return ExitCode::from(EXIT_USAGE);
+    }
 
-impl TokenBuffer {
-    fn new(max_length: usize) -> Self {
-        Self {
-            tokens: Vec::new(),
-            max_length,
+    match cli::parse_args(args).and_then(|command| execute(&program, command)) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            if e.code == EXIT_USAGE {
+                eprintln!("Run '{} --help' for usage.", program);
+            }
+            ExitCode::from(e.code)
         }
     }
+}
 
-    fn push(&mut self, token: char) {
-        self.tokens.push(token);
-        if self.tokens.len() > self.max_length {
-            self.tokens.remove(0);
+fn execute(program: &str, command: Command) -> Result<(), CliError> {
+    match command {
+        Command::Help(None) => print!("{}", cli::usage(program)),
+        Command::Help(Some(topic)) => match cli::command_usage(program, &topic) {
+            Some(text) => print!("{}", text),
+            None => return Err(CliError::usage(format!("unknown command '{}'", topic))),
+        },
+        Command::Version => println!("{} {}", env!("CARGO_PKG_NAME"), env!("CARGO_PKG_VERSION")),
+        Command::Run(options) => run(options)?,
+        Command::Check { grammar } => check(&grammar)?,
+        Command::Export {
+            grammar,
+            format,
+            output,
+        } => {
+            let (grammar, dfa) = load_grammar(&grammar)?;
+            let text = match format {
+                ExportFormat::Dot => export::to_dot(&dfa, &grammar),
+                ExportFormat::Table => export::to_table(&dfa),
+            };
+            match output {
+                Some(path) => write_output(&path, &text)?,
+                None => print!("{}", text),
+            }
+        }
+        Command::Compile { grammar, output } => {
+            let output = output.unwrap_or_else(|| grammar.with_extension("dfa"));
+            let (_, dfa) = load_grammar(&grammar)?;
+            write_output(&output, &export::to_table(&dfa))?;
+            println!(
+                "Compiled {} states to {}",
+                dfa.states().len(),
+                output.display()
+            );
+        }
+        Command::Replay {
+            grammar,
+            input,
+            debug,
+            matching,
+        } => {
+            let (grammar, dfa) = load_grammar(&grammar)?;
+            let mut printer = TokenPrinter::new(
+                grammar.clone(),
+                dfa,
+                debug,
+                matching,
+                FrameClock::new(DEFAULT_FPS),
+            );
+            let result = match input {
+                Some(path) => {
+                    let file = File::open(&path).map_err(|e| {
+                        CliError::new(
+                            EXIT_IO,
+                            format!("Failed to read input file '{}': {}", path.display(), e),
+                        )
+                    })?;
+                    feed_lines(&grammar, BufReader::new(file), |ch, name| {
+                        printer.token(ch, name);
+                    })
+                }
+                None => feed_lines(&grammar, io::stdin().lock(), |ch, name| {
+                    printer.token(ch, name);
+                }),
+            };
+            printer.finish();
+            println!();
+            result.map_err(|e| CliError::new(EXIT_IO, format!("Failed to read input: {}", e)))?;
+        }
+        Command::ListMoves { grammar, character } => {
+            let (grammar, _) = load_grammar(&grammar)?;
+            list_moves(&grammar, character.as_deref())?;
         }
+        Command::Drill(options) => drill(options)?,
+        Command::Generate(options) => generate(&options)?,
+        Command::Stats {
+            grammar,
+            character,
+            reset,
+            file,
+        } => show_stats(&grammar, character.as_deref(), reset, file)?,
+        Command::Fmt {
+            grammar,
+            check,
+            output,
+        } => format_grammar(&grammar, check, output.as_deref())?,
     }
+    Ok(())
+}
 
+/// Parse a grammar file and build its automaton
+fn load_grammar(path: &Path) -> Result<(Grammar, DFA), CliError> {
+    let grammar = parse_grammar_file(path)
+        .map_err(|e| CliError::new(EXIT_GRAMMAR, format!("Error parsing grammar file: {}", e)))?;
+    let dfa = DFA::from_grammar(&grammar);
+    Ok((grammar, dfa))
 }
 
-fn main() {
-    // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+fn write_output(path: &Path, contents: &str) -> Result<(), CliError> {
+    fs::write(path, contents).map_err(|e| {
+        CliError::new(
+            EXIT_IO,
+            format!("Failed to write '{}': {}", path.display(), e),
+        )
+    })
+}
 
-    if args.len() < 2 {
-        eprintln!("Usage: {} <grammar_file.gmr> [OPTIONS]", args[0]);
-        eprintln!();
-        eprintln!("Options:");
-        eprintln!("  --gui      Enable graphical SDL window (optional)");
-        eprintln!("  --debug    Enable debug mode with state transition tracing (optional)");
-        eprintln!();
-        eprintln!("Examples:");
-        eprintln!("  {} grammars/mk9.gmr", args[0]);
-        eprintln!("  {} grammars/mk9.gmr --debug", args[0]);
-        eprintln!("  {} grammars/mk9.gmr --gui", args[0]);
-        eprintln!("  {} grammars/mk9.gmr --gui --debug", args[0]);
-        process::exit(1);
+fn load_theme(spec: Option<&str>) -> Result<Theme, CliError> {
+    match spec {
+        Some(spec) => Theme::load(spec).map_err(|e| {
+            // A spec that names no file is a mistyped preset
+            let code = if Path::new(spec).exists() {
+                EXIT_IO
+            } else {
+                EXIT_USAGE
+            };
+            CliError::new(code, e)
+        }),
+        None => Ok(Theme::default()),
     }
+}
 
-    let grammar_path = &args[1];
-    let debug_mode = args.iter().any(|arg| arg == "--debug");
-    let gui_mode = args.iter().any(|arg| arg == "--gui");
+/// Practice statistics recorded during a session, saved when it ends
+struct StatsSession {
+    path: PathBuf,
+    key: String,
+    store: StatsStore,
+}
 
-    // Parse the grammar file
-    let grammar = match parse_grammar_file(grammar_path) {
-        Ok(g) => g,
-        Err(e) => {
-            eprintln!("Error parsing grammar file: {}", e);
-            process::exit(1);
+impl StatsSession {
+    /// Load the statistics of a session. Statistics are a side feature:
+    /// problems are reported and the session goes on without them.
+    fn open(options: &StatsOptions, grammar: &Path) -> Option<Self> {
+        if options.disabled {
+            return None;
         }
-    };
+        let Some(path) = options.file.clone().or_else(stats::default_path) else {
+            eprintln!("Warning: no home directory, practice statistics will not be saved");
+            return None;
+        };
+        match StatsStore::load(&path) {
+            Ok(store) => Some(Self {
+                path,
+                key: stats::grammar_key(grammar),
+                store,
+            }),
+            Err(e) => {
+                eprintln!("Warning: {}; practice statistics will not be saved", e);
+                None
+            }
+        }
+    }
 
-    // Display key mappings automatically derived from grammar
-    grammar.display_key_mappings();
+    fn record(&mut self, m: &MoveDef, attempt: &AttemptRecord) {
+        self.store.record(&self.key, m, attempt);
+    }
 
-    // Build DFA from moves in the grammar
-    let dfa = if grammar.moves.is_empty() {
-        eprintln!(
-            "Warning: No moves defined in grammar file. The DFA will not recognize any combos."
-        );
-        eprintln!("Add move definitions like: Move Name: k e y s");
-        DFA::from_moves(vec![])
-    } else {
-        let move_data: Vec<_> = grammar
-            .moves
+    fn save(&self) {
+        if let Err(e) = self.store.save(&self.path) {
+            eprintln!("Warning: {}", e);
+        }
+    }
+}
+
+/// Console side of the interactive and replay modes: feeds each player's
+/// recognizer, echoes each token, explains broken sequences, prints the
+/// moves it completes and records practice statistics
+struct TokenPrinter {
+    grammar: Grammar,
+    dfa: DFA,
+    config: DFAConfig,
+    /// Match options of the command line, applied again on reload
+    matching: MatchOptions,
+    /// Stamps each input with the game tick it was pressed on
+    clock: FrameClock,
+    /// Reloads the grammar when its files change
+    watcher: Option<GrammarWatcher>,
+    /// One recognizer per player, player 1 first
+    recognizers: Vec<Recognizer>,
+    /// Statistics with one attempt tracker per player
+    stats: Option<(StatsSession, Vec<StatsTracker>)>,
+}
+
+impl TokenPrinter {
+    fn new(
+        grammar: Grammar,
+        dfa: DFA,
+        debug: bool,
+        matching: MatchOptions,
+        clock: FrameClock,
+    ) -> Self {
+        Self {
+            recognizers: (1..=grammar.player_count())
+                .map(|player| Recognizer::for_player(&dfa, player))
+                .collect(),
+            config: dfa_config(&grammar, debug, matching, clock.fps()),
+            grammar,
+            dfa,
+            matching,
+            clock,
+            watcher: None,
+            stats: None,
+        }
+    }
+
+    fn with_stats(mut self, stats: Option<StatsSession>) -> Self {
+        let trackers = vec![StatsTracker::new(&self.grammar); self.recognizers.len()];
+        self.stats = stats.map(|stats| (stats, trackers));
+        self
+    }
+
+    fn with_watcher(mut self, watcher: GrammarWatcher) -> Self {
+        self.watcher = Some(watcher);
+        self
+    }
+
+    /// Report the moves still held back by the recognizers
+    fn flush(&mut self) {
+        let released: Vec<(usize, String)> = self
+            .recognizers
+            .iter_mut()
+            .flat_map(|r| {
+                let player = r.player();
+                r.flush(&self.dfa)
+                    .into_iter()
+                    .map(move |name| (player, name))
+            })
+            .collect();
+        self.print_released(&released);
+    }
+
+    /// Switch to a reloaded grammar. Sequences underway are dropped; the
+    /// statistics and input history carry on.
+    fn swap(&mut self, grammar: Grammar, dfa: DFA) {
+        self.flush();
+        let mut histories: Vec<InputHistory> = self
+            .recognizers
             .iter()
-            .map(|m| (m.sequence.clone(), m.name.clone()))
+            .map(|r| r.history().clone())
             .collect();
-        DFA::from_moves(move_data)
-    };
+        histories.resize_with(grammar.player_count(), InputHistory::default);
+        self.recognizers = histories
+            .into_iter()
+            .zip(1..)
+            .map(|(history, player)| Recognizer::for_player(&dfa, player).with_history(history))
+            .collect();
+        if let Some((_, trackers)) = &mut self.stats {
+            *trackers = vec![StatsTracker::new(&grammar); self.recognizers.len()];
+        }
+        self.config = dfa_config(&grammar, self.config.debug, self.matching, self.clock.fps());
+        self.grammar = grammar;
+        self.dfa = dfa;
+    }
 
-    // Create DFA configuration
-    let config = DFAConfig { debug: debug_mode };
+    /// Prefix naming the player, only when several are playing
+    fn label(&self, player: usize) -> String {
+        if self.recognizers.len() > 1 {
+            format!("P{} ", player)
+        } else {
+            String::new()
+        }
+    }
 
-    // Track current DFA state
-    let current_state = Arc::new(Mutex::new(dfa.start_state().clone()));
-    let token_buffer = Arc::new(Mutex::new(TokenBuffer::new(20)));
+    /// Print a completed move with what the grammar tells about it
+    fn print_match(&self, player: usize, move_name: &str, metadata: Option<&MoveMetadata>) {
+        let label = self.label(player);
+        match metadata
+            .map(MoveMetadata::summary)
+            .filter(|s| !s.is_empty())
+        {
+            Some(summary) => println!("{}{} !! ({})", label, move_name, summary),
+            None => println!("{}{} !!", label, move_name),
+        }
+        if let Some(notes) = metadata.and_then(|m| m.notes.as_deref()) {
+            println!("{}  {}", label, notes);
+        }
+    }
+
+    /// Show the inputs of a move the leniency rules let through, when they
+    /// are not its sequence
+    fn print_lenient_inputs(&self, player: usize, move_name: &str, span: Range<u64>) {
+        let history = self.recognizers[player - 1].history();
+        let keys: Vec<Symbol> = history.range(span.clone()).map(|r| r.key.clone()).collect();
+        let sequence = self
+            .grammar
+            .moves
+            .iter()
+            .find(|m| m.name == move_name)
+            .map(|m| m.sequence.as_slice());
+        if sequence.is_some_and(|sequence| sequence != keys) {
+            println!(
+                "{}  entered as {}",
+                self.label(player),
+                history.tokens(span).join(", ")
+            );
+        }
+    }
 
-    // Clone references for the closure
-    let current_state_clone = Arc::clone(&current_state);
-    let token_buffer_clone = Arc::clone(&token_buffer);
+    fn print_released(&self, released: &[(usize, String)]) {
+        if !released.is_empty() {
+            println!();
+            for (player, move_name) in released {
+                self.print_match(*player, move_name, self.dfa.metadata(move_name));
+            }
+        }
+    }
 
-    // Define the token processing callback
-    let process_token = move |ch: char, token_name: &str| {
-        // Add token to buffer
-        {
-            let mut buffer = match token_buffer_clone.lock() {
-                Ok(guard) => guard,
-                Err(poisoned) => {
-                    eprintln!("Token buffer lock poisoned; attempting recovery.");
-                    let mut guard = poisoned.into_inner();
-                    guard.tokens.clear();
-                    guard
-                }
-            };
-            buffer.push(ch);
+    /// Report the moves still held back at the end of the input and save
+    /// the statistics
+    fn finish(mut self) {
+        self.flush();
+        if let Some((stats, _)) = &self.stats {
+            stats.save();
         }
+    }
+}
+
+impl TokenHandler for TokenPrinter {
+    fn token(&mut self, key: &Symbol, token_name: &str) -> StepEvent {
+        // Keys of other players stand for the player 1 keys of the moves
+        let (player, key) = self
+            .grammar
+            .resolve_key(key.as_str())
+            .map_or((1, key.clone()), |(player, key)| (player, key.clone()));
+        let label = self.label(player);
 
         // Print the token as it's pressed (echo input)
-        print!("[{}]", token_name);
+        print!("[{}{}]", label, token_name);
 
         // Process token through DFA
-        let mut state = match current_state_clone.lock() {
-            Ok(guard) => guard,
-            Err(poisoned) => {
-                eprintln!("State lock poisoned; resetting DFA state.");
-                let mut guard = poisoned.into_inner();
-                *guard = dfa.start_state().clone();
-                guard
+        let event = self.recognizers[player - 1].feed_on(
+            &self.dfa,
+            key,
+            token_name,
+            &self.config,
+            &self.clock,
+        );
+
+        if event.reset || !event.matches.is_empty() {
+            println!(); // New line after token
+            if let Some(miss) = &event.near_miss {
+                let history = self.recognizers[player - 1].history();
+                println!(
+                    "{}Missed {} (after {})",
+                    label,
+                    miss.describe(&self.grammar),
+                    miss.entered(history).join(", ")
+                );
             }
-        };
-        let (next_state, matches) = dfa.step(&state, ch, token_name, &config);
+            for ((move_name, metadata), span) in
+                event.matches.iter().zip(&event.metadata).zip(&event.spans)
+            {
+                self.print_match(player, move_name, Some(metadata));
+                self.print_lenient_inputs(player, move_name, span.clone());
+            }
+        } else {
+            // Continue on same line if no match
+            print!(", ");
+        }
 
-        if let Some(next) = next_state {
-            // Valid transition - clone only when we need to update state
-            *state = next.clone();
+        if let Some((stats, trackers)) = &mut self.stats {
+            for (m, attempt) in trackers[player - 1].observe(&self.dfa, &event, self.clock.now()) {
+                stats.record(m, &attempt);
+            }
+        }
+        event
+    }
 
-            // Print matched moves
-            if !matches.is_empty() {
-                println!(); // New line after token
-                for move_name in matches {
-                    println!("{} !!", move_name);
-                }
-            } else {
-                // Continue on same line if no match
-                print!(", ");
+    fn idle(&mut self, now: Instant) -> Vec<(usize, String)> {
+        let mut released = Vec::new();
+        for recognizer in &mut self.recognizers {
+            let player = recognizer.player();
+            for name in recognizer.poll(&self.dfa, &self.config, now) {
+                released.push((player, name));
             }
-        } else {
-            // No valid transition - reset to start
-            println!(); // New line
-            *state = dfa.start_state().clone();
-
-            // Try from start state with this token
-            let (next_state2, matches2) = dfa.step(&state, ch, token_name, &config);
-            if let Some(next2) = next_state2 {
-                *state = next2.clone();
-                if !matches2.is_empty() {
-                    for move_name in matches2 {
-                        println!("{} !!", move_name);
-                    }
-                }
+        }
+        self.print_released(&released);
+        released
+    }
+
+    fn reload(&mut self, now: Instant) -> Option<(Grammar, DFA)> {
+        match self.watcher.as_mut()?.poll(now)? {
+            Ok((grammar, dfa)) => {
+                println!();
+                println!(
+                    "Grammar reloaded: {} key mappings, {} moves",
+                    grammar.mappings.len(),
+                    grammar.moves.len()
+                );
+                self.swap(grammar.clone(), dfa.clone());
+                Some((grammar, dfa))
+            }
+            Err(e) => {
+                eprintln!();
+                eprintln!("Grammar not reloaded, keeping the previous one: {}", e);
+                None
             }
         }
-    };
+    }
+}
+
+/// Recognizer settings from the command line and the timing windows of the
+/// grammar; `--window` wins over the window of the grammar. Windows given in
+/// frames last that many ticks at `fps`.
+fn dfa_config(grammar: &Grammar, debug: bool, matching: MatchOptions, fps: u32) -> DFAConfig {
+    DFAConfig {
+        debug,
+        policy: matching.policy,
+        continuation_window: matching
+            .window
+            .or(grammar.leniency.window)
+            .map_or(DEFAULT_CONTINUATION_WINDOW, |window| window.duration(fps)),
+        buffer_window: grammar.leniency.buffer.map(|window| window.duration(fps)),
+    }
+}
+
+fn run(options: RunOptions) -> Result<(), CliError> {
+    let theme = load_theme(options.theme.as_deref())?;
+    // The watcher also finds the included files, so edits to any of them are
+    // picked up while running
+    let mut watcher = GrammarWatcher::new(&options.grammar);
+    let (grammar, dfa) = watcher
+        .load()
+        .map_err(|e| CliError::new(EXIT_GRAMMAR, format!("Error parsing grammar file: {}", e)))?;
+
+    // Display key mappings automatically derived from grammar
+    grammar.display_key_mappings();
+
+    if grammar.moves.is_empty() {
+        eprintln!(
+            "Warning: No moves defined in grammar file. The DFA will not recognize any combos."
+        );
+        eprintln!("Add move definitions like: Move Name: k e y s");
+    }
+
+    // Headless rendering: draw the initial overlay to a file instead of a window
+    if let Some(path) = options.snapshot {
+        let model = UiModel::new(dfa.start_state()).with_players(grammar.player_count());
+        ui::dump_frame_bmp(&path, &theme, &grammar, &dfa, &model, Instant::now())
+            .map_err(|e| CliError::new(EXIT_IO, format!("Error rendering snapshot: {}", e)))?;
+        println!("Frame written to {}", path.display());
+        return Ok(());
+    }
+
+    let clock = FrameClock::new(options.fps.unwrap_or(DEFAULT_FPS));
+    let mut printer = TokenPrinter::new(
+        grammar.clone(),
+        dfa.clone(),
+        options.debug,
+        options.matching,
+        clock.clone(),
+    )
+    .with_stats(StatsSession::open(&options.stats, &options.grammar))
+    .with_watcher(watcher);
 
     // Run the appropriate input mode based on GUI flag
-    let result = if gui_mode {
+    let result = if options.gui {
         println!("Starting GUI mode (SDL window)...");
-        run_input_loop(&grammar, process_token)
+        run_input_loop(&grammar, &dfa, &theme, &clock, &mut printer)
     } else {
         println!("Starting console mode (text input)...");
-        run_console_mode(&grammar, process_token)
+        run_console_mode(&grammar, &mut printer)
+    };
+    printer.finish();
+    result.map_err(|e| CliError::new(EXIT_RUNTIME, format!("Error running input loop: {}", e)))?;
+
+    println!("\nExiting...");
+    Ok(())
+}
+
+/// Validate a grammar and report what it defines. Problems that do not stop
+/// the grammar from loading are reported as warnings.
+fn check(path: &Path) -> Result<(), CliError> {
+    let (grammar, dfa) = load_grammar(path)?;
+
+    let mut warnings = Vec::new();
+    if grammar.moves.is_empty() {
+        warnings.push("no moves defined".to_string());
+    }
+    for m in &grammar.moves {
+        if m.sequence.is_empty() {
+            warnings.push(format!("move '{}' has no inputs", m.name));
+        }
+        for key in &m.sequence {
+            if grammar.get_token_for_key(key.as_str()).is_none() {
+                warnings.push(format!("move '{}' uses unmapped key '{}'", m.name, key));
+            }
+        }
+    }
+    let leniency = &grammar.leniency;
+    for (rule, keys) in [
+        ("ignore", &leniency.ignore),
+        ("optional", &leniency.optional),
+    ] {
+        for key in keys {
+            if !grammar.mappings.contains_key(key) {
+                warnings.push(format!("{} rule uses unmapped key '{}'", rule, key));
+            }
+        }
+    }
+    for key in leniency.ignore.intersection(&leniency.optional) {
+        warnings.push(format!("key '{}' is both ignored and optional", key));
+    }
+
+    let players = match grammar.player_count() {
+        1 => String::new(),
+        n => format!(", {} players", n),
     };
+    println!(
+        "{}: OK - {} key mappings{}, {} moves, {} states, {} transitions",
+        path.display(),
+        grammar.mappings.len(),
+        players,
+        grammar.moves.len(),
+        dfa.states().len(),
+        dfa.transitions().count()
+    );
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    Ok(())
+}
 
-    if let Err(e) = result {
-        eprintln!("Error running input loop: {}", e);
-        process::exit(1);
+fn drill(options: DrillOptions) -> Result<(), CliError> {
+    let theme = load_theme(options.theme.as_deref())?;
+    let (grammar, _) = load_grammar(&options.grammar)?;
+
+    let moves: Vec<MoveDef> = grammar
+        .moves
+        .iter()
+        .filter(|m| match &options.character {
+            Some(wanted) => m
+                .character()
+                .is_some_and(|c| c.eq_ignore_ascii_case(wanted)),
+            None => true,
+        })
+        .cloned()
+        .collect();
+    let mut drill = Drill::new(
+        moves,
+        options.order,
+        options.seed.unwrap_or_else(drill::time_seed),
+    )
+    .with_count(options.count);
+    if drill.moves().is_empty() {
+        return Err(CliError::usage(match &options.character {
+            Some(character) => format!("no moves to drill for character '{}'", character),
+            None => "the grammar defines no moves to drill".to_string(),
+        }));
     }
 
-    println!("\nExiting...");
+    let result = if options.gui {
+        let time_limit = Duration::from_millis(options.time_limit_ms);
+        let clock = FrameClock::new(DEFAULT_FPS);
+        run_drill_loop(&grammar, &theme, &mut drill, time_limit, &clock)
+    } else {
+        run_drill_console(&grammar, &mut drill, io::stdin().lock())
+    };
+    if let Some(mut stats) = StatsSession::open(&options.stats, &options.grammar) {
+        for (m, result) in drill.results() {
+            stats.record(m, &AttemptRecord::from(result));
+        }
+        stats.save();
+    }
+    result.map_err(|e| CliError::new(EXIT_RUNTIME, format!("Error running drill: {}", e)))?;
+
+    println!("{}", drill.summary());
+    Ok(())
+}
+
+/// Write a generated grammar, and the key presses to test it with
+fn generate(options: &GenerateOptions) -> Result<(), CliError> {
+    let config = &options.config;
+    let grammar = generate_grammar(config).map_err(CliError::usage)?;
+    write_output(&options.grammar, &grammar.to_gmr())?;
+    println!(
+        "Generated {} moves of {} characters into {}",
+        grammar.moves.len(),
+        config.characters,
+        options.grammar.display()
+    );
+
+    if options.input.is_none() && options.expected.is_none() {
+        return Ok(());
+    }
+    // Another seed than the grammar's, so that both do not follow each other
+    let input = generate_input(&grammar, options.presses, config.seed.wrapping_add(1))
+        .map_err(|e| CliError::new(EXIT_RUNTIME, e))?;
+    if let Some(path) = &options.input {
+        write_output(path, &input.text)?;
+        println!(
+            "Wrote {} moves played into {}",
+            options.presses,
+            path.display()
+        );
+    }
+    if let Some(path) = &options.expected {
+        let mut expected = input.expected.join("\n");
+        expected.push('\n');
+        write_output(path, &expected)?;
+        println!(
+            "Wrote {} expected matches into {}",
+            input.expected.len(),
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Rewrite a `.gmr` file in canonical form, after making sure that the
+/// result defines the same grammar
+fn format_grammar(path: &Path, check: bool, output: Option<&Path>) -> Result<(), CliError> {
+    if GrammarFormat::from_path(path) != GrammarFormat::Gmr {
+        return Err(CliError::usage(format!(
+            "'{}' is a grammar document; 'fmt' only formats .gmr files",
+            path.display()
+        )));
+    }
+    let text = fs::read_to_string(path).map_err(|e| {
+        CliError::new(
+            EXIT_IO,
+            format!("Failed to read grammar file '{}': {}", path.display(), e),
+        )
+    })?;
+    let grammar_error =
+        |e: String| CliError::new(EXIT_GRAMMAR, format!("Error parsing grammar file: {}", e));
+    let formatted = format::format_gmr(&text).map_err(grammar_error)?;
+    let before = parse_grammar_text(&text, path).map_err(grammar_error)?;
+    if parse_grammar_text(&formatted, path).as_ref() != Ok(&before) {
+        return Err(CliError::new(
+            EXIT_RUNTIME,
+            format!(
+                "Formatting '{}' would change its grammar; file left alone",
+                path.display()
+            ),
+        ));
+    }
+
+    if check {
+        if formatted != text {
+            return Err(CliError::new(
+                EXIT_UNFORMATTED,
+                format!("'{}' is not formatted", path.display()),
+            ));
+        }
+        println!("{}: formatted", path.display());
+    } else if let Some(output) = output {
+        write_output(output, &formatted)?;
+        println!("Formatted {} into {}", path.display(), output.display());
+    } else if formatted == text {
+        println!("{}: already formatted", path.display());
+    } else {
+        write_output(path, &formatted)?;
+        println!("Formatted {}", path.display());
+    }
+    Ok(())
+}
+
+/// Print the practice statistics of a grammar, or forget them
+fn show_stats(
+    grammar_path: &Path,
+    character: Option<&str>,
+    reset: bool,
+    file: Option<PathBuf>,
+) -> Result<(), CliError> {
+    let path = file.or_else(stats::default_path).ok_or_else(|| {
+        CliError::new(
+            EXIT_IO,
+            "no home directory: pass --stats-file or set FT_ALITY_STATS",
+        )
+    })?;
+    let mut store = StatsStore::load(&path).map_err(|e| CliError::new(EXIT_IO, e))?;
+    let key = stats::grammar_key(grammar_path);
+
+    if reset {
+        let forgotten = store.reset(&key, character);
+        store.save(&path).map_err(|e| CliError::new(EXIT_IO, e))?;
+        println!("Forgot the statistics of {} move(s) of {}", forgotten, key);
+        return Ok(());
+    }
+
+    // The grammar names the tokens of the most missed steps
+    let (grammar, _) = load_grammar(grammar_path)?;
+    let characters = store
+        .grammar(&key)
+        .into_iter()
+        .flatten()
+        .filter(|(name, _)| character.is_none_or(|wanted| name.eq_ignore_ascii_case(wanted)))
+        .collect::<Vec<_>>();
+    let width = characters
+        .iter()
+        .flat_map(|(_, moves)| moves.keys())
+        .map(|m| m.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut shown = 0;
+    for (name, moves) in characters {
+        println!("{}:", name);
+        for (move_name, totals) in moves {
+            let average = totals
+                .average_time()
+                .map(|d| format!("{}ms", d.as_millis()))
+                .unwrap_or_else(|| "-".to_string());
+            let mistake = match totals.most_common_mistake() {
+                Some((step, count)) => {
+                    let token = grammar
+                        .moves
+                        .iter()
+                        .find(|m| {
+                            m.short_name() == move_name
+                                && m.character().unwrap_or(stats::NO_CHARACTER) == name
+                        })
+                        .and_then(|m| m.sequence.get(step))
+                        .map(|key| {
+                            format!(" ({})", grammar.token_names(std::slice::from_ref(key))[0])
+                        })
+                        .unwrap_or_default();
+                    format!("step {}{} x{}", step + 1, token, count)
+                }
+                None => "-".to_string(),
+            };
+            println!(
+                "  {:<width$}  {:>3}/{:<3} completed  {:>5.1}%  avg {:<7} most missed: {}",
+                move_name,
+                totals.completions,
+                totals.attempts,
+                totals.completion_rate() * 100.0,
+                average,
+                mistake,
+            );
+            shown += 1;
+        }
+    }
+
+    if shown == 0 {
+        match character {
+            Some(wanted) => println!("No statistics for character '{}' of {} yet.", wanted, key),
+            None => println!("No statistics for {} yet.", key),
+        }
+    }
+    Ok(())
+}
+
+fn list_moves(grammar: &Grammar, character: Option<&str>) -> Result<(), CliError> {
+    let groups = grammar.moves_by_character();
+    let mut listed = 0;
+    for (name, moves) in &groups {
+        if let Some(wanted) = character {
+            if !name.is_some_and(|n| n.eq_ignore_ascii_case(wanted)) {
+                continue;
+            }
+        }
+        println!("{}:", name.unwrap_or("Other"));
+        for m in moves {
+            let tokens = grammar.token_names(&m.sequence);
+            let keys: Vec<String> = m.sequence.iter().map(|k| k.to_string()).collect();
+            println!(
+                "  {}: {} ({})",
+                m.short_name(),
+                tokens.join(", "),
+                keys.join(" ")
+            );
+            let summary = m.metadata.summary();
+            if !summary.is_empty() {
+                println!("    {}", summary);
+            }
+            if let Some(notes) = &m.metadata.notes {
+                println!("    {}", notes);
+            }
+            listed += 1;
+        }
+    }
+
+    if listed == 0 {
+        if let Some(wanted) = character {
+            return Err(CliError::usage(format!(
+                "no moves for character '{}'",
+                wanted
+            )));
+        }
+        println!("No moves defined.");
+    }
+    Ok(())
 }
@@ -0,0 +1,866 @@
+use crate::tools::clock::TimingWindow;
+use crate::tools::dfa::MatchPolicy;
+use crate::tools::drill::DrillOrder;
+use crate::tools::generate::GeneratorConfig;
+use std::fmt;
+use std::path::PathBuf;
+
+/// Process exit codes, one per failure class
+pub const EXIT_OK: u8 = 0;
+/// Runtime failure while running (SDL, terminal I/O, ...)
+pub const EXIT_RUNTIME: u8 = 1;
+/// Invalid command line
+pub const EXIT_USAGE: u8 = 2;
+/// The grammar file could not be read or parsed
+pub const EXIT_GRAMMAR: u8 = 3;
+/// An input or output file could not be read or written
+pub const EXIT_IO: u8 = 4;
+/// `fmt --check` found a file that is not in canonical form
+pub const EXIT_UNFORMATTED: u8 = 5;
+
+/// An error that ends the program with a specific exit code
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CliError {
+    pub code: u8,
+    pub message: String,
+}
+
+impl CliError {
+    pub fn new(code: u8, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+        }
+    }
+
+    pub fn usage(message: impl Into<String>) -> Self {
+        Self::new(EXIT_USAGE, message)
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Options of the interactive `run` subcommand
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RunOptions {
+    pub grammar: PathBuf,
+    pub gui: bool,
+    pub debug: bool,
+    pub matching: MatchOptions,
+    /// Preset name or theme file
+    pub theme: Option<String>,
+    /// Render one frame to this BMP file instead of opening a window
+    pub snapshot: Option<PathBuf>,
+    pub stats: StatsOptions,
+    /// Game ticks per second, [`crate::tools::clock::DEFAULT_FPS`] when `None`
+    pub fps: Option<u32>,
+}
+
+/// How ambiguous moves are reported
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MatchOptions {
+    pub policy: MatchPolicy,
+    /// Continuation window, the library default when `None`
+    pub window: Option<TimingWindow>,
+}
+
+/// Where practice statistics are recorded
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct StatsOptions {
+    /// Statistics file, the per-user default when `None`
+    pub file: Option<PathBuf>,
+    /// Do not record statistics of this session
+    pub disabled: bool,
+}
+
+/// Options of the `drill` subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrillOptions {
+    pub grammar: PathBuf,
+    pub order: DrillOrder,
+    /// Number of prompts in the session
+    pub count: usize,
+    /// Only drill moves of this character
+    pub character: Option<String>,
+    /// Fixed random seed, for repeatable sessions
+    pub seed: Option<u64>,
+    /// Milliseconds an attempt may take in the SDL window
+    pub time_limit_ms: u64,
+    pub gui: bool,
+    pub theme: Option<String>,
+    pub stats: StatsOptions,
+}
+
+/// Default number of prompts of a drill session
+pub const DEFAULT_DRILL_COUNT: usize = 10;
+/// Default time limit of one drill attempt in the SDL window
+pub const DEFAULT_DRILL_TIME_LIMIT_MS: u64 = 3000;
+
+/// Default number of moves played by `generate --input`
+pub const DEFAULT_GENERATED_PRESSES: usize = 1000;
+
+/// Options of the `generate` subcommand
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenerateOptions {
+    /// Grammar file to write
+    pub grammar: PathBuf,
+    pub config: GeneratorConfig,
+    /// Key presses playing moves of the grammar
+    pub input: Option<PathBuf>,
+    /// Moves recognized from the key presses, one per line
+    pub expected: Option<PathBuf>,
+    /// Number of moves played
+    pub presses: usize,
+}
+
+/// Output format of the `export` subcommand
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExportFormat {
+    /// Graphviz digraph
+    #[default]
+    Dot,
+    /// Plain-text transition table
+    Table,
+}
+
+/// A fully validated command line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Run(RunOptions),
+    Check {
+        grammar: PathBuf,
+    },
+    Export {
+        grammar: PathBuf,
+        format: ExportFormat,
+        /// Standard output when `None`
+        output: Option<PathBuf>,
+    },
+    Compile {
+        grammar: PathBuf,
+        /// Defaults to the grammar path with a `.dfa` extension
+        output: Option<PathBuf>,
+    },
+    Replay {
+        grammar: PathBuf,
+        /// Key presses to feed; standard input when `None`
+        input: Option<PathBuf>,
+        debug: bool,
+        matching: MatchOptions,
+    },
+    ListMoves {
+        grammar: PathBuf,
+        character: Option<String>,
+    },
+    Drill(DrillOptions),
+    Generate(GenerateOptions),
+    Stats {
+        grammar: PathBuf,
+        /// Only this character
+        character: Option<String>,
+        /// Forget the statistics instead of printing them
+        reset: bool,
+        file: Option<PathBuf>,
+    },
+    Fmt {
+        grammar: PathBuf,
+        /// Only report whether the file is formatted
+        check: bool,
+        /// Rewrite the grammar file itself when `None`
+        output: Option<PathBuf>,
+    },
+    /// Print the general help, or the help of one subcommand
+    Help(Option<String>),
+    Version,
+}
+
+/// Subcommands with their one-line description, in help order
+pub const SUBCOMMANDS: [(&str, &str); 10] = [
+    ("run", "Recognize moves interactively (default)"),
+    ("check", "Validate a grammar file and report its size"),
+    (
+        "export",
+        "Write the automaton as Graphviz DOT or a text table",
+    ),
+    ("compile", "Write the transition table next to the grammar"),
+    ("replay", "Feed recorded key presses from a file or stdin"),
+    (
+        "list-moves",
+        "Print the moves of a grammar grouped by character",
+    ),
+    ("drill", "Prompt moves one at a time and score each attempt"),
+    (
+        "stats",
+        "Print or reset the practice statistics of a grammar",
+    ),
+    ("fmt", "Rewrite a .gmr file in canonical form"),
+    (
+        "generate",
+        "Write a large random grammar and inputs to test it with",
+    ),
+];
+
+/// An option accepted by a subcommand
+struct OptionSpec {
+    long: &'static str,
+    short: Option<char>,
+    /// Placeholder shown in help; flags take no value
+    value: Option<&'static str>,
+    help: &'static str,
+}
+
+const fn flag(long: &'static str, help: &'static str) -> OptionSpec {
+    OptionSpec {
+        long,
+        short: None,
+        value: None,
+        help,
+    }
+}
+
+const fn valued(
+    long: &'static str,
+    short: Option<char>,
+    value: &'static str,
+    help: &'static str,
+) -> OptionSpec {
+    OptionSpec {
+        long,
+        short,
+        value: Some(value),
+        help,
+    }
+}
+
+const STATS_FILE_OPTION: OptionSpec = valued(
+    "stats-file",
+    None,
+    "file.toml",
+    "Statistics file (default: $FT_ALITY_STATS or ~/.local/share/ft_ality/stats.toml)",
+);
+const NO_STATS_OPTION: OptionSpec = flag("no-stats", "Do not record practice statistics");
+const MATCH_OPTION: OptionSpec = valued(
+    "match",
+    None,
+    "all|longest|priority",
+    "Which of several matching moves to report (default: all)",
+);
+const WINDOW_OPTION: OptionSpec = valued(
+    "window",
+    None,
+    "ms|Nf",
+    "Time a held-back move waits for a longer one, in ms or frames (default: 250)",
+);
+
+/// Highest accepted `--fps`
+pub const MAX_FPS: u32 = 1000;
+
+const RUN_OPTIONS: [OptionSpec; 9] = [
+    flag("gui", "Open the SDL training window"),
+    flag("debug", "Trace every state transition"),
+    MATCH_OPTION,
+    WINDOW_OPTION,
+    valued(
+        "theme",
+        None,
+        "name|file.toml",
+        "GUI theme: dark, light, high-contrast, colorblind or a TOML file",
+    ),
+    valued(
+        "snapshot",
+        None,
+        "file.bmp",
+        "Render one GUI frame to a BMP file and exit",
+    ),
+    valued(
+        "fps",
+        None,
+        "n",
+        "Game ticks per second, for pacing and frame windows (default: 60)",
+    ),
+    STATS_FILE_OPTION,
+    NO_STATS_OPTION,
+];
+const EXPORT_OPTIONS: [OptionSpec; 2] = [
+    valued(
+        "format",
+        Some('f'),
+        "dot|table",
+        "Output format (default: dot)",
+    ),
+    valued(
+        "output",
+        Some('o'),
+        "file",
+        "Write to this file instead of stdout",
+    ),
+];
+const COMPILE_OPTIONS: [OptionSpec; 1] = [valued(
+    "output",
+    Some('o'),
+    "file",
+    "Output file (default: <grammar>.dfa)",
+)];
+const REPLAY_OPTIONS: [OptionSpec; 4] = [
+    valued(
+        "input",
+        Some('i'),
+        "file",
+        "File of key presses (default: stdin)",
+    ),
+    flag("debug", "Trace every state transition"),
+    MATCH_OPTION,
+    WINDOW_OPTION,
+];
+const LIST_MOVES_OPTIONS: [OptionSpec; 1] = [valued(
+    "character",
+    Some('c'),
+    "name",
+    "Only list moves of this character",
+)];
+
+const DRILL_OPTIONS: [OptionSpec; 9] = [
+    valued(
+        "order",
+        None,
+        "random|sequential|weighted",
+        "How moves are picked (default: random)",
+    ),
+    valued(
+        "count",
+        Some('n'),
+        "prompts",
+        "Number of prompts (default: 10)",
+    ),
+    valued(
+        "character",
+        Some('c'),
+        "name",
+        "Only drill moves of this character",
+    ),
+    valued(
+        "seed",
+        None,
+        "number",
+        "Random seed, for repeatable sessions",
+    ),
+    valued(
+        "time-limit",
+        None,
+        "ms",
+        "Time allowed per attempt in the window (default: 3000)",
+    ),
+    flag("gui", "Drill in the SDL training window"),
+    valued(
+        "theme",
+        None,
+        "name|file.toml",
+        "GUI theme: dark, light, high-contrast, colorblind or a TOML file",
+    ),
+    STATS_FILE_OPTION,
+    NO_STATS_OPTION,
+];
+const STATS_OPTIONS: [OptionSpec; 3] = [
+    valued(
+        "character",
+        Some('c'),
+        "name",
+        "Only this character's moves",
+    ),
+    flag("reset", "Forget the statistics instead of printing them"),
+    STATS_FILE_OPTION,
+];
+
+const FMT_OPTIONS: [OptionSpec; 2] = [
+    flag(
+        "check",
+        "Only check that the file is formatted, exit with 5 if not",
+    ),
+    valued(
+        "output",
+        Some('o'),
+        "file",
+        "Write to this file instead of rewriting the grammar",
+    ),
+];
+
+const GENERATE_OPTIONS: [OptionSpec; 9] = [
+    valued(
+        "characters",
+        None,
+        "n",
+        "Number of characters (default: 30)",
+    ),
+    valued("moves", None, "n", "Moves of each character (default: 20)"),
+    valued(
+        "keys",
+        None,
+        "n",
+        "Keys the moves are made of, up to 36 (default: 12)",
+    ),
+    valued(
+        "length",
+        None,
+        "min-max",
+        "Keys in a move, or a range of them (default: 2-8)",
+    ),
+    valued(
+        "shared",
+        None,
+        "percent",
+        "Moves starting like an earlier move (default: 30)",
+    ),
+    valued("seed", None, "number", "Random seed (default: 1)"),
+    valued(
+        "input",
+        Some('i'),
+        "file",
+        "Also write key presses playing random moves",
+    ),
+    valued(
+        "expected",
+        None,
+        "file",
+        "Also write the moves recognized from those key presses",
+    ),
+    valued(
+        "presses",
+        Some('n'),
+        "moves",
+        "Moves played in the key presses (default: 1000)",
+    ),
+];
+
+fn options_of(command: &str) -> &'static [OptionSpec] {
+    match command {
+        "run" => &RUN_OPTIONS,
+        "export" => &EXPORT_OPTIONS,
+        "compile" => &COMPILE_OPTIONS,
+        "replay" => &REPLAY_OPTIONS,
+        "list-moves" => &LIST_MOVES_OPTIONS,
+        "drill" => &DRILL_OPTIONS,
+        "stats" => &STATS_OPTIONS,
+        "fmt" => &FMT_OPTIONS,
+        "generate" => &GENERATE_OPTIONS,
+        _ => &[],
+    }
+}
+
+/// Usage line of a subcommand, e.g. `run <grammar.gmr> [--gui] ...`
+fn synopsis(command: &str) -> String {
+    let mut line = format!("{} <grammar.gmr>", command);
+    for option in options_of(command) {
+        match option.value {
+            Some(value) => line.push_str(&format!(" [--{} <{}>]", option.long, value)),
+            None => line.push_str(&format!(" [--{}]", option.long)),
+        }
+    }
+    line
+}
+
+/// General help text
+pub fn usage(program: &str) -> String {
+    let mut text = format!(
+        "ft_ality - fighting game training mode\n\n\
+         Usage: {program} <command> <grammar.gmr> [OPTIONS]\n\
+         \x20      {program} <grammar.gmr> [OPTIONS]        (same as 'run')\n\nCommands:\n"
+    );
+    for (name, about) in SUBCOMMANDS {
+        text.push_str(&format!("  {:<12}{}\n", name, about));
+    }
+    text.push_str(&format!(
+        "\nGrammars are .gmr files, or TOML/JSON documents ending in .toml or .json.\n\n\
+         Global options:\n  -h, --help     Print help (also: {program} <command> --help)\n  \
+         -V, --version  Print version\n\n\
+         Exit codes:\n  {EXIT_OK}  success\n  {EXIT_RUNTIME}  runtime error\n  \
+         {EXIT_USAGE}  invalid command line\n  {EXIT_GRAMMAR}  invalid grammar\n  \
+         {EXIT_IO}  file could not be read or written\n  \
+         {EXIT_UNFORMATTED}  file would be reformatted (fmt --check)\n\n\
+         Examples:\n  {program} run grammars/mk9_with_moves.gmr --gui\n  \
+         {program} check grammars/mk9.gmr\n  \
+         {program} export grammars/mk9_with_moves.gmr -o mk9.dot\n  \
+         echo 'ssol' | {program} replay grammars/mk9_with_moves.gmr\n  \
+         {program} drill grammars/mk9_with_moves.gmr --order weighted --count 20\n  \
+         {program} stats grammars/mk9_with_moves.gmr --character ermac\n  \
+         {program} fmt grammars/mk9_with_moves.gmr --check\n  \
+         {program} generate roster.gmr --characters 60 -i roster.txt --expected roster.out\n"
+    ));
+    text
+}
+
+/// Help text of one subcommand
+pub fn command_usage(program: &str, command: &str) -> Option<String> {
+    let (_, about) = SUBCOMMANDS.iter().find(|(name, _)| *name == command)?;
+    let mut text = format!("{}\n\nUsage: {} {}\n", about, program, synopsis(command));
+    let options = options_of(command);
+    if !options.is_empty() {
+        text.push_str("\nOptions:\n");
+        let flags: Vec<String> = options
+            .iter()
+            .map(|option| {
+                let mut flag = match option.short {
+                    Some(short) => format!("-{}, --{}", short, option.long),
+                    None => format!("    --{}", option.long),
+                };
+                if let Some(value) = option.value {
+                    flag.push_str(&format!(" <{}>", value));
+                }
+                flag
+            })
+            .collect();
+        let width = flags.iter().map(String::len).max().unwrap_or(0) + 2;
+        for (flag, option) in flags.iter().zip(options) {
+            text.push_str(&format!("  {:<width$}{}\n", flag, option.help));
+        }
+    }
+    Some(text)
+}
+
+fn parse_number(option: &str, value: &str) -> Result<u64, CliError> {
+    value.parse().map_err(|_| {
+        CliError::usage(format!(
+            "option '--{}' expects a number, got '{}'",
+            option, value
+        ))
+    })
+}
+
+/// Sequence lengths like `2-8`, or `5` for a single length
+fn parse_lengths(value: &str) -> Result<(usize, usize), CliError> {
+    let (min, max) = value.split_once('-').unwrap_or((value, value));
+    Ok((
+        parse_number("length", min)? as usize,
+        parse_number("length", max)? as usize,
+    ))
+}
+
+/// Edit distance, used to suggest the option or command the user meant
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, &cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = if ca == cb {
+                previous
+            } else {
+                1 + previous.min(row[j]).min(row[j + 1])
+            };
+            previous = current;
+        }
+    }
+    row[b.len()]
+}
+
+fn suggestion<'a>(given: &str, prefix: &str, candidates: impl Iterator<Item = &'a str>) -> String {
+    candidates
+        .filter(|c| edit_distance(given, c) <= 2)
+        .min_by_key(|c| edit_distance(given, c))
+        .map(|c| format!(" (did you mean '{}{}'?)", prefix, c))
+        .unwrap_or_default()
+}
+
+/// Parse the arguments that follow the program name
+pub fn parse_args<I, S>(args: I) -> Result<Command, CliError>
+where
+    I: IntoIterator<Item = S>,
+    S: Into<String>,
+{
+    let args: Vec<String> = args.into_iter().map(Into::into).collect();
+
+    let Some(first) = args.first() else {
+        return Err(CliError::usage("missing grammar file"));
+    };
+    match first.as_str() {
+        "-h" | "--help" => return Ok(Command::Help(None)),
+        "-V" | "--version" => return Ok(Command::Version),
+        "help" => return Ok(Command::Help(args.get(1).cloned())),
+        _ => {}
+    }
+
+    // Without a known subcommand the arguments are those of `run`
+    let (command, rest) = match SUBCOMMANDS.iter().find(|(name, _)| name == first) {
+        Some((name, _)) => (*name, &args[1..]),
+        None => {
+            if !first.starts_with('-') && !first.contains('.') && !first.contains('/') {
+                let hint = suggestion(first, "", SUBCOMMANDS.iter().map(|(name, _)| *name));
+                if !hint.is_empty() {
+                    return Err(CliError::usage(format!(
+                        "unknown command '{}'{}",
+                        first, hint
+                    )));
+                }
+            }
+            ("run", &args[..])
+        }
+    };
+
+    let options = options_of(command);
+    let mut positional: Vec<&String> = Vec::new();
+    let mut values: Vec<(&'static str, Option<String>)> = Vec::new();
+    let mut iter = rest.iter();
+    while let Some(arg) = iter.next() {
+        if arg == "-h" || arg == "--help" {
+            return Ok(Command::Help(Some(command.to_string())));
+        }
+        if arg == "-V" || arg == "--version" {
+            return Ok(Command::Version);
+        }
+        if arg == "--" {
+            positional.extend(iter.by_ref());
+            break;
+        }
+
+        let (spec, inline_value) = if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let spec = options.iter().find(|o| o.long == name).ok_or_else(|| {
+                CliError::usage(format!(
+                    "unknown option '--{}' for '{}'{}",
+                    name,
+                    command,
+                    suggestion(name, "--", options.iter().map(|o| o.long))
+                ))
+            })?;
+            (spec, inline)
+        } else if arg.len() == 2 && arg.starts_with('-') && arg != "-" {
+            let short = arg.chars().nth(1);
+            let spec = options
+                .iter()
+                .find(|o| o.short.is_some() && o.short == short)
+                .ok_or_else(|| {
+                    CliError::usage(format!("unknown option '{}' for '{}'", arg, command))
+                })?;
+            (spec, None)
+        } else if arg.starts_with('-') && arg != "-" {
+            return Err(CliError::usage(format!(
+                "unknown option '{}' for '{}'",
+                arg, command
+            )));
+        } else {
+            positional.push(arg);
+            continue;
+        };
+
+        let long = spec.long;
+        let value = if spec.value.is_some() {
+            match inline_value {
+                Some(value) => Some(value),
+                None => Some(iter.next().cloned().ok_or_else(|| {
+                    CliError::usage(format!("option '--{}' requires a value", long))
+                })?),
+            }
+        } else if inline_value.is_some() {
+            return Err(CliError::usage(format!(
+                "option '--{}' does not take a value",
+                long
+            )));
+        } else {
+            None
+        };
+        if values.iter().any(|(l, _)| *l == long) {
+            return Err(CliError::usage(format!(
+                "option '--{}' given more than once",
+                long
+            )));
+        }
+        values.push((long, value));
+    }
+
+    let grammar = match positional.as_slice() {
+        [grammar] => PathBuf::from(grammar.as_str()),
+        [] => {
+            return Err(CliError::usage(format!(
+                "missing grammar file for '{}'",
+                command
+            )))
+        }
+        [_, extra, ..] => {
+            return Err(CliError::usage(format!(
+                "unexpected argument '{}' for '{}'",
+                extra, command
+            )))
+        }
+    };
+
+    let flag = |name: &str| values.iter().any(|(l, _)| *l == name);
+    let value = |name: &str| {
+        values
+            .iter()
+            .find(|(l, _)| *l == name)
+            .and_then(|(_, v)| v.clone())
+    };
+    let stats = StatsOptions {
+        file: value("stats-file").map(PathBuf::from),
+        disabled: flag("no-stats"),
+    };
+    let matching = MatchOptions {
+        policy: match value("match") {
+            None => MatchPolicy::default(),
+            Some(name) => MatchPolicy::from_name(&name).ok_or_else(|| {
+                CliError::usage(format!(
+                    "unknown match policy '{}': expected all, longest or priority",
+                    name
+                ))
+            })?,
+        },
+        window: value("window")
+            .map(|window| {
+                TimingWindow::parse(&window).ok_or_else(|| {
+                    CliError::usage(format!(
+                        "option '--window' expects milliseconds or frames like 15f, got '{}'",
+                        window
+                    ))
+                })
+            })
+            .transpose()?,
+    };
+    if stats.disabled && stats.file.is_some() {
+        return Err(CliError::usage(
+            "options '--stats-file' and '--no-stats' cannot be used together",
+        ));
+    }
+
+    Ok(match command {
+        "run" => Command::Run(RunOptions {
+            grammar,
+            gui: flag("gui"),
+            debug: flag("debug"),
+            matching,
+            theme: value("theme"),
+            snapshot: value("snapshot").map(PathBuf::from),
+            stats,
+            fps: match value("fps") {
+                None => None,
+                Some(fps) => match parse_number("fps", &fps)? {
+                    n if (1..=MAX_FPS as u64).contains(&n) => Some(n as u32),
+                    _ => {
+                        return Err(CliError::usage(format!(
+                            "option '--fps' must be from 1 to {}",
+                            MAX_FPS
+                        )))
+                    }
+                },
+            },
+        }),
+        "check" => Command::Check { grammar },
+        "export" => Command::Export {
+            grammar,
+            format: match value("format").as_deref() {
+                None | Some("dot") => ExportFormat::Dot,
+                Some("table") => ExportFormat::Table,
+                Some(other) => {
+                    return Err(CliError::usage(format!(
+                        "unknown export format '{}': expected dot or table",
+                        other
+                    )))
+                }
+            },
+            output: value("output").map(PathBuf::from),
+        },
+        "compile" => Command::Compile {
+            grammar,
+            output: value("output").map(PathBuf::from),
+        },
+        "replay" => Command::Replay {
+            grammar,
+            input: value("input").map(PathBuf::from),
+            debug: flag("debug"),
+            matching,
+        },
+        "list-moves" => Command::ListMoves {
+            grammar,
+            character: value("character"),
+        },
+        "drill" => Command::Drill(DrillOptions {
+            grammar,
+            order: match value("order") {
+                None => DrillOrder::default(),
+                Some(name) => DrillOrder::from_name(&name).ok_or_else(|| {
+                    CliError::usage(format!(
+                        "unknown drill order '{}': expected random, sequential or weighted",
+                        name
+                    ))
+                })?,
+            },
+            count: match value("count") {
+                None => DEFAULT_DRILL_COUNT,
+                Some(count) => match parse_number("count", &count)? {
+                    0 => return Err(CliError::usage("option '--count' must be at least 1")),
+                    n => n as usize,
+                },
+            },
+            character: value("character"),
+            seed: value("seed")
+                .map(|seed| parse_number("seed", &seed))
+                .transpose()?,
+            time_limit_ms: value("time-limit")
+                .map(|ms| parse_number("time-limit", &ms))
+                .transpose()?
+                .unwrap_or(DEFAULT_DRILL_TIME_LIMIT_MS),
+            gui: flag("gui"),
+            theme: value("theme"),
+            stats,
+        }),
+        "stats" => Command::Stats {
+            grammar,
+            character: value("character"),
+            reset: flag("reset"),
+            file: stats.file,
+        },
+        "generate" => {
+            let defaults = GeneratorConfig::default();
+            let number = |name: &str, default: usize| {
+                value(name)
+                    .map(|n| parse_number(name, &n).map(|n| n as usize))
+                    .transpose()
+                    .map(|n| n.unwrap_or(default))
+            };
+            let (min_length, max_length) = match value("length") {
+                Some(lengths) => parse_lengths(&lengths)?,
+                None => (defaults.min_length, defaults.max_length),
+            };
+            let config = GeneratorConfig {
+                characters: number("characters", defaults.characters)?,
+                moves_per_character: number("moves", defaults.moves_per_character)?,
+                keys: number("keys", defaults.keys)?,
+                min_length,
+                max_length,
+                shared_prefixes: number("shared", defaults.shared_prefixes)?,
+                seed: value("seed")
+                    .map(|seed| parse_number("seed", &seed))
+                    .transpose()?
+                    .unwrap_or(defaults.seed),
+            };
+            config.validate().map_err(CliError::usage)?;
+            Command::Generate(GenerateOptions {
+                grammar,
+                config,
+                input: value("input").map(PathBuf::from),
+                expected: value("expected").map(PathBuf::from),
+                presses: number("presses", DEFAULT_GENERATED_PRESSES)?,
+            })
+        }
+        "fmt" => {
+            if flag("check") && value("output").is_some() {
+                return Err(CliError::usage(
+                    "options '--check' and '--output' cannot be used together",
+                ));
+            }
+            Command::Fmt {
+                grammar,
+                check: flag("check"),
+                output: value("output").map(PathBuf::from),
+            }
+        }
+        _ => unreachable!("every subcommand is handled"),
+    })
+}
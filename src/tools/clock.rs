@@ -0,0 +1,193 @@
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Game ticks per second unless configured otherwise
+pub const DEFAULT_FPS: u32 = 60;
+
+/// Number of a game tick, counted from the start of the clock
+pub type Frame = u64;
+
+/// Where a [`FrameClock`] gets the time from
+pub trait TimeSource {
+    fn now(&self) -> Instant;
+    /// Wait until `deadline`; returns at once if it has passed
+    fn sleep_until(&self, deadline: Instant);
+}
+
+/// The system clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealTime;
+
+impl TimeSource for RealTime {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        let now = Instant::now();
+        if deadline > now {
+            thread::sleep(deadline - now);
+        }
+    }
+}
+
+/// A clock that only moves when told to, for tests. Clones share the same
+/// time, and sleeping jumps straight to the deadline.
+#[derive(Debug, Clone)]
+pub struct ManualTime {
+    now: Rc<Cell<Instant>>,
+}
+
+impl ManualTime {
+    pub fn new() -> Self {
+        Self {
+            now: Rc::new(Cell::new(Instant::now())),
+        }
+    }
+
+    pub fn advance(&self, duration: Duration) {
+        self.now.set(self.now.get() + duration);
+    }
+}
+
+impl Default for ManualTime {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TimeSource for ManualTime {
+    fn now(&self) -> Instant {
+        self.now.get()
+    }
+
+    fn sleep_until(&self, deadline: Instant) {
+        if deadline > self.now.get() {
+            self.now.set(deadline);
+        }
+    }
+}
+
+// Rounded up to the nanosecond, so that a tick starts within itself
+fn frames_duration(frames: Frame, fps: u32) -> Duration {
+    let nanos = (frames as u128 * 1_000_000_000).div_ceil(fps as u128);
+    Duration::from_nanos(nanos as u64)
+}
+
+/// Counts game ticks at a fixed rate from the moment it was created, and
+/// paces loops to that rate
+#[derive(Debug, Clone)]
+pub struct FrameClock<S: TimeSource = RealTime> {
+    source: S,
+    fps: u32,
+    start: Instant,
+}
+
+impl FrameClock {
+    /// A clock on the system time at `fps` ticks per second
+    pub fn new(fps: u32) -> Self {
+        Self::with_source(RealTime, fps)
+    }
+}
+
+impl<S: TimeSource> FrameClock<S> {
+    pub fn with_source(source: S, fps: u32) -> Self {
+        let start = source.now();
+        Self {
+            source,
+            fps: fps.max(1),
+            start,
+        }
+    }
+
+    pub fn fps(&self) -> u32 {
+        self.fps
+    }
+
+    pub fn source(&self) -> &S {
+        &self.source
+    }
+
+    /// Length of one tick
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_secs(1) / self.fps
+    }
+
+    pub fn now(&self) -> Instant {
+        self.source.now()
+    }
+
+    /// Tick in progress
+    pub fn frame(&self) -> Frame {
+        self.frame_at(self.now())
+    }
+
+    /// Tick in progress at `at`; instants before the start are frame 0
+    pub fn frame_at(&self, at: Instant) -> Frame {
+        let elapsed = at.saturating_duration_since(self.start);
+        (elapsed.as_nanos() * self.fps as u128 / 1_000_000_000) as Frame
+    }
+
+    /// Instant at which `frame` begins
+    pub fn instant_of(&self, frame: Frame) -> Instant {
+        self.start + self.duration_of(frame)
+    }
+
+    /// Time taken by `frames` ticks
+    pub fn duration_of(&self, frames: Frame) -> Duration {
+        frames_duration(frames, self.fps)
+    }
+
+    /// Ticks in `duration`, rounded to the nearest
+    pub fn frames_in(&self, duration: Duration) -> Frame {
+        ((duration.as_nanos() * self.fps as u128 + 500_000_000) / 1_000_000_000) as Frame
+    }
+
+    /// Wait for the next tick to begin and return its number. Ticks missed
+    /// by a slow iteration are skipped rather than caught up with.
+    pub fn wait_next_frame(&self) -> Frame {
+        let next = self.frame() + 1;
+        self.source.sleep_until(self.instant_of(next));
+        next
+    }
+}
+
+/// A timing window, as given on the command line: in milliseconds or in
+/// frames of the game clock
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimingWindow {
+    Millis(u64),
+    Frames(Frame),
+}
+
+impl TimingWindow {
+    /// Read "250", "250ms" or "15f"
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().to_ascii_lowercase();
+        if let Some(frames) = text.strip_suffix('f') {
+            return frames.parse().ok().map(TimingWindow::Frames);
+        }
+        let ms = text.strip_suffix("ms").unwrap_or(&text);
+        ms.parse().ok().map(TimingWindow::Millis)
+    }
+
+    /// Length of the window at `fps` ticks per second
+    pub fn duration(self, fps: u32) -> Duration {
+        match self {
+            TimingWindow::Millis(ms) => Duration::from_millis(ms),
+            TimingWindow::Frames(frames) => frames_duration(frames, fps.max(1)),
+        }
+    }
+}
+
+impl fmt::Display for TimingWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimingWindow::Millis(ms) => write!(f, "{}ms", ms),
+            TimingWindow::Frames(frames) => write!(f, "{}f", frames),
+        }
+    }
+}
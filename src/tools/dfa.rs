@@ -1,7 +1,10 @@
-use std::collections::BTreeMap;
+use crate::tools::parsing::{Grammar, MoveMetadata};
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::time::Duration;
+
+pub use crate::tools::symbol::Symbol;
 
 pub type State = String;
-pub type Symbol = char;
 
 /// Represents a recognized move with its name
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -9,35 +12,93 @@ pub struct Move {
     pub name: String,
 }
 
+/// A move that can be completed from some state, see [`DFA::reachable_moves`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Completion<'a> {
+    pub name: &'a str,
+    /// Shortest input completing the move, empty if it ends where it starts
+    pub remaining: Vec<Symbol>,
+    /// Accept state the completion leads to
+    pub state: &'a State,
+}
+
+/// How a recognizer reports moves when several could match
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MatchPolicy {
+    /// Report every move as soon as its sequence is complete
+    #[default]
+    All,
+    /// Hold back a move while a longer move may continue it, and report it
+    /// only if the continuation window expires or the sequence breaks
+    Longest,
+    /// Report only the highest-priority moves ending at a state, holding
+    /// them back while a higher-priority move may continue them
+    Priority,
+}
+
+impl MatchPolicy {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "all" => Some(Self::All),
+            "longest" => Some(Self::Longest),
+            "priority" => Some(Self::Priority),
+            _ => None,
+        }
+    }
+}
+
+/// Default time allowed between two inputs of a held-back sequence
+pub const DEFAULT_CONTINUATION_WINDOW: Duration = Duration::from_millis(250);
+
 /// Configuration for DFA runtime behavior
 #[derive(Debug, Clone)]
 pub struct DFAConfig {
     pub debug: bool,
+    pub policy: MatchPolicy,
+    /// How long a held-back move waits for the next input of a longer one
+    pub continuation_window: Duration,
+    /// How long a move may take from its first input to its last; a
+    /// sequence running longer starts over. Unlimited when `None`.
+    pub buffer_window: Option<Duration>,
 }
 
 impl Default for DFAConfig {
     fn default() -> Self {
-        Self { debug: false }
+        Self {
+            debug: false,
+            policy: MatchPolicy::default(),
+            continuation_window: DEFAULT_CONTINUATION_WINDOW,
+            buffer_window: None,
+        }
     }
 }
 
 // A DFA for combo recognition that tracks which moves end at each state
-#[derive(Debug)]
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Debug, Clone)]
 pub struct DFA {
     start: State,
     // derived: for O(1)ish lookup of δ(q,a)
     delta_map: BTreeMap<(State, Symbol), State>,
     // Maps each accept state to the list of moves that end at that state
     state_moves: BTreeMap<State, Vec<String>>,
+    // Priorities declared in the grammar; moves not listed have priority 0
+    priorities: BTreeMap<String, i32>,
+    // Metadata of the moves that have any
+    metadata: BTreeMap<String, MoveMetadata>,
+    // Leniency rules of the grammar, see `Leniency`
+    ignored: BTreeSet<Symbol>,
+    optional: BTreeSet<Symbol>,
 }
 
 impl DFA {
     /// Build a DFA from a collection of moves. Each move is a pair of (sequence, name).
     /// The DFA will have a start state named "q0" and new states named "q1", "q2", ...
     /// Shared prefixes among moves will reuse states so the automaton is compact and deterministic.
-    pub fn from_moves<I>(moves: I) -> Self
+    pub fn from_moves<I, S>(moves: I) -> Self
     where
-        I: IntoIterator<Item = (Vec<Symbol>, String)>,
+        I: IntoIterator<Item = (Vec<S>, String)>,
+        S: Into<Symbol>,
     {
         let mut delta_map: BTreeMap<(State, Symbol), State> = BTreeMap::new();
         let mut state_moves: BTreeMap<State, Vec<String>> = BTreeMap::new();
@@ -49,9 +110,9 @@ impl DFA {
 
         for (seq, name) in moves.into_iter() {
             let mut current = start.clone();
-            for sym in seq.into_iter() {
+            for sym in seq.into_iter().map(Into::into) {
                 // if transition exists, follow it; otherwise create a new state
-                if let Some(existing) = delta_map.get(&(current.clone(), sym)) {
+                if let Some(existing) = delta_map.get(&(current.clone(), sym.clone())) {
                     current = existing.clone();
                     continue;
                 }
@@ -64,19 +125,69 @@ impl DFA {
             }
             // current is the final state for this move
             // Track which move(s) end at this state
-            state_moves
-                .entry(current)
-                .or_insert_with(Vec::new)
-                .push(name);
+            state_moves.entry(current).or_default().push(name);
         }
 
         Self {
             start,
             delta_map,
             state_moves,
+            priorities: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+            ignored: BTreeSet::new(),
+            optional: BTreeSet::new(),
         }
     }
 
+    /// Build a DFA recognizing every move of a grammar
+    pub fn from_grammar(grammar: &Grammar) -> Self {
+        let mut dfa = Self::from_moves(
+            grammar
+                .moves
+                .iter()
+                .map(|m| (m.sequence.clone(), m.name.clone())),
+        );
+        dfa.priorities = grammar
+            .moves
+            .iter()
+            .filter(|m| m.priority != 0)
+            .map(|m| (m.name.clone(), m.priority))
+            .collect();
+        dfa.metadata = grammar
+            .moves
+            .iter()
+            .filter(|m| !m.metadata.is_empty())
+            .map(|m| (m.name.clone(), m.metadata.clone()))
+            .collect();
+        dfa.ignored = grammar.leniency.ignore.clone();
+        dfa.optional = grammar.leniency.optional.clone();
+        dfa
+    }
+
+    /// Priority of a move, as declared in the grammar
+    pub fn priority(&self, name: &str) -> i32 {
+        self.priorities.get(name).copied().unwrap_or(0)
+    }
+
+    /// Metadata of a move, when the grammar gives any
+    pub fn metadata(&self, name: &str) -> Option<&MoveMetadata> {
+        self.metadata.get(name)
+    }
+
+    /// True if `key` may come between two inputs of a move without
+    /// breaking it
+    pub fn is_ignored(&self, key: &Symbol) -> bool {
+        self.ignored.contains(key)
+    }
+
+    /// When `key` does not follow `state` but does after one of the optional
+    /// keys leaving it, that key and the state it leads to
+    pub fn skip_optional(&self, state: &State, key: &Symbol) -> Option<(&Symbol, &State)> {
+        self.transitions_from(state)
+            .filter(|(sym, _)| self.optional.contains(*sym))
+            .find(|(_, next)| self.delta(next, key).is_some())
+    }
+
     /// Get the start state
     pub fn start_state(&self) -> &State {
         &self.start
@@ -85,8 +196,8 @@ impl DFA {
     /// Transition function: δ(q, a) -> q'
     /// Returns None if no transition exists
     /// Optimized to avoid unnecessary clones in hot path
-    pub fn delta(&self, q: &State, a: Symbol) -> Option<&State> {
-        self.delta_map.get(&(q.clone(), a))
+    pub fn delta(&self, q: &State, a: &Symbol) -> Option<&State> {
+        self.delta_map.get(&(q.clone(), a.clone()))
     }
 
     /// Check if a state is an accept state and return the moves that match
@@ -94,13 +205,182 @@ impl DFA {
         self.state_moves.get(state)
     }
 
+    /// Every state, in creation order (q0 first)
+    pub fn states(&self) -> Vec<&State> {
+        let mut states: Vec<&State> = std::iter::once(&self.start)
+            .chain(self.delta_map.values())
+            .collect();
+        states.sort_by_key(|q| q[1..].parse::<usize>().unwrap_or(usize::MAX));
+        states.dedup();
+        states
+    }
+
+    /// All transitions as (from, symbol, to)
+    pub fn transitions(&self) -> impl Iterator<Item = (&State, &Symbol, &State)> {
+        self.delta_map
+            .iter()
+            .map(|((from, sym), to)| (from, sym, to))
+    }
+
+    /// Accept states with the moves that end at each of them
+    pub fn accept_states(&self) -> impl Iterator<Item = (&State, &[String])> {
+        self.state_moves
+            .iter()
+            .map(|(state, moves)| (state, moves.as_slice()))
+    }
+
+    /// Outgoing transitions of `state` as (symbol, target), by symbol
+    pub fn transitions_from<'a>(
+        &'a self,
+        state: &State,
+    ) -> impl Iterator<Item = (&'a Symbol, &'a State)> + 'a {
+        let state = state.clone();
+        self.delta_map
+            .range((state.clone(), Symbol::default())..)
+            .take_while(move |((from, _), _)| *from == state)
+            .map(|((_, sym), to)| (sym, to))
+    }
+
+    /// Keys with a transition out of `state`, in order
+    pub fn expected_symbols(&self, state: &State) -> Vec<Symbol> {
+        self.transitions_from(state)
+            .map(|(sym, _)| sym.clone())
+            .collect()
+    }
+
+    /// Every move that can still be completed from `state`, nearest first,
+    /// with the shortest input completing it. Moves ending at `state` itself
+    /// come first with an empty completion.
+    pub fn reachable_moves(&self, state: &State) -> Vec<Completion<'_>> {
+        let mut reachable: Vec<Completion<'_>> = Vec::new();
+        let mut found: BTreeSet<&str> = BTreeSet::new();
+        if let Some((here, moves)) = self.state_moves.get_key_value(state) {
+            for name in moves {
+                if found.insert(name) {
+                    reachable.push(Completion {
+                        name,
+                        remaining: Vec::new(),
+                        state: here,
+                    });
+                }
+            }
+        }
+        // Each visited state with the state and key it was first reached
+        // from; states reached straight from `state` have no parent
+        let mut parents: BTreeMap<&State, (Option<&State>, &Symbol)> = BTreeMap::new();
+        let mut queue: VecDeque<&State> = VecDeque::new();
+        for (sym, next) in self.transitions_from(state) {
+            if !parents.contains_key(next) {
+                parents.insert(next, (None, sym));
+                queue.push_back(next);
+            }
+        }
+        while let Some(q) = queue.pop_front() {
+            for name in self.get_matches(q).into_iter().flatten() {
+                if found.insert(name) {
+                    reachable.push(Completion {
+                        name,
+                        remaining: keys_to(&parents, q),
+                        state: q,
+                    });
+                }
+            }
+            for (sym, next) in self.transitions_from(q) {
+                if !parents.contains_key(next) {
+                    parents.insert(next, (Some(q), sym));
+                    queue.push_back(next);
+                }
+            }
+        }
+        reachable
+    }
+
+    /// Moves that need at least one more input from `state` to complete,
+    /// nearest first
+    pub fn pending_moves(&self, state: &State) -> Vec<&str> {
+        self.reachable_moves(state)
+            .into_iter()
+            .filter(|c| !c.remaining.is_empty())
+            .map(|c| c.name)
+            .collect()
+    }
+
+    /// Whether a move for which `wanted` holds needs at least one more input
+    /// from `state` to complete. The search stops at the first such move.
+    pub fn has_pending_move(&self, state: &State, mut wanted: impl FnMut(&str) -> bool) -> bool {
+        let mut seen: BTreeSet<&State> = BTreeSet::new();
+        let mut queue: VecDeque<&State> =
+            self.transitions_from(state).map(|(_, next)| next).collect();
+        while let Some(q) = queue.pop_front() {
+            if !seen.insert(q) {
+                continue;
+            }
+            if self
+                .get_matches(q)
+                .into_iter()
+                .flatten()
+                .any(|name| wanted(name))
+            {
+                return true;
+            }
+            queue.extend(self.transitions_from(q).map(|(_, next)| next));
+        }
+        false
+    }
+
+    /// Shortest input leading from the start state to `state`, or `None` if
+    /// the state is not part of the automaton
+    pub fn path_to(&self, state: &State) -> Option<Vec<Symbol>> {
+        if *state == self.start {
+            return Some(Vec::new());
+        }
+        let mut parents: BTreeMap<&State, (Option<&State>, &Symbol)> = BTreeMap::new();
+        let mut queue: VecDeque<&State> = VecDeque::from([&self.start]);
+        while let Some(q) = queue.pop_front() {
+            let parent = (q != &self.start).then_some(q);
+            for (sym, next) in self.transitions_from(q) {
+                if next != &self.start && !parents.contains_key(next) {
+                    parents.insert(next, (parent, sym));
+                    if next == state {
+                        return Some(keys_to(&parents, next));
+                    }
+                    queue.push_back(next);
+                }
+            }
+        }
+        None
+    }
+
+    /// Number of inputs from the start state to `state`
+    pub fn depth(&self, state: &State) -> Option<usize> {
+        self.path_to(state).map(|path| path.len())
+    }
+
+    /// How many leading symbols of `sequence` have been entered when the
+    /// automaton is in `state`. Returns 0 when `state` is not on the path of
+    /// `sequence` (states are never shared between different prefixes).
+    pub fn progress(&self, state: &State, sequence: &[Symbol]) -> usize {
+        let mut current = &self.start;
+        let mut entered = 0;
+        for (i, sym) in sequence.iter().enumerate() {
+            match self.delta(current, sym) {
+                Some(next) => current = next,
+                None => break,
+            }
+            if current == state {
+                entered = i + 1;
+            }
+        }
+        entered
+    }
+
     /// Process a single symbol from a given state, with optional debug output
     /// Returns (new_state, matched_moves)
     /// Optimized to minimize allocations in the hot path
     pub fn step(
         &self,
         current: &State,
-        symbol: Symbol,
+        symbol: &Symbol,
         token_name: &str,
         config: &DFAConfig,
     ) -> (Option<&State>, &[String]) {
@@ -135,3 +415,15 @@ impl DFA {
         (next, matches)
     }
 }
+
+/// Keys leading to `state` along the parents recorded by a search
+fn keys_to(parents: &BTreeMap<&State, (Option<&State>, &Symbol)>, state: &State) -> Vec<Symbol> {
+    let mut path = Vec::new();
+    let mut current = Some(state);
+    while let Some((parent, sym)) = current.and_then(|q| parents.get(q)) {
+        path.push((*sym).clone());
+        current = *parent;
+    }
+    path.reverse();
+    path
+}
@@ -0,0 +1,235 @@
+//! Grammars written as TOML or JSON documents. Both use the same schema:
+//!
+//! ```toml
+//! # player 1: key -> token name
+//! [mappings]
+//! s = "Down"
+//! o = "[BP]"
+//!
+//! # other players bind their own keys to player 1 token names
+//! [players.2]
+//! "5" = "Down"
+//!
+//! # keys the recognizer lets slide, see `Leniency`
+//! [leniency]
+//! ignore = ["p"]
+//! optional = ["x"]
+//! buffer = "20f"
+//! window = "15f"
+//!
+//! # moves of no particular character
+//! [[moves]]
+//! name = "Uppercut"
+//! sequence = ["s", "o"]
+//!
+//! # moves grouped by character, named "Butt slam (Ermac)"
+//! [[characters]]
+//! name = "Ermac"
+//!
+//! [[characters.moves]]
+//! name = "Butt slam"
+//! sequence = ["s", "s", "o"]
+//! priority = 1
+//! damage = 12
+//! startup = 14
+//! active = 3
+//! recovery = 30
+//! meter = 0
+//! category = "special"
+//! notes = "Hits overhead"
+//! ```
+//!
+//! Everything but the mappings is optional, as is every move field besides
+//! `name` and `sequence`.
+
+use crate::tools::clock::TimingWindow;
+use crate::tools::dfa::Symbol;
+use crate::tools::parsing::{Grammar, Leniency, MoveCategory, MoveDef, MoveMetadata, MAX_PLAYERS};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::Path;
+
+/// How a grammar file is written, told by its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GrammarFormat {
+    /// The line-based `.gmr` format, also used for unknown extensions
+    Gmr,
+    Toml,
+    Json,
+}
+
+impl GrammarFormat {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let extension = path
+            .as_ref()
+            .extension()
+            .map(|e| e.to_string_lossy().to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("toml") => GrammarFormat::Toml,
+            Some("json") => GrammarFormat::Json,
+            _ => GrammarFormat::Gmr,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct GrammarDocument {
+    mappings: BTreeMap<String, String>,
+    #[serde(default)]
+    players: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(default)]
+    moves: Vec<MoveDocument>,
+    #[serde(default)]
+    characters: Vec<CharacterDocument>,
+    #[serde(default)]
+    leniency: LeniencyDocument,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct LeniencyDocument {
+    #[serde(default)]
+    ignore: Vec<String>,
+    #[serde(default)]
+    optional: Vec<String>,
+    /// "300ms", "300" or "20f"
+    buffer: Option<String>,
+    window: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CharacterDocument {
+    name: String,
+    #[serde(default)]
+    moves: Vec<MoveDocument>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MoveDocument {
+    name: String,
+    sequence: Vec<String>,
+    #[serde(default)]
+    priority: i32,
+    damage: Option<u32>,
+    startup: Option<u32>,
+    active: Option<u32>,
+    recovery: Option<u32>,
+    meter: Option<u32>,
+    category: Option<MoveCategory>,
+    notes: Option<String>,
+}
+
+impl MoveDocument {
+    fn into_move(self, character: Option<&str>) -> Result<MoveDef, String> {
+        if self.name.is_empty() {
+            return Err("move name must not be empty".to_string());
+        }
+        let name = match character {
+            Some(character) => format!("{} ({})", self.name, character),
+            None => self.name,
+        };
+        let sequence = self
+            .sequence
+            .iter()
+            .map(|key| Symbol::new(key).map_err(|e| format!("move '{}': {}", name, e)))
+            .collect::<Result<_, _>>()?;
+        Ok(MoveDef {
+            name,
+            sequence,
+            priority: self.priority,
+            metadata: MoveMetadata {
+                damage: self.damage,
+                startup: self.startup,
+                active: self.active,
+                recovery: self.recovery,
+                meter: self.meter,
+                category: self.category,
+                notes: self.notes,
+            },
+        })
+    }
+}
+
+impl LeniencyDocument {
+    fn into_leniency(self) -> Result<Leniency, String> {
+        let keys = |rule: &str, keys: Vec<String>| {
+            keys.iter()
+                .map(|key| Symbol::new(key).map_err(|e| format!("{}: {}", rule, e)))
+                .collect::<Result<_, _>>()
+        };
+        let window = |rule: &str, window: Option<String>| {
+            window
+                .map(|window| {
+                    TimingWindow::parse(&window).ok_or_else(|| {
+                        format!(
+                            "{} must be milliseconds or frames like 20f: '{}'",
+                            rule, window
+                        )
+                    })
+                })
+                .transpose()
+        };
+        Ok(Leniency {
+            ignore: keys("ignore", self.ignore)?,
+            optional: keys("optional", self.optional)?,
+            buffer: window("buffer", self.buffer)?,
+            window: window("window", self.window)?,
+        })
+    }
+}
+
+impl GrammarDocument {
+    fn into_grammar(self) -> Result<Grammar, String> {
+        let mut grammar = Grammar::new();
+        let mut bind = |player: usize, mappings: BTreeMap<String, String>| {
+            for (key, token) in mappings {
+                let key = Symbol::new(&key).map_err(|e| format!("mapping: {}", e))?;
+                grammar.bind_key(player, key, &token)?;
+            }
+            Ok::<_, String>(())
+        };
+        bind(1, self.mappings)?;
+
+        let mut players = Vec::new();
+        for (number, mappings) in self.players {
+            match number.parse() {
+                Ok(n) if (2..=MAX_PLAYERS).contains(&n) => players.push((n, mappings)),
+                _ => {
+                    return Err(format!(
+                        "player must be a number from 2 to {}: '{}'",
+                        MAX_PLAYERS, number
+                    ))
+                }
+            }
+        }
+        players.sort_by_key(|&(n, _)| n);
+        for (player, mappings) in players {
+            bind(player, mappings)?;
+        }
+
+        for m in self.moves {
+            grammar.moves.push(m.into_move(None)?);
+        }
+        for character in self.characters {
+            for m in character.moves {
+                grammar.moves.push(m.into_move(Some(&character.name))?);
+            }
+        }
+        grammar.leniency = self.leniency.into_leniency()?;
+        Ok(grammar)
+    }
+}
+
+/// Read a grammar from the text of a TOML or JSON document. `.gmr` text is
+/// left to [`crate::tools::parsing::parse_grammar_file`].
+pub fn parse_grammar_document(text: &str, format: GrammarFormat) -> Result<Grammar, String> {
+    let document: GrammarDocument = match format {
+        GrammarFormat::Toml => toml::from_str(text).map_err(|e| e.to_string())?,
+        GrammarFormat::Json => serde_json::from_str(text).map_err(|e| e.to_string())?,
+        GrammarFormat::Gmr => return Err("not a grammar document".to_string()),
+    };
+    document.into_grammar()
+}
@@ -0,0 +1,476 @@
+use crate::tools::dfa::Symbol;
+use crate::tools::parsing::{Grammar, MoveDef};
+use crate::tools::rng::Rng;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How the next prompted move is chosen
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DrillOrder {
+    /// Uniformly at random
+    #[default]
+    Random,
+    /// Every move in grammar order, wrapping around
+    Sequential,
+    /// At random, favouring moves that failed more often
+    Weighted,
+}
+
+impl DrillOrder {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "random" => Some(Self::Random),
+            "sequential" => Some(Self::Sequential),
+            "weighted" => Some(Self::Weighted),
+            _ => None,
+        }
+    }
+}
+
+/// An input that did not continue the prompted sequence
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrongInput {
+    /// Index in the sequence of the input that was expected
+    pub step: usize,
+    pub expected: Symbol,
+    pub got: Symbol,
+}
+
+/// How an attempt ended
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    /// The sequence was entered exactly, with nothing else
+    Clean,
+    /// The sequence was entered but wrong or extra inputs were mixed in
+    Sloppy,
+    /// The sequence was never completed; `entered` inputs were right
+    Incomplete { entered: usize },
+}
+
+/// Score of one attempt at a prompted move
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttemptResult {
+    pub move_name: String,
+    pub sequence: Vec<Symbol>,
+    pub outcome: Outcome,
+    pub wrong_inputs: Vec<WrongInput>,
+    /// Inputs entered after the sequence was complete
+    pub extra_inputs: usize,
+    /// Time from the prompt to the first input
+    pub reaction: Option<Duration>,
+    /// Time from the first input to the input completing the sequence
+    pub duration: Option<Duration>,
+}
+
+impl AttemptResult {
+    pub fn is_success(&self) -> bool {
+        self.outcome == Outcome::Clean
+    }
+
+    pub fn is_completed(&self) -> bool {
+        !matches!(self.outcome, Outcome::Incomplete { .. })
+    }
+
+    /// One-line human readable verdict, using the grammar's token names
+    pub fn describe(&self, grammar: &Grammar) -> String {
+        let token = |key: &Symbol| {
+            grammar
+                .get_token_for_key(key.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| key.to_string())
+        };
+        let mut text = match self.outcome {
+            Outcome::Clean => "SUCCESS".to_string(),
+            Outcome::Sloppy => "COMPLETED WITH MISTAKES".to_string(),
+            Outcome::Incomplete { entered } => {
+                format!("MISSED ({}/{} inputs)", entered, self.sequence.len())
+            }
+        };
+        // Inputs typed on one console line all arrive together
+        if let Some(duration) = self.duration.filter(|d| !d.is_zero()) {
+            text.push_str(&format!(" in {}ms", duration.as_millis()));
+        }
+        if let Some(reaction) = self.reaction {
+            text.push_str(&format!(", reaction {}ms", reaction.as_millis()));
+        }
+        if let Some(first) = self.wrong_inputs.first() {
+            text.push_str(&format!(
+                ", wrong input at step {}: expected {} got {}",
+                first.step + 1,
+                token(&first.expected),
+                token(&first.got)
+            ));
+            if self.wrong_inputs.len() > 1 {
+                text.push_str(&format!(" (+{} more)", self.wrong_inputs.len() - 1));
+            }
+        }
+        if self.extra_inputs > 0 {
+            text.push_str(&format!(", {} extra input(s)", self.extra_inputs));
+        }
+        text
+    }
+}
+
+/// Inputs of the attempt in progress
+#[derive(Debug, Clone, Default)]
+struct Attempt {
+    prompted_at: Option<Instant>,
+    progress: usize,
+    wrong_inputs: Vec<WrongInput>,
+    extra_inputs: usize,
+    first_input: Option<Instant>,
+    completed_at: Option<Instant>,
+}
+
+/// Per-move totals of a drill session
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MoveTally {
+    pub attempts: usize,
+    pub clean: usize,
+    pub completed: usize,
+    /// Summed time from prompt to completion of the clean attempts
+    pub clean_time: Duration,
+}
+
+impl MoveTally {
+    pub fn failures(&self) -> usize {
+        self.attempts - self.clean
+    }
+
+    /// Share of clean attempts, between 0 and 1
+    pub fn accuracy(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.clean as f64 / self.attempts as f64
+        }
+    }
+
+    pub fn average_clean_time(&self) -> Option<Duration> {
+        (self.clean > 0).then(|| self.clean_time / self.clean as u32)
+    }
+}
+
+/// Accuracy of every drilled move, printed at the end of a session
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrillSummary {
+    /// Drilled moves in grammar order with their totals
+    pub moves: Vec<(MoveDef, MoveTally)>,
+}
+
+impl DrillSummary {
+    pub fn attempts(&self) -> usize {
+        self.moves.iter().map(|(_, t)| t.attempts).sum()
+    }
+
+    pub fn clean(&self) -> usize {
+        self.moves.iter().map(|(_, t)| t.clean).sum()
+    }
+}
+
+impl fmt::Display for DrillSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Drill summary:")?;
+        let width = self
+            .moves
+            .iter()
+            .map(|(m, _)| m.name.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(4);
+        for (m, tally) in &self.moves {
+            let average = tally
+                .average_clean_time()
+                .map(|d| format!("{}ms", d.as_millis()))
+                .unwrap_or_else(|| "-".to_string());
+            writeln!(
+                f,
+                "  {:<width$}  {:>3}/{:<3} clean  {:>5.1}%  avg {}",
+                m.name,
+                tally.clean,
+                tally.attempts,
+                tally.accuracy() * 100.0,
+                average,
+            )?;
+        }
+        let attempts = self.attempts();
+        let accuracy = if attempts == 0 {
+            0.0
+        } else {
+            self.clean() as f64 * 100.0 / attempts as f64
+        };
+        write!(
+            f,
+            "  {:<width$}  {:>3}/{:<3} clean  {:>5.1}%",
+            "Total",
+            self.clean(),
+            attempts,
+            accuracy
+        )
+    }
+}
+
+/// How long an attempt stays open after the sequence is complete, so that
+/// extra inputs count against it
+pub const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+/// Seed derived from the clock, for drills that should differ between runs
+pub fn time_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or(1)
+}
+
+/// A training session: prompts moves one at a time and scores each attempt
+#[derive(Debug, Clone)]
+pub struct Drill {
+    moves: Vec<MoveDef>,
+    order: DrillOrder,
+    rng: Rng,
+    /// Prompts left before the session ends, `None` for endless sessions
+    remaining: Option<usize>,
+    prompted: usize,
+    current: Option<usize>,
+    attempt: Attempt,
+    tallies: BTreeMap<usize, MoveTally>,
+    /// Finished attempts, with the index of their move
+    results: Vec<(usize, AttemptResult)>,
+}
+
+impl Drill {
+    /// Drill the given moves. Moves without inputs cannot be prompted and
+    /// are left out.
+    pub fn new(moves: Vec<MoveDef>, order: DrillOrder, seed: u64) -> Self {
+        Self {
+            moves: moves
+                .into_iter()
+                .filter(|m| !m.sequence.is_empty())
+                .collect(),
+            order,
+            rng: Rng::new(seed),
+            remaining: None,
+            prompted: 0,
+            current: None,
+            attempt: Attempt::default(),
+            tallies: BTreeMap::new(),
+            results: Vec::new(),
+        }
+    }
+
+    /// End the session after `count` prompts
+    pub fn with_count(mut self, count: usize) -> Self {
+        self.remaining = Some(count);
+        self
+    }
+
+    pub fn moves(&self) -> &[MoveDef] {
+        &self.moves
+    }
+
+    /// Number of prompts so far, including the current one
+    pub fn prompted(&self) -> usize {
+        self.prompted
+    }
+
+    /// Total prompts of the session, if limited
+    pub fn count(&self) -> Option<usize> {
+        self.remaining.map(|r| r + self.prompted)
+    }
+
+    /// The move being prompted
+    pub fn prompt(&self) -> Option<&MoveDef> {
+        self.current.map(|i| &self.moves[i])
+    }
+
+    /// Choose the next move to prompt and start a fresh attempt at `now`.
+    /// Returns `None` once the session is over.
+    pub fn next_prompt(&mut self, now: Instant) -> Option<&MoveDef> {
+        self.attempt = Attempt {
+            prompted_at: Some(now),
+            ..Attempt::default()
+        };
+        self.current = None;
+        if self.moves.is_empty() || self.remaining == Some(0) {
+            return None;
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+
+        let index = match self.order {
+            DrillOrder::Sequential => self.prompted % self.moves.len(),
+            DrillOrder::Random => self.rng.below(self.moves.len()),
+            DrillOrder::Weighted => {
+                // Every move keeps a chance; each failure adds two more
+                let weights: Vec<usize> = (0..self.moves.len())
+                    .map(|i| 1 + 2 * self.tallies.get(&i).map_or(0, MoveTally::failures))
+                    .collect();
+                let mut pick = self.rng.below(weights.iter().sum());
+                weights
+                    .iter()
+                    .position(|&w| {
+                        if pick < w {
+                            true
+                        } else {
+                            pick -= w;
+                            false
+                        }
+                    })
+                    .unwrap_or(0)
+            }
+        };
+        self.prompted += 1;
+        self.current = Some(index);
+        self.prompt()
+    }
+
+    /// Number of prompted inputs entered so far in the current attempt
+    pub fn progress(&self) -> usize {
+        self.attempt.progress
+    }
+
+    /// The prompted sequence has been entered
+    pub fn is_complete(&self) -> bool {
+        self.prompt()
+            .is_some_and(|m| self.attempt.progress == m.sequence.len())
+    }
+
+    /// When the current attempt received its first input
+    pub fn attempt_started(&self) -> Option<Instant> {
+        self.attempt.first_input
+    }
+
+    /// When the current attempt completed the sequence
+    pub fn completed_at(&self) -> Option<Instant> {
+        self.attempt.completed_at
+    }
+
+    /// Score one key press against the prompted move
+    pub fn input<K: Into<Symbol>>(&mut self, key: K, at: Instant) {
+        let key = key.into();
+        let Some(index) = self.current else {
+            return;
+        };
+        let sequence = &self.moves[index].sequence;
+        let attempt = &mut self.attempt;
+        attempt.first_input.get_or_insert(at);
+
+        if attempt.progress == sequence.len() {
+            attempt.extra_inputs += 1;
+        } else if sequence[attempt.progress] == key {
+            attempt.progress += 1;
+            if attempt.progress == sequence.len() {
+                attempt.completed_at = Some(at);
+            }
+        } else {
+            attempt.wrong_inputs.push(WrongInput {
+                step: attempt.progress,
+                expected: sequence[attempt.progress].clone(),
+                got: key.clone(),
+            });
+            // Fall back to the longest prefix of the sequence that the
+            // latest inputs still spell, as the recognizer would
+            let mut entered = sequence[..attempt.progress].to_vec();
+            entered.push(key);
+            attempt.progress = (1..=attempt.progress)
+                .rev()
+                .find(|&k| entered.ends_with(&sequence[..k]))
+                .unwrap_or(0);
+            if attempt.progress == sequence.len() {
+                attempt.completed_at = Some(at);
+            }
+        }
+    }
+
+    /// Score the current attempt and add it to the session totals. Returns
+    /// `None` if no move is being prompted.
+    pub fn finish_attempt(&mut self) -> Option<AttemptResult> {
+        let index = self.current.take()?;
+        let attempt = std::mem::take(&mut self.attempt);
+        let m = &self.moves[index];
+
+        let completed = attempt.progress == m.sequence.len();
+        let outcome = if !completed {
+            Outcome::Incomplete {
+                entered: attempt.progress,
+            }
+        } else if attempt.wrong_inputs.is_empty() && attempt.extra_inputs == 0 {
+            Outcome::Clean
+        } else {
+            Outcome::Sloppy
+        };
+        let duration = match (attempt.first_input, attempt.completed_at) {
+            (Some(first), Some(done)) => Some(done.saturating_duration_since(first)),
+            _ => None,
+        };
+        let reaction = match (attempt.prompted_at, attempt.first_input) {
+            (Some(prompted), Some(first)) => Some(first.saturating_duration_since(prompted)),
+            _ => None,
+        };
+
+        let tally = self.tallies.entry(index).or_default();
+        tally.attempts += 1;
+        if completed {
+            tally.completed += 1;
+        }
+        if outcome == Outcome::Clean {
+            tally.clean += 1;
+            tally.clean_time += reaction.unwrap_or_default() + duration.unwrap_or_default();
+        }
+
+        let result = AttemptResult {
+            move_name: m.name.clone(),
+            sequence: m.sequence.clone(),
+            outcome,
+            wrong_inputs: attempt.wrong_inputs,
+            extra_inputs: attempt.extra_inputs,
+            reaction,
+            duration,
+        };
+        self.results.push((index, result.clone()));
+        Some(result)
+    }
+
+    /// Finish the current attempt at `now` if it settled after completing
+    /// the sequence or ran past `time_limit`, and prompt the next move.
+    /// Returns the finished attempt.
+    pub fn tick(&mut self, now: Instant, time_limit: Duration) -> Option<AttemptResult> {
+        let settled = self
+            .completed_at()
+            .is_some_and(|done| now.saturating_duration_since(done) >= SETTLE_TIME);
+        let timed_out = self
+            .attempt_started()
+            .is_some_and(|start| now.saturating_duration_since(start) >= time_limit);
+        if !settled && !timed_out {
+            return None;
+        }
+        let result = self.finish_attempt();
+        self.next_prompt(now);
+        result
+    }
+
+    /// Result of the most recently finished attempt
+    pub fn last_result(&self) -> Option<&AttemptResult> {
+        self.results.last().map(|(_, result)| result)
+    }
+
+    /// Every attempt finished so far, oldest first, with its move
+    pub fn results(&self) -> impl Iterator<Item = (&MoveDef, &AttemptResult)> {
+        self.results
+            .iter()
+            .map(|(i, result)| (&self.moves[*i], result))
+    }
+
+    /// Totals of every move drilled so far
+    pub fn summary(&self) -> DrillSummary {
+        DrillSummary {
+            moves: self
+                .tallies
+                .iter()
+                .map(|(&i, tally)| (self.moves[i].clone(), tally.clone()))
+                .collect(),
+        }
+    }
+}
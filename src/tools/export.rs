@@ -0,0 +1,59 @@
+use crate::tools::dfa::DFA;
+use crate::tools::parsing::Grammar;
+use std::fmt::Write;
+
+/// Escape a label for a double-quoted Graphviz string
+fn dot_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Render the automaton as a Graphviz `digraph`. Edges are labelled with the
+/// token names of the grammar and accept states list the moves ending there.
+pub fn to_dot(dfa: &DFA, grammar: &Grammar) -> String {
+    let mut out = String::new();
+    out.push_str("digraph ft_ality {\n");
+    out.push_str("    rankdir=LR;\n");
+    out.push_str("    node [shape=circle];\n");
+    out.push_str("    start [shape=point];\n");
+    let _ = writeln!(out, "    start -> {};", dfa.start_state());
+
+    for (state, moves) in dfa.accept_states() {
+        let names: Vec<String> = moves.iter().map(|name| dot_escape(name)).collect();
+        let label = format!("{}\\n{}", state, names.join("\\n"));
+        let _ = writeln!(
+            out,
+            "    {} [shape=doublecircle, label=\"{}\"];",
+            state, label
+        );
+    }
+    for (from, symbol, to) in dfa.transitions() {
+        let token = grammar
+            .get_token_for_key(symbol.as_str())
+            .map(dot_escape)
+            .unwrap_or_else(|| dot_escape(symbol.as_str()));
+        let _ = writeln!(out, "    {} -> {} [label=\"{}\"];", from, to, token);
+    }
+    out.push_str("}\n");
+    out
+}
+
+/// Render the automaton as a plain-text transition table, one entry per line:
+///
+/// ```text
+/// start q0
+/// delta q0 s q1
+/// accept q3 Butt slam (Ermac)
+/// ```
+pub fn to_table(dfa: &DFA) -> String {
+    let mut out = String::new();
+    let _ = writeln!(out, "start {}", dfa.start_state());
+    for (from, symbol, to) in dfa.transitions() {
+        let _ = writeln!(out, "delta {} {} {}", from, symbol, to);
+    }
+    for (state, moves) in dfa.accept_states() {
+        for name in moves {
+            let _ = writeln!(out, "accept {} {}", state, name);
+        }
+    }
+    out
+}
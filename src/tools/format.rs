@@ -0,0 +1,195 @@
+use crate::tools::dfa::Symbol;
+use crate::tools::parsing::{parse_line, quote, quote_notes, split_comment, GmrLine, Grammar};
+
+fn join_keys(keys: &[Symbol]) -> String {
+    let keys: Vec<_> = keys.iter().map(|k| quote(k.as_str())).collect();
+    keys.join(" ")
+}
+
+/// One line in canonical form. Tokens of mappings and sequences of moves
+/// start after `width` columns, so that the lines of a block line up.
+fn render_line(line: &GmrLine, width: usize) -> String {
+    match line {
+        GmrLine::Blank => String::new(),
+        GmrLine::Comment(text) => text.to_string(),
+        GmrLine::Include(target) => format!("include \"{}\"", target),
+        GmrLine::Player(n) => format!("player {}", n),
+        GmrLine::Mapping(key, token) => format!(
+            "{:<width$} {}",
+            format!("{},", quote(key.as_str())),
+            quote(token),
+            width = width + 1
+        ),
+        GmrLine::Ignore(keys) => format!("ignore {}", join_keys(keys)),
+        GmrLine::Optional(keys) => format!("optional {}", join_keys(keys)),
+        GmrLine::Buffer(window) => format!("buffer {}", window),
+        GmrLine::Window(window) => format!("window {}", window),
+        GmrLine::Move(m) => {
+            let mut text = format!(
+                "{:<width$} {}",
+                format!("{}:", quote(&m.name)),
+                join_keys(&m.sequence),
+                width = width + 1
+            );
+            let metadata = &m.metadata;
+            let counts = [
+                ("damage", metadata.damage),
+                ("startup", metadata.startup),
+                ("active", metadata.active),
+                ("recovery", metadata.recovery),
+                ("meter", metadata.meter),
+            ];
+            let mut attributes: Vec<String> = (m.priority != 0)
+                .then(|| format!("priority={}", m.priority))
+                .into_iter()
+                .chain(
+                    counts
+                        .iter()
+                        .filter_map(|(name, value)| value.map(|v| format!("{}={}", name, v))),
+                )
+                .chain(metadata.category.map(|c| format!("category={}", c.name())))
+                .collect();
+            // Notes run to the end of the line, so they come last
+            if let Some(notes) = metadata.notes.as_deref().filter(|n| !n.is_empty()) {
+                attributes.push(format!("notes={}", quote_notes(notes)));
+            }
+            if !attributes.is_empty() {
+                text = format!("{} | {}", text.trim_end(), attributes.join(" "));
+            }
+            text.trim_end().to_string()
+        }
+    }
+}
+
+/// Width of the first column of a line: the key of a mapping or the name of
+/// a move, for lines that have one
+fn column_width(line: &GmrLine) -> Option<usize> {
+    match line {
+        GmrLine::Mapping(key, _) => Some(quote(key.as_str()).chars().count()),
+        GmrLine::Move(m) => Some(quote(&m.name).chars().count()),
+        _ => None,
+    }
+}
+
+/// Render lines followed by their inline comment, lining up each block of
+/// consecutive mappings and each block of consecutive moves
+fn render_lines(lines: &[(GmrLine, Option<&str>)]) -> String {
+    let same_kind =
+        |a: &GmrLine, b: &GmrLine| std::mem::discriminant(a) == std::mem::discriminant(b);
+    let mut text = String::new();
+    let mut width = 0;
+    for (i, (line, comment)) in lines.iter().enumerate() {
+        let starts_block = match lines[..i].last() {
+            Some((previous, _)) => !same_kind(previous, line),
+            None => true,
+        };
+        if starts_block && column_width(line).is_some() {
+            width = lines[i..]
+                .iter()
+                .take_while(|(next, _)| same_kind(next, line))
+                .filter_map(|(next, _)| column_width(next))
+                .max()
+                .unwrap_or(0);
+        }
+        text.push_str(&render_line(line, width));
+        if let Some(comment) = comment {
+            text.push_str("  ");
+            text.push_str(comment);
+        }
+        text.push('\n');
+    }
+    text
+}
+
+impl Grammar {
+    /// The grammar as `.gmr` text in canonical form: player 1 mappings
+    /// sorted by key, the sections of the other players, the leniency rules,
+    /// then the moves in their order, which decides the order of moves matched together.
+    /// Names and keys are quoted where needed, so that parsing the text
+    /// gives the grammar back, provided that notes fit on one line.
+    pub fn to_gmr(&self) -> String {
+        let mut lines: Vec<GmrLine> = self
+            .mappings
+            .iter()
+            .map(|(key, token)| GmrLine::Mapping(key.clone(), token.into()))
+            .collect();
+        for player in 2..=self.player_count() {
+            let keys: Vec<GmrLine> = self
+                .player_keys
+                .iter()
+                .filter(|(_, (p, _))| *p == player)
+                .map(|(key, (_, base))| {
+                    GmrLine::Mapping(key.clone(), self.mappings[base].as_str().into())
+                })
+                .collect();
+            if !keys.is_empty() {
+                lines.extend([GmrLine::Blank, GmrLine::Player(player)]);
+                lines.extend(keys);
+            }
+        }
+        let leniency = &self.leniency;
+        if !leniency.is_empty() {
+            if !lines.is_empty() {
+                lines.push(GmrLine::Blank);
+            }
+            let ignore: Vec<Symbol> = leniency.ignore.iter().cloned().collect();
+            let optional: Vec<Symbol> = leniency.optional.iter().cloned().collect();
+            if !ignore.is_empty() {
+                lines.push(GmrLine::Ignore(ignore));
+            }
+            if !optional.is_empty() {
+                lines.push(GmrLine::Optional(optional));
+            }
+            lines.extend(leniency.buffer.map(GmrLine::Buffer));
+            lines.extend(leniency.window.map(GmrLine::Window));
+        }
+        if !self.moves.is_empty() && !lines.is_empty() {
+            lines.push(GmrLine::Blank);
+        }
+        lines.extend(self.moves.iter().cloned().map(GmrLine::Move));
+        let lines: Vec<_> = lines.into_iter().map(|line| (line, None)).collect();
+        render_lines(&lines)
+    }
+}
+
+/// Rewrite the text of a `.gmr` file in canonical form, keeping its
+/// comments and includes where they are: each block of consecutive mappings
+/// is sorted by key, mappings and moves are lined up, syntax is normalized
+/// (`key, Name`, `include "file"`) and blank lines are collapsed. The
+/// grammar it defines does not change.
+pub fn format_gmr(text: &str) -> Result<String, String> {
+    let mut lines = Vec::new();
+    for (lineno, line) in text.lines().enumerate() {
+        // Comments after content stay on their line, even once sorted
+        let (content, comment) = if line.trim_start().starts_with('#') {
+            (line, None)
+        } else {
+            split_comment(line)
+        };
+        let line = parse_line(content).map_err(|e| format!("{}: {}", lineno + 1, e))?;
+        let blank_after_blank =
+            line == GmrLine::Blank && matches!(lines.last(), None | Some((GmrLine::Blank, _)));
+        if !blank_after_blank {
+            lines.push((line, comment));
+        }
+    }
+    if matches!(lines.last(), Some((GmrLine::Blank, _))) {
+        lines.pop();
+    }
+
+    // Later mappings of a key win, so keep duplicates in their order
+    let mut start = 0;
+    while start < lines.len() {
+        let run = lines[start..]
+            .iter()
+            .take_while(|(line, _)| matches!(line, GmrLine::Mapping(..)))
+            .count();
+        lines[start..start + run].sort_by_key(|(line, _)| match line {
+            GmrLine::Mapping(key, _) => key.clone(),
+            _ => unreachable!("only mappings are sorted"),
+        });
+        start += run.max(1);
+    }
+
+    Ok(render_lines(&lines))
+}
@@ -0,0 +1,170 @@
+use crate::tools::parsing::{Grammar, MoveDef};
+use crate::tools::rng::Rng;
+use crate::tools::symbol::Symbol;
+
+/// Most keys a generated grammar binds: the letters, then the digits
+pub const MAX_KEYS: usize = 36;
+
+/// Key pressed between two generated moves. No move uses it, so it breaks
+/// every sequence and the next move starts from the start state.
+pub const SEPARATOR_KEY: &str = "Pause";
+
+/// Shape of a generated grammar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GeneratorConfig {
+    pub characters: usize,
+    pub moves_per_character: usize,
+    /// Keys the moves are made of, up to [`MAX_KEYS`]
+    pub keys: usize,
+    pub min_length: usize,
+    pub max_length: usize,
+    /// Percentage of moves that start like an earlier move
+    pub shared_prefixes: usize,
+    pub seed: u64,
+}
+
+impl Default for GeneratorConfig {
+    /// About the roster of a full game
+    fn default() -> Self {
+        Self {
+            characters: 30,
+            moves_per_character: 20,
+            keys: 12,
+            min_length: 2,
+            max_length: 8,
+            shared_prefixes: 30,
+            seed: 1,
+        }
+    }
+}
+
+impl GeneratorConfig {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.characters == 0 || self.moves_per_character == 0 {
+            return Err("a grammar needs at least one character and one move".to_string());
+        }
+        if !(1..=MAX_KEYS).contains(&self.keys) {
+            return Err(format!("keys must be from 1 to {}", MAX_KEYS));
+        }
+        if self.min_length == 0 || self.min_length > self.max_length {
+            return Err(format!(
+                "invalid sequence lengths {}-{}",
+                self.min_length, self.max_length
+            ));
+        }
+        if self.shared_prefixes > 100 {
+            return Err("shared prefixes are a percentage, up to 100".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Key number `index` of a generated grammar: 'a' to 'z', then '0' to '9'
+fn key(index: usize) -> Symbol {
+    let ch = match index {
+        0..=25 => (b'a' + index as u8) as char,
+        _ => (b'0' + (index - 26) as u8) as char,
+    };
+    Symbol::from(ch)
+}
+
+/// Synthesize a grammar of `config.characters` characters with
+/// `config.moves_per_character` moves each, named like
+/// "Move 3 (Fighter 12)". The same config always gives the same grammar.
+pub fn generate_grammar(config: &GeneratorConfig) -> Result<Grammar, String> {
+    config.validate()?;
+    let mut rng = Rng::new(config.seed);
+
+    let mut grammar = Grammar::new();
+    for index in 0..config.keys {
+        let key = key(index);
+        let token = format!("[{}]", key.as_str().to_uppercase());
+        grammar.mappings.insert(key, token);
+    }
+    grammar
+        .mappings
+        .insert(Symbol::new(SEPARATOR_KEY)?, SEPARATOR_KEY.to_string());
+
+    for character in 1..=config.characters {
+        for number in 1..=config.moves_per_character {
+            let length = rng.between(config.min_length, config.max_length);
+            let mut sequence = Vec::with_capacity(length);
+            if !grammar.moves.is_empty() && rng.chance(config.shared_prefixes) {
+                let earlier = &rng.pick(&grammar.moves).sequence;
+                let shared = rng.between(1, earlier.len().min(length));
+                sequence.extend_from_slice(&earlier[..shared]);
+            }
+            while sequence.len() < length {
+                sequence.push(key(rng.below(config.keys)));
+            }
+            grammar.moves.push(MoveDef {
+                name: format!("Move {} (Fighter {})", number, character),
+                sequence,
+                ..MoveDef::default()
+            });
+        }
+    }
+    Ok(grammar)
+}
+
+/// Key presses playing moves of a grammar, with the moves a recognizer
+/// reports for them under the default match policy
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GeneratedInput {
+    /// One move per line, keys separated by spaces and followed by the
+    /// separator key, as read by `replay`
+    pub text: String,
+    /// Names of the reported moves, in order
+    pub expected: Vec<String>,
+}
+
+/// Play `count` moves of `grammar` picked at random. Each completes its own
+/// move and every move spelled by one of its prefixes, in grammar order.
+/// The grammar must bind [`SEPARATOR_KEY`] and have no leniency rules.
+pub fn generate_input(
+    grammar: &Grammar,
+    count: usize,
+    seed: u64,
+) -> Result<GeneratedInput, String> {
+    if grammar.moves.is_empty() {
+        return Err("the grammar has no moves to play".to_string());
+    }
+    if !grammar.leniency.is_empty() {
+        return Err("expected matches cannot be worked out with leniency rules".to_string());
+    }
+    let separator = Symbol::new(SEPARATOR_KEY)?;
+    if !grammar.mappings.contains_key(&separator)
+        || grammar
+            .moves
+            .iter()
+            .any(|m| m.sequence.contains(&separator))
+    {
+        return Err(format!(
+            "the grammar must bind '{}' and use it in no move",
+            SEPARATOR_KEY
+        ));
+    }
+
+    let mut rng = Rng::new(seed);
+    let mut input = GeneratedInput::default();
+    for _ in 0..count {
+        let played = &grammar.moves[rng.below(grammar.moves.len())].sequence;
+        for length in 1..=played.len() {
+            let prefix = &played[..length];
+            input.expected.extend(
+                grammar
+                    .moves
+                    .iter()
+                    .filter(|m| m.sequence == prefix)
+                    .map(|m| m.name.clone()),
+            );
+        }
+        for key in played {
+            input.text.push_str(key.as_str());
+            input.text.push(' ');
+        }
+        input.text.push_str(SEPARATOR_KEY);
+        input.text.push('\n');
+    }
+    Ok(input)
+}
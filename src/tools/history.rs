@@ -0,0 +1,174 @@
+use crate::tools::clock::Frame;
+use crate::tools::dfa::Symbol;
+use std::collections::VecDeque;
+use std::ops::{Bound, Range, RangeBounds};
+use std::time::{Duration, Instant};
+
+/// Inputs an [`InputHistory`] keeps unless configured otherwise
+pub const DEFAULT_HISTORY_CAPACITY: usize = 32;
+
+/// One key press kept by an [`InputHistory`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputRecord {
+    /// Number of the input, counted from 0 since the history was created.
+    /// Set by [`InputHistory::push`]; it does not change as older inputs
+    /// drop out.
+    pub index: u64,
+    pub player: usize,
+    /// Key that was pressed, as the player 1 key the moves are written with
+    pub key: Symbol,
+    /// Token name of the key
+    pub token: String,
+    pub at: Instant,
+    /// Game tick of the input, when stamped by a frame clock
+    pub frame: Option<Frame>,
+    /// The input broke the sequence being followed
+    pub reset: bool,
+}
+
+impl InputRecord {
+    pub fn new(player: usize, key: impl Into<Symbol>, token: &str, at: Instant) -> Self {
+        Self {
+            index: 0,
+            player,
+            key: key.into(),
+            token: token.to_string(),
+            at,
+            frame: None,
+            reset: false,
+        }
+    }
+}
+
+/// The latest inputs in a ring buffer: once full, each new input pushes the
+/// oldest one out. Inputs are numbered as they come, so that spans of inputs
+/// (see [`crate::tools::recognizer::StepEvent::spans`]) can be looked up
+/// for as long as they are kept.
+#[derive(Debug, Clone)]
+pub struct InputHistory {
+    records: VecDeque<InputRecord>,
+    capacity: usize,
+    next_index: u64,
+}
+
+impl Default for InputHistory {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_CAPACITY)
+    }
+}
+
+impl InputHistory {
+    /// An empty history keeping the last `capacity` inputs, at least one
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        Self {
+            records: VecDeque::with_capacity(capacity),
+            capacity,
+            next_index: 0,
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Number of inputs kept
+    pub fn len(&self) -> usize {
+        self.records.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.records.is_empty()
+    }
+
+    /// Index the next input will get
+    pub fn next_index(&self) -> u64 {
+        self.next_index
+    }
+
+    /// Add an input, dropping the oldest one when full, and return its index
+    pub fn push(&mut self, mut record: InputRecord) -> u64 {
+        record.index = self.next_index;
+        self.next_index += 1;
+        if self.records.len() == self.capacity {
+            self.records.pop_front();
+        }
+        self.records.push_back(record);
+        self.next_index - 1
+    }
+
+    /// Input number `index`, if still kept
+    pub fn get(&self, index: u64) -> Option<&InputRecord> {
+        let first = self.records.front()?.index;
+        let offset = index.checked_sub(first)?;
+        self.records.get(usize::try_from(offset).ok()?)
+    }
+
+    /// Most recent input
+    pub fn latest(&self) -> Option<&InputRecord> {
+        self.records.back()
+    }
+
+    /// Every input kept, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &InputRecord> + '_ {
+        self.records.iter()
+    }
+
+    /// Inputs kept whose index is in `range`, oldest first
+    pub fn range<R: RangeBounds<u64>>(
+        &self,
+        range: R,
+    ) -> impl DoubleEndedIterator<Item = &InputRecord> + '_ {
+        let Some(first) = self.records.front().map(|r| r.index) else {
+            return self.records.range(0..0);
+        };
+        let start = match range.start_bound() {
+            Bound::Included(&i) => i,
+            Bound::Excluded(&i) => i.saturating_add(1),
+            Bound::Unbounded => first,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&i) => i.saturating_add(1),
+            Bound::Excluded(&i) => i,
+            Bound::Unbounded => self.next_index,
+        };
+        let offset = |i: u64| (i.clamp(first, self.next_index) - first) as usize;
+        let (start, end) = (offset(start), offset(end));
+        self.records.range(start..end.max(start))
+    }
+
+    /// The last `count` inputs, oldest first
+    pub fn recent(&self, count: usize) -> impl DoubleEndedIterator<Item = &InputRecord> + '_ {
+        self.records
+            .range(self.records.len().saturating_sub(count)..)
+    }
+
+    /// Inputs pressed at or after `at`, oldest first
+    pub fn since(&self, at: Instant) -> impl DoubleEndedIterator<Item = &InputRecord> + '_ {
+        let start = self.records.partition_point(|r| r.at < at);
+        self.records.range(start..)
+    }
+
+    /// Token names of the inputs in `span` still kept, oldest first
+    pub fn tokens(&self, span: Range<u64>) -> Vec<&str> {
+        self.range(span).map(|r| r.token.as_str()).collect()
+    }
+
+    /// Time between input `index` and the one before it
+    pub fn gap(&self, index: u64) -> Option<Duration> {
+        let previous = self.get(index.checked_sub(1)?)?;
+        Some(self.get(index)?.at.saturating_duration_since(previous.at))
+    }
+
+    /// Game ticks between input `index` and the one before it, when both
+    /// were stamped
+    pub fn frame_gap(&self, index: u64) -> Option<Frame> {
+        let previous = self.get(index.checked_sub(1)?)?.frame?;
+        Some(self.get(index)?.frame?.saturating_sub(previous))
+    }
+
+    /// Forget every input; indices carry on from where they were
+    pub fn clear(&mut self) {
+        self.records.clear();
+    }
+}
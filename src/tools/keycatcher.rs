@@ -1,62 +1,76 @@
 extern crate sdl2;
 
+use crate::tools::clock::{FrameClock, TimeSource};
+use crate::tools::dfa::{Symbol, DFA};
+use crate::tools::drill::Drill;
+use crate::tools::layout::{self, Layout};
 use crate::tools::parsing::Grammar;
-use crate::tools::ui;
-use sdl2::event::Event;
+use crate::tools::recognizer::StepEvent;
+use crate::tools::theme::Theme;
+use crate::tools::ui::{self, UiModel};
+use sdl2::event::{Event, WindowEvent};
 use sdl2::keyboard::Keycode;
+use sdl2::render::Canvas;
+use sdl2::video::Window;
+use sdl2::Sdl;
 use std::error::Error;
-use std::time::Duration;
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
 
 /// Represents an input event from keyboard or gamepad
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum InputEvent {
-    Token(char), // A valid token character
-    Quit,        // User wants to quit
-    Invalid,     // Other events to ignore
+    Token(Symbol),     // A key mapped by the grammar
+    Scroll(i32),       // Scroll the move list by this many rows
+    Resized(u32, u32), // Window was resized to (width, height)
+    Quit,              // User wants to quit
+    Invalid,           // Other events to ignore
 }
 
-/// Translates SDL Keycode to a character for our token system
-fn keycode_to_char(keycode: Keycode) -> Option<char> {
-    match keycode {
-        // Letters
-        Keycode::A => Some('a'),
-        Keycode::B => Some('b'),
-        Keycode::C => Some('c'),
-        Keycode::D => Some('d'),
-        Keycode::E => Some('e'),
-        Keycode::F => Some('f'),
-        Keycode::G => Some('g'),
-        Keycode::H => Some('h'),
-        Keycode::I => Some('i'),
-        Keycode::J => Some('j'),
-        Keycode::K => Some('k'),
-        Keycode::L => Some('l'),
-        Keycode::M => Some('m'),
-        Keycode::N => Some('n'),
-        Keycode::O => Some('o'),
-        Keycode::P => Some('p'),
-        Keycode::Q => Some('q'),
-        Keycode::R => Some('r'),
-        Keycode::S => Some('s'),
-        Keycode::T => Some('t'),
-        Keycode::U => Some('u'),
-        Keycode::V => Some('v'),
-        Keycode::W => Some('w'),
-        Keycode::X => Some('x'),
-        Keycode::Y => Some('y'),
-        Keycode::Z => Some('z'),
-        // Numbers
-        Keycode::Num0 => Some('0'),
-        Keycode::Num1 => Some('1'),
-        Keycode::Num2 => Some('2'),
-        Keycode::Num3 => Some('3'),
-        Keycode::Num4 => Some('4'),
-        Keycode::Num5 => Some('5'),
-        Keycode::Num6 => Some('6'),
-        Keycode::Num7 => Some('7'),
-        Keycode::Num8 => Some('8'),
-        Keycode::Num9 => Some('9'),
-        _ => None,
+/// Receives the key presses of the SDL input loop
+pub trait TokenHandler {
+    /// Handle one mapped key press
+    fn token(&mut self, key: &Symbol, token_name: &str) -> StepEvent;
+
+    /// Called once per frame between inputs. Returns moves reported without
+    /// a new input, such as held-back moves whose continuation window
+    /// expired, as (player, move).
+    fn idle(&mut self, _now: Instant) -> Vec<(usize, String)> {
+        Vec::new()
+    }
+
+    /// Called once per frame, and before each console line. Returns the
+    /// grammar and automaton to use from now on when they were reloaded.
+    fn reload(&mut self, _now: Instant) -> Option<(Grammar, DFA)> {
+        None
+    }
+}
+
+impl<F: FnMut(&Symbol, &str) -> StepEvent> TokenHandler for F {
+    fn token(&mut self, key: &Symbol, token_name: &str) -> StepEvent {
+        self(key, token_name)
+    }
+}
+
+/// Rows scrolled by one mouse wheel notch and by PageUp/PageDown
+const WHEEL_SCROLL_ROWS: i32 = 3;
+const PAGE_SCROLL_ROWS: i32 = 10;
+
+/// SDL keycodes without this bit are the character the key types
+const SCANCODE_MASK: i32 = 1 << 30;
+
+/// Translates SDL Keycode to a key of our token system: the character the
+/// key types (letters, digits, punctuation, accented letters of the layout)
+/// or, for other keys, the SDL key name without spaces, like `LeftShift`
+fn keycode_to_symbol(keycode: Keycode) -> Option<Symbol> {
+    let code = keycode.into_i32();
+    let typed = (code & SCANCODE_MASK == 0)
+        .then(|| char::from_u32(code as u32))
+        .flatten()
+        .filter(|ch| !ch.is_control() && !ch.is_whitespace());
+    match typed {
+        Some(ch) => Some(Symbol::from(ch)),
+        None => Symbol::new(&keycode.name().replace(' ', "")).ok(),
     }
 }
 
@@ -68,62 +82,108 @@ fn process_event(event: Event, grammar: &Grammar) -> InputEvent {
             keycode: Some(Keycode::Escape),
             ..
         } => InputEvent::Quit,
+        Event::Window {
+            win_event: WindowEvent::Resized(w, h) | WindowEvent::SizeChanged(w, h),
+            ..
+        } => InputEvent::Resized(w.max(0) as u32, h.max(0) as u32),
+        Event::MouseWheel { y, .. } => InputEvent::Scroll(-y * WHEEL_SCROLL_ROWS),
         Event::KeyDown {
             keycode: Some(keycode),
             ..
         } => {
-            if let Some(ch) = keycode_to_char(keycode) {
-                // Check if this character maps to a token in our grammar
-                if grammar.get_token_for_key(ch).is_some() {
-                    return InputEvent::Token(ch);
-                }
+            // Keys mapped in the grammar win over scrolling, so that the
+            // arrows can be mapped
+            let key = keycode_to_symbol(keycode)
+                .filter(|key| grammar.get_token_for_key(key.as_str()).is_some());
+            match (key, keycode) {
+                (Some(key), _) => InputEvent::Token(key),
+                (None, Keycode::PageUp) => InputEvent::Scroll(-PAGE_SCROLL_ROWS),
+                (None, Keycode::PageDown) => InputEvent::Scroll(PAGE_SCROLL_ROWS),
+                (None, Keycode::Up) => InputEvent::Scroll(-1),
+                (None, Keycode::Down) => InputEvent::Scroll(1),
+                _ => InputEvent::Invalid,
             }
-            InputEvent::Invalid
         }
         _ => InputEvent::Invalid,
     }
 }
 
-/// Main input handling loop with SDL
-/// Takes a grammar and a callback function that processes token events
-/// Returns Ok(()) on normal exit, Err on error
-pub fn run_input_loop<F>(grammar: &Grammar, mut on_token: F) -> Result<(), Box<dyn Error>>
-where
-    F: FnMut(char, &str),
-{
+/// Open the resizable training window and its renderer
+fn open_window() -> Result<(Sdl, Canvas<Window>), Box<dyn Error>> {
     let sdl_context = sdl2::init().map_err(|e| format!("SDL init failed: {}", e))?;
     let video_subsystem = sdl_context
         .video()
         .map_err(|e| format!("Video subsystem failed: {}", e))?;
 
     let (width, height) = ui::get_window_size();
-    let window = video_subsystem
+    let mut window = video_subsystem
         .window("ft_ality - Fighting Game Training Mode", width, height)
         .position_centered()
+        .resizable()
         .build()
         .map_err(|e| format!("Window creation failed: {}", e))?;
+    window
+        .set_minimum_size(layout::MIN_WIDTH, layout::MIN_HEIGHT)
+        .map_err(|e| format!("Window setup failed: {}", e))?;
 
-    let mut canvas = window
+    let canvas = window
         .into_canvas()
         .build()
         .map_err(|e| format!("Canvas creation failed: {}", e))?;
+    Ok((sdl_context, canvas))
+}
 
-    // Initial render of key mappings
-    ui::render_key_mappings(&mut canvas, grammar);
+/// Main input handling loop with SDL
+/// Takes a grammar, the DFA being recognized, the theme to draw with, the
+/// clock pacing the loop and a handler that processes token events. The
+/// window is redrawn every tick of the clock from the events returned by the
+/// handler and can be freely resized.
+/// Returns Ok(()) on normal exit, Err on error
+pub fn run_input_loop<H>(
+    grammar: &Grammar,
+    dfa: &DFA,
+    theme: &Theme,
+    clock: &FrameClock,
+    handler: &mut H,
+) -> Result<(), Box<dyn Error>>
+where
+    H: TokenHandler,
+{
+    let (sdl_context, mut canvas) = open_window()?;
+
+    let mut model = UiModel::new(dfa.start_state()).with_players(grammar.player_count());
+    let (width, height) = canvas.output_size()?;
+    let mut layout = Layout::compute_with_style(width, height, theme.layout);
 
     let mut event_pump = sdl_context
         .event_pump()
         .map_err(|e| format!("Event pump failed: {}", e))?;
 
+    // Grammar and automaton swapped in by the handler, replacing the ones given
+    let mut reloaded: Option<(Grammar, DFA)> = None;
     'main_loop: loop {
+        let (grammar, dfa) = match &reloaded {
+            Some((grammar, dfa)) => (grammar, dfa),
+            None => (grammar, dfa),
+        };
         for event in event_pump.poll_iter() {
             match process_event(event, grammar) {
-                InputEvent::Token(ch) => {
+                InputEvent::Token(key) => {
                     // Get the token name and pass it to the callback
-                    if let Some(token_name) = grammar.get_token_for_key(ch) {
-                        on_token(ch, token_name);
+                    if let Some(token_name) = grammar.get_token_for_key(key.as_str()) {
+                        let event = handler.token(&key, token_name);
+                        model.record(&event, clock.now());
                     }
                 }
+                InputEvent::Scroll(rows) => {
+                    model.scroll_move_list(rows, ui::move_list_max_scroll(grammar, &layout, theme));
+                }
+                InputEvent::Resized(..) => {
+                    // The drawable size can differ from the window size on
+                    // high-DPI displays, so lay out for what the renderer sees
+                    let (width, height) = canvas.output_size()?;
+                    layout = Layout::compute_with_style(width, height, theme.layout);
+                }
                 InputEvent::Quit => {
                     break 'main_loop;
                 }
@@ -133,33 +193,180 @@ where
             }
         }
 
-        ::std::thread::sleep(Duration::new(0, 1_000_000_000u32 / 60));
+        let now = clock.now();
+        let released = handler.idle(now);
+        if !released.is_empty() {
+            model.release(&released, dfa.start_state(), now);
+        }
+        model.prune(now);
+        ui::render_frame(&mut canvas, &layout, theme, grammar, dfa, &model, now);
+
+        if let Some((grammar, dfa)) = handler.reload(now) {
+            model.restart(dfa.start_state(), grammar.player_count());
+            reloaded = Some((grammar, dfa));
+        }
+
+        clock.wait_next_frame();
+    }
+    Ok(())
+}
+
+/// Drill mode with SDL: prompts moves from `drill` one at a time until the
+/// session is over, then keeps the summary on screen until the window is
+/// closed. An attempt ends shortly after the prompted sequence is complete,
+/// so that extra inputs are caught, or when the time limit runs out. Inputs
+/// and attempts are timed by `clock`, which also paces the loop.
+pub fn run_drill_loop<S: TimeSource>(
+    grammar: &Grammar,
+    theme: &Theme,
+    drill: &mut Drill,
+    time_limit: Duration,
+    clock: &FrameClock<S>,
+) -> Result<(), Box<dyn Error>> {
+    let (sdl_context, mut canvas) = open_window()?;
+    let (width, height) = canvas.output_size()?;
+    let mut layout = Layout::compute_with_style(width, height, theme.layout);
+
+    let mut event_pump = sdl_context
+        .event_pump()
+        .map_err(|e| format!("Event pump failed: {}", e))?;
+
+    drill.next_prompt(clock.now());
+    'main_loop: loop {
+        for event in event_pump.poll_iter() {
+            match process_event(event, grammar) {
+                InputEvent::Token(key) => {
+                    // Any player's keys stand for the player 1 keys of the moves
+                    let key = grammar
+                        .resolve_key(key.as_str())
+                        .map_or(&key, |(_, key)| key);
+                    drill.input(key, clock.now());
+                }
+                InputEvent::Resized(..) => {
+                    let (width, height) = canvas.output_size()?;
+                    layout = Layout::compute_with_style(width, height, theme.layout);
+                }
+                InputEvent::Quit => break 'main_loop,
+                InputEvent::Scroll(_) | InputEvent::Invalid => {}
+            }
+        }
+
+        if let Some(result) = drill.tick(clock.now(), time_limit) {
+            println!("{}: {}", result.move_name, result.describe(grammar));
+        }
+
+        ui::render_drill_frame(&mut canvas, &layout, theme, grammar, drill);
+        clock.wait_next_frame();
     }
     Ok(())
 }
 
+/// Console drill: prints each prompted move and scores the next line of
+/// input as the attempt, until the session is over, the input ends or a line
+/// reads 'quit' or 'exit'
+pub fn run_drill_console<R: BufRead>(
+    grammar: &Grammar,
+    drill: &mut Drill,
+    reader: R,
+) -> Result<(), Box<dyn Error>> {
+    println!("Drill mode - type the inputs of each move and press Enter, 'quit' to stop");
+    println!();
+
+    let mut lines = reader.lines();
+    while let Some(m) = drill.next_prompt(Instant::now()).cloned() {
+        let count = drill.count().map(|c| format!("/{}", c)).unwrap_or_default();
+        let keys: Vec<String> = m.sequence.iter().map(|k| k.to_string()).collect();
+        println!("Drill {}{}: {}", drill.prompted(), count, m.name);
+        println!(
+            "  {}  ({})",
+            grammar.token_names(&m.sequence).join(", "),
+            keys.join(" ")
+        );
+        print!("> ");
+        io::stdout().flush()?;
+
+        let Some(line) = lines.next() else {
+            println!();
+            break;
+        };
+        let line = line?;
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("exit") {
+            break;
+        }
+
+        let now = Instant::now();
+        for key in grammar.keys_in(line) {
+            if let Some((_, key)) = grammar.resolve_key(key.as_str()) {
+                drill.input(key, now);
+            }
+        }
+        if let Some(result) = drill.finish_attempt() {
+            println!("  {}", result.describe(grammar));
+        }
+        println!();
+    }
+
+    Ok(())
+}
+
 /// Console-only input mode (no SDL GUI)
 /// Reads input from stdin line by line
-pub fn run_console_mode<F>(grammar: &Grammar, mut on_token: F) -> Result<(), Box<dyn Error>>
+pub fn run_console_mode<H>(grammar: &Grammar, handler: &mut H) -> Result<(), Box<dyn Error>>
 where
-    F: FnMut(char, &str),
+    H: TokenHandler,
 {
-    use std::io::{self, BufRead};
+    println!("Console mode - enter keys or 'quit' to exit:");
+    print_valid_keys(grammar);
+    println!();
+
+    let mut reloaded: Option<Grammar> = None;
+    for line in io::stdin().lock().lines() {
+        let line = line?;
+        let line = line.trim();
 
-    println!("Console mode - enter tokens (single characters) or 'quit' to exit:");
+        if line.eq_ignore_ascii_case("quit") || line.eq_ignore_ascii_case("exit") {
+            break;
+        }
+
+        // Keys are looked up in the grammar loaded when the line arrives
+        if let Some((grammar, _)) = handler.reload(Instant::now()) {
+            print_valid_keys(&grammar);
+            reloaded = Some(grammar);
+        }
+        let grammar = reloaded.as_ref().unwrap_or(grammar);
+        for key in grammar.keys_in(line) {
+            if let Some(token_name) = grammar.get_token_for_key(key.as_str()) {
+                handler.token(key, token_name);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn print_valid_keys(grammar: &Grammar) {
     println!(
         "Valid keys: {}",
         grammar
             .mappings
             .keys()
+            .chain(grammar.player_keys.keys())
             .map(|k| k.to_string())
             .collect::<Vec<_>>()
             .join(", ")
     );
-    println!();
+}
 
-    let stdin = io::stdin();
-    for line in stdin.lock().lines() {
+/// Feed every mapped key of `reader` to the callback, line by line, until
+/// the input ends or a line reads 'quit' or 'exit'. Keys are read as
+/// [`Grammar::keys_in`] does; unmapped characters are skipped.
+pub fn feed_lines<R, F>(grammar: &Grammar, reader: R, mut on_token: F) -> Result<(), Box<dyn Error>>
+where
+    R: BufRead,
+    F: FnMut(&Symbol, &str),
+{
+    for line in reader.lines() {
         let line = line?;
         let line = line.trim();
 
@@ -167,10 +374,9 @@ where
             break;
         }
 
-        // Process each character in the line
-        for ch in line.chars() {
-            if let Some(token_name) = grammar.get_token_for_key(ch) {
-                on_token(ch, token_name);
+        for key in grammar.keys_in(line) {
+            if let Some(token_name) = grammar.get_token_for_key(key.as_str()) {
+                on_token(key, token_name);
             }
         }
     }
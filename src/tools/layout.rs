@@ -0,0 +1,209 @@
+use sdl2::rect::Rect;
+
+/// Outer margin between the window edge and the panels
+const MARGIN: i32 = 20;
+/// Gap between neighbouring panels
+const GAP: i32 = 20;
+const HEADER_HEIGHT: u32 = 60;
+const INSTRUCTIONS_HEIGHT: u32 = 80;
+const COMPACT_INSTRUCTIONS_HEIGHT: u32 = 40;
+/// Windows narrower than this stack the panels in two columns instead of three
+const WIDE_LAYOUT_MIN_WIDTH: u32 = 900;
+/// Windows shorter than this get a slimmer instructions bar
+const TALL_LAYOUT_MIN_HEIGHT: u32 = 560;
+
+/// Smallest window the layout is designed for
+pub const MIN_WIDTH: u32 = 640;
+pub const MIN_HEIGHT: u32 = 480;
+
+/// How the body panels are arranged
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LayoutStyle {
+    /// Three columns on wide windows, two columns otherwise
+    #[default]
+    Auto,
+    /// Always three columns: mappings | history over feed | move list
+    Wide,
+    /// Always two columns: mappings over history | feed over move list
+    Compact,
+}
+
+impl LayoutStyle {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "auto" => Some(Self::Auto),
+            "wide" => Some(Self::Wide),
+            "compact" => Some(Self::Compact),
+            _ => None,
+        }
+    }
+}
+
+/// Screen rectangles of every UI panel for a given window size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Layout {
+    pub width: u32,
+    pub height: u32,
+    pub border: Rect,
+    pub header: Rect,
+    /// Whole area between the header and the instructions bar
+    pub body: Rect,
+    pub mappings: Rect,
+    pub history: Rect,
+    pub feed: Rect,
+    pub move_list: Rect,
+    pub instructions: Rect,
+}
+
+/// Split `total` pixels into parts proportional to `weights`, leaving `GAP`
+/// pixels between consecutive parts. Returns (offset, length) pairs.
+fn split(start: i32, total: u32, weights: &[u32]) -> Vec<(i32, u32)> {
+    let gaps = GAP * (weights.len() as i32 - 1).max(0);
+    let available = (total as i32 - gaps).max(weights.len() as i32) as u32;
+    let weight_sum: u32 = weights.iter().sum::<u32>().max(1);
+
+    let mut parts = Vec::with_capacity(weights.len());
+    let mut offset = start;
+    let mut used = 0;
+    for (i, &w) in weights.iter().enumerate() {
+        let len = if i + 1 == weights.len() {
+            available - used
+        } else {
+            (available * w / weight_sum).max(1)
+        };
+        parts.push((offset, len));
+        offset += len as i32 + GAP;
+        used += len;
+    }
+    parts
+}
+
+impl Layout {
+    /// Compute panel positions for a window of `width` x `height` pixels.
+    /// Sizes below [`MIN_WIDTH`] x [`MIN_HEIGHT`] are laid out as if they were
+    /// the minimum size.
+    pub fn compute(width: u32, height: u32) -> Self {
+        Self::compute_with_style(width, height, LayoutStyle::Auto)
+    }
+
+    /// Like [`Layout::compute`] with an explicit panel arrangement
+    pub fn compute_with_style(width: u32, height: u32, style: LayoutStyle) -> Self {
+        let w = width.max(MIN_WIDTH);
+        let h = height.max(MIN_HEIGHT);
+
+        let inner_width = w - 2 * MARGIN as u32;
+        let instructions_height = if h >= TALL_LAYOUT_MIN_HEIGHT {
+            INSTRUCTIONS_HEIGHT
+        } else {
+            COMPACT_INSTRUCTIONS_HEIGHT
+        };
+
+        let border = Rect::new(10, 10, w - 20, h - 20);
+        let header = Rect::new(MARGIN, MARGIN, inner_width, HEADER_HEIGHT);
+        let instructions = Rect::new(
+            MARGIN,
+            h as i32 - MARGIN - instructions_height as i32,
+            inner_width,
+            instructions_height,
+        );
+
+        // Body: everything between the header and the instructions bar
+        let body_top = header.bottom() + GAP;
+        let body_height = (instructions.top() - GAP - body_top).max(1) as u32;
+        let body_left = MARGIN + 10;
+        let body_width = inner_width - 20;
+        let body = Rect::new(body_left, body_top, body_width, body_height);
+
+        let wide = match style {
+            LayoutStyle::Auto => w >= WIDE_LAYOUT_MIN_WIDTH,
+            LayoutStyle::Wide => true,
+            LayoutStyle::Compact => false,
+        };
+        let (mappings, history, feed, move_list) = if wide {
+            // mappings | history over feed | move list
+            let cols = split(body_left, body_width, &[20, 35, 36]);
+            let rows = split(body_top, body_height, &[60, 40]);
+            (
+                Rect::new(cols[0].0, body_top, cols[0].1, body_height),
+                Rect::new(cols[1].0, rows[0].0, cols[1].1, rows[0].1),
+                Rect::new(cols[1].0, rows[1].0, cols[1].1, rows[1].1),
+                Rect::new(cols[2].0, body_top, cols[2].1, body_height),
+            )
+        } else {
+            // mappings over history | feed over move list
+            let cols = split(body_left, body_width, &[45, 55]);
+            let left = split(body_top, body_height, &[55, 45]);
+            let right = split(body_top, body_height, &[30, 70]);
+            (
+                Rect::new(cols[0].0, left[0].0, cols[0].1, left[0].1),
+                Rect::new(cols[0].0, left[1].0, cols[0].1, left[1].1),
+                Rect::new(cols[1].0, right[0].0, cols[1].1, right[0].1),
+                Rect::new(cols[1].0, right[1].0, cols[1].1, right[1].1),
+            )
+        };
+
+        Self {
+            width: w,
+            height: h,
+            border,
+            header,
+            body,
+            mappings,
+            history,
+            feed,
+            move_list,
+            instructions,
+        }
+    }
+
+    /// All body panels, used to check they do not overlap
+    pub fn panels(&self) -> [Rect; 4] {
+        [self.mappings, self.history, self.feed, self.move_list]
+    }
+}
+
+/// How many rows of `row_height` fit in `height` pixels below a `top_padding`
+pub fn rows_that_fit(height: u32, top_padding: u32, row_height: u32) -> usize {
+    (height.saturating_sub(top_padding) / row_height.max(1)) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_contained(layout: &Layout) {
+        let window = Rect::new(0, 0, layout.width, layout.height);
+        let body = [layout.header, layout.instructions];
+        for (i, a) in layout.panels().iter().enumerate() {
+            assert!(window.contains_rect(*a), "{a:?} outside window");
+            for b in body.iter() {
+                assert!(!a.has_intersection(*b), "{a:?} overlaps {b:?}");
+            }
+            for b in layout.panels().iter().skip(i + 1) {
+                assert!(!a.has_intersection(*b), "{a:?} overlaps {b:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn panels_fit_without_overlap_at_common_sizes() {
+        for (w, h) in [
+            (640, 480),
+            (800, 600),
+            (1000, 600),
+            (1280, 720),
+            (1920, 1080),
+        ] {
+            for style in [LayoutStyle::Auto, LayoutStyle::Wide, LayoutStyle::Compact] {
+                assert_contained(&Layout::compute_with_style(w, h, style));
+            }
+        }
+    }
+
+    #[test]
+    fn tiny_windows_are_clamped_to_minimum_size() {
+        let layout = Layout::compute(100, 50);
+        assert_eq!((layout.width, layout.height), (MIN_WIDTH, MIN_HEIGHT));
+        assert_contained(&layout);
+    }
+}
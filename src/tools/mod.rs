@@ -3,5 +3,25 @@ pub use dfa::{DFAConfig, DFA};
 pub mod parsing;
 pub use parsing::parse_grammar_file;
 pub mod keycatcher;
-pub use keycatcher::{run_console_mode, run_input_loop};
+pub use keycatcher::{
+    feed_lines, run_console_mode, run_drill_console, run_drill_loop, run_input_loop, TokenHandler,
+};
+pub mod recognizer;
+pub use recognizer::{Recognizer, StepEvent};
+pub mod font;
+pub mod layout;
+pub mod theme;
+pub use theme::Theme;
+pub mod cli;
+pub mod clock;
+pub mod document;
+pub mod drill;
+pub mod export;
+pub mod format;
+pub mod generate;
+pub mod history;
+pub mod reload;
+pub mod rng;
+pub mod stats;
+pub mod symbol;
 pub mod ui;
@@ -1,29 +1,194 @@
-use std::collections::BTreeMap;
-use std::fs::read_to_string;
-use std::path::Path;
+use crate::tools::clock::TimingWindow;
+use crate::tools::dfa::Symbol;
+use crate::tools::document::{self, GrammarFormat};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{self, read_to_string};
+use std::path::{Path, PathBuf};
 
-/// A parsed grammar: mapping single-character keys to token names
-/// and a list of moves (each move has a name and a sequence of chars).
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Kind of move, as the games group them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MoveCategory {
+    Normal,
+    Special,
+    Enhanced,
+    #[serde(rename = "x-ray")]
+    XRay,
+    Fatality,
+}
+
+impl MoveCategory {
+    pub const ALL: [MoveCategory; 5] = [
+        MoveCategory::Normal,
+        MoveCategory::Special,
+        MoveCategory::Enhanced,
+        MoveCategory::XRay,
+        MoveCategory::Fatality,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MoveCategory::Normal => "normal",
+            MoveCategory::Special => "special",
+            MoveCategory::Enhanced => "enhanced",
+            MoveCategory::XRay => "x-ray",
+            MoveCategory::Fatality => "fatality",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|c| c.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// What a trainer may know about a move besides its inputs; every field is
+/// optional
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct MoveMetadata {
+    /// Damage dealt, in percent of a health bar
+    pub damage: Option<u32>,
+    /// Frames before the move can hit
+    pub startup: Option<u32>,
+    /// Frames during which the move can hit
+    pub active: Option<u32>,
+    /// Frames before the player can act again
+    pub recovery: Option<u32>,
+    /// Bars of super meter spent
+    pub meter: Option<u32>,
+    pub category: Option<MoveCategory>,
+    pub notes: Option<String>,
+}
+
+impl MoveMetadata {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Frame data as startup/active/recovery, `-` for unknown parts
+    pub fn frames(&self) -> Option<String> {
+        let frames = [self.startup, self.active, self.recovery];
+        frames.iter().any(Option::is_some).then(|| {
+            frames
+                .iter()
+                .map(|f| f.map_or("-".to_string(), |f| f.to_string()))
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+    }
+
+    /// Damage and frame data in a few characters, e.g. "12% 14/3/30F", for
+    /// places with little room
+    pub fn tag(&self) -> String {
+        let damage = self.damage.map(|d| format!("{}%", d));
+        let frames = self.frames().map(|f| format!("{}F", f));
+        damage
+            .into_iter()
+            .chain(frames)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// One line describing everything but the notes, e.g.
+    /// "special, 12% damage, 14/3/30 frames, 1 bar"
+    pub fn summary(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(category) = self.category {
+            parts.push(category.name().to_string());
+        }
+        if let Some(damage) = self.damage {
+            parts.push(format!("{}% damage", damage));
+        }
+        if let Some(frames) = self.frames() {
+            parts.push(format!("{} frames", frames));
+        }
+        match self.meter {
+            Some(1) => parts.push("1 bar".to_string()),
+            Some(bars) => parts.push(format!("{} bars", bars)),
+            None => {}
+        }
+        parts.join(", ")
+    }
+}
+
+/// A parsed grammar: mapping keys to token names and a list of moves (each
+/// move has a name and a sequence of keys).
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct MoveDef {
     pub name: String,
-    pub sequence: Vec<char>,
+    pub sequence: Vec<Symbol>,
+    /// Preference among moves that could match the same inputs, used by the
+    /// priority match policy (default 0)
+    pub priority: i32,
+    pub metadata: MoveMetadata,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+impl MoveDef {
+    /// Character the move belongs to, taken from a trailing `(Name)` in the
+    /// move name (e.g. "Claw Slam (Freddy Krueger)")
+    pub fn character(&self) -> Option<&str> {
+        let name = self.name.trim_end();
+        let inner = name.strip_suffix(')')?;
+        let open = inner.rfind('(')?;
+        let character = inner[open + 1..].trim();
+        if character.is_empty() {
+            None
+        } else {
+            Some(character)
+        }
+    }
+
+    /// Move name without the character suffix
+    pub fn short_name(&self) -> &str {
+        match self.character() {
+            Some(_) => self.name[..self.name.rfind('(').unwrap_or(self.name.len())].trim(),
+            None => self.name.trim(),
+        }
+    }
+}
+
+/// Inputs the recognizer lets slide, so that a move does not have to list
+/// every way players enter it. Keys are player 1 keys, like move sequences.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Leniency {
+    /// Keys that may come between two inputs of a move without breaking it
+    pub ignore: BTreeSet<Symbol>,
+    /// Keys a move may go without, such as the diagonal of a quarter circle
+    pub optional: BTreeSet<Symbol>,
+    /// Time a move may take from its first input to its last, unlimited
+    /// when `None`
+    pub buffer: Option<TimingWindow>,
+    /// Time a held-back move waits for the next input of a longer one, the
+    /// front-end default when `None`
+    pub window: Option<TimingWindow>,
+}
+
+impl Leniency {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct Grammar {
-    /// key -> token name (e.g. 'i' -> "[BK]")
-    pub mappings: BTreeMap<char, String>,
+    /// key -> token name (e.g. "i" -> "[BK]")
+    pub mappings: BTreeMap<Symbol, String>,
     /// moves found in the grammar (may be empty)
     pub moves: Vec<MoveDef>,
+    /// keys of players 2 and up -> (player, player 1 key with the same token)
+    pub player_keys: BTreeMap<Symbol, (usize, Symbol)>,
+    pub leniency: Leniency,
 }
 
+/// Highest player number a grammar may declare
+pub const MAX_PLAYERS: usize = 4;
+
 impl Grammar {
     pub fn new() -> Self {
-        Self {
-            mappings: BTreeMap::new(),
-            moves: Vec::new(),
-        }
+        Self::default()
     }
 
     /// Display key mappings in the format shown in README examples
@@ -32,95 +197,559 @@ impl Grammar {
         for (key, token_name) in &self.mappings {
             println!("{} -> {}", key, token_name);
         }
+        for player in 2..=self.player_count() {
+            println!("Player {}:", player);
+            for (key, (_, base)) in self.player_keys.iter().filter(|(_, (p, _))| *p == player) {
+                println!("{} -> {}", key, self.mappings[base]);
+            }
+        }
         println!("----------------------");
     }
 
-    /// Get the token name for a keyboard character
-    pub fn get_token_for_key(&self, key: char) -> Option<&str> {
-        self.mappings.get(&key).map(|s| s.as_str())
+    /// Moves grouped by the character they belong to. Moves without a
+    /// character are grouped under `None`, which sorts first.
+    pub fn moves_by_character(&self) -> BTreeMap<Option<&str>, Vec<&MoveDef>> {
+        let mut groups: BTreeMap<Option<&str>, Vec<&MoveDef>> = BTreeMap::new();
+        for m in &self.moves {
+            groups.entry(m.character()).or_default().push(m);
+        }
+        groups
+    }
+
+    /// Get the token name for a key, of any player
+    pub fn get_token_for_key(&self, key: &str) -> Option<&str> {
+        let (_, key) = self.resolve_key(key)?;
+        self.mappings.get(key).map(|s| s.as_str())
+    }
+
+    /// Number of players with key bindings; player 1 uses the plain mappings
+    pub fn player_count(&self) -> usize {
+        self.player_keys
+            .values()
+            .map(|&(player, _)| player)
+            .max()
+            .unwrap_or(1)
+    }
+
+    /// Player pressing `key` and the player 1 key it stands for, which is
+    /// the key used by move sequences
+    pub fn resolve_key(&self, key: &str) -> Option<(usize, &Symbol)> {
+        match self.mappings.get_key_value(key) {
+            Some((key, _)) => Some((1, key)),
+            None => self
+                .player_keys
+                .get(key)
+                .map(|(player, base)| (*player, base)),
+        }
+    }
+
+    /// Keys of any player typed in `text`, in order. Keys may follow each
+    /// other without spaces: the longest key at each position is taken, and
+    /// characters that start no key are skipped.
+    pub fn keys_in<'a>(&'a self, text: &str) -> Vec<&'a Symbol> {
+        let mut keys = Vec::new();
+        let mut rest = text;
+        while let Some(ch) = rest.chars().next() {
+            let longest = self
+                .mappings
+                .keys()
+                .chain(self.player_keys.keys())
+                .filter(|key| rest.starts_with(key.as_str()))
+                .max_by_key(|key| key.as_str().len());
+            match longest {
+                Some(key) => {
+                    keys.push(key);
+                    rest = &rest[key.as_str().len()..];
+                }
+                None => rest = &rest[ch.len_utf8()..],
+            }
+        }
+        keys
     }
+
+    /// Add a key binding of `player`. Player 1 defines tokens; other players
+    /// bind their own keys to tokens player 1 already has.
+    pub(crate) fn bind_key(
+        &mut self,
+        player: usize,
+        key: Symbol,
+        token: &str,
+    ) -> Result<(), String> {
+        if self
+            .resolve_key(key.as_str())
+            .is_some_and(|(p, _)| p != player)
+        {
+            return Err(format!("key '{}' is already bound to another player", key));
+        }
+        if player == 1 {
+            self.mappings.insert(key, token.to_string());
+            return Ok(());
+        }
+        let base = self
+            .mappings
+            .iter()
+            .find(|(_, name)| name.as_str() == token)
+            .map(|(base, _)| base.clone())
+            .ok_or_else(|| {
+                format!(
+                    "player {} key '{}' maps to '{}', which player 1 does not define",
+                    player, key, token
+                )
+            })?;
+        self.player_keys.insert(key, (player, base));
+        Ok(())
+    }
+
+    /// Token names of a key sequence; unmapped keys are shown as "?"
+    pub fn token_names(&self, sequence: &[Symbol]) -> Vec<&str> {
+        sequence
+            .iter()
+            .map(|k| self.get_token_for_key(k.as_str()).unwrap_or("?"))
+            .collect()
+    }
+}
+
+/// Apply one `attribute=value` of a move definition
+fn parse_move_attribute(m: &mut MoveDef, attribute: &str) -> Result<(), String> {
+    let (name, value) = attribute
+        .split_once('=')
+        .ok_or_else(|| format!("move attribute must be name=value: '{}'", attribute))?;
+    let count = |value: &str| {
+        value
+            .parse()
+            .map_err(|_| format!("{} must be a whole number: '{}'", name, value))
+    };
+    let metadata = &mut m.metadata;
+    match name {
+        "priority" => {
+            m.priority = value
+                .parse()
+                .map_err(|_| format!("priority must be a whole number: '{}'", value))?;
+        }
+        "damage" => metadata.damage = Some(count(value)?),
+        "startup" => metadata.startup = Some(count(value)?),
+        "active" => metadata.active = Some(count(value)?),
+        "recovery" => metadata.recovery = Some(count(value)?),
+        "meter" => metadata.meter = Some(count(value)?),
+        "category" => {
+            metadata.category = Some(MoveCategory::from_name(value).ok_or_else(|| {
+                let names: Vec<&str> = MoveCategory::ALL.iter().map(|c| c.name()).collect();
+                format!(
+                    "unknown category '{}': expected one of {}",
+                    value,
+                    names.join(", ")
+                )
+            })?);
+        }
+        "notes" => {
+            metadata.notes = Some(unquote(value)?.into_owned()).filter(|notes| !notes.is_empty())
+        }
+        _ => return Err(format!("unknown move attribute '{}'", name)),
+    }
+    Ok(())
 }
 
-/// Parse a `.gmr` grammar file. Currently supports two line formats:
+/// Split the attributes of a move line into `name=value` words and the
+/// free text of a trailing `notes=`
+fn split_notes(attributes: &str) -> (&str, Option<&str>) {
+    let mut from = 0;
+    while let Some(pos) = attributes[from..].find("notes=") {
+        let pos = from + pos;
+        if attributes[..pos].ends_with(char::is_whitespace) || pos == 0 {
+            return (&attributes[..pos], Some(&attributes[pos..]));
+        }
+        from = pos + 1;
+    }
+    (attributes, None)
+}
+
+/// Parse a `.gmr` grammar file. Currently supports these line formats:
 ///
 /// 1) key, Name
-///    - maps a single-character `key` to a token name
+///    - maps `key` to a token name. Keys are single characters (`s`, `é`)
+///      or names (`df`, `LeftShift`) without spaces.
 /// 2) Name: k k k
-///    - (optional) move definitions where tokens are single-character keys
+///    - (optional) move definitions, keys separated by spaces
+///    - may end with `| attribute=value ...`, e.g. `| priority=2`. Besides
+///      `priority`, the attributes are the move metadata: `damage`,
+///      `startup`, `active`, `recovery`, `meter`, `category` and `notes`,
+///      whose text runs to the end of the line.
+/// 3) player N
+///    - the mappings that follow bind the keys of player N (up to
+///      [`MAX_PLAYERS`]) to token names of player 1. Moves are always
+///      written with player 1 keys.
+/// 4) include "file.gmr"
+///    - parses another grammar file in place, relative to the including
+///      file. Its player sections end with it.
+/// 5) ignore k k, optional k k, buffer 20f, window 15f
+///    - leniency rules, see [`Leniency`]: keys that may come between the
+///      inputs of a move, keys a move may skip, the time a move may take and
+///      the time a held-back move waits for a longer one, in milliseconds
+///      (`300` or `300ms`) or frames (`20f`)
 ///
-/// Lines starting with `#` or empty lines are ignored.
+/// Keys and names holding `, : | # " \` are written in double quotes, as
+/// in `":", Colon` or `"Kano: Ball": s d o`, or with a backslash before each
+/// such character, as in `\:, Colon`. A backslash also escapes `"` and `\`
+/// inside quotes.
+///
+/// Lines starting with `#` or empty lines are ignored, and so is the rest of
+/// a line from a `#` following a space. Files ending in `.toml` or `.json`
+/// are read as grammar documents instead, see [`crate::tools::document`].
 pub fn parse_grammar_file<P: AsRef<Path>>(path: P) -> Result<Grammar, String> {
-    let mut grammar = Grammar::new();
+    parse_grammar_with_sources(path).0
+}
+
+/// Like [`parse_grammar_file`], also returning every file it tried to read
+/// in include order, even when parsing fails
+pub fn parse_grammar_with_sources<P: AsRef<Path>>(
+    path: P,
+) -> (Result<Grammar, String>, Vec<PathBuf>) {
     let path_ref = path.as_ref();
-    let contents = read_to_string(path_ref).map_err(|e| {
-        format!(
-            "Failed to read grammar file '{}': {}",
-            path_ref.display(),
-            e
-        )
-    })?;
+    let mut sources = vec![path_ref.to_path_buf()];
+    let format = GrammarFormat::from_path(path_ref);
+    let result = read_grammar_source(path_ref).and_then(|contents| {
+        if format != GrammarFormat::Gmr {
+            return document::parse_grammar_document(&contents, format)
+                .map_err(|e| format!("Invalid grammar file '{}': {}", path_ref.display(), e));
+        }
+        let mut grammar = Grammar::new();
+        parse_source(
+            &mut grammar,
+            path_ref,
+            &contents,
+            &mut Vec::new(),
+            &mut sources,
+        )?;
+        Ok(grammar)
+    });
+    (result, sources)
+}
 
-    for (lineno, line) in contents.lines().enumerate() {
-        let line = line.trim();
-        if line.is_empty() || line.starts_with('#') {
-            continue;
-        }
-
-        // mapping: single_char, Name
-        if let Some(pos) = line.find(',') {
-            let (left, right) = line.split_at(pos);
-            let left = left.trim();
-            let right = right[1..].trim(); // skip comma
-            if left.len() != 1 {
-                return Err(format!(
-                    "{}: left side must be a single char key",
-                    lineno + 1
-                ));
-            }
-            let key = left.chars().next().unwrap();
-            grammar.mappings.insert(key, right.to_string());
-            continue;
-        }
-
-        // move: Name: keys...
-        if let Some(pos) = line.find(':') {
-            let (name, seq) = line.split_at(pos);
-            let name = name.trim();
-            let seq = seq[1..].trim();
-            // tokens separated by whitespace
-            let mut sequence = Vec::new();
-            for tok in seq.split_whitespace() {
-                if tok.len() != 1 {
-                    return Err(format!(
-                        "{}: move token must be a single character key: '{}'",
-                        lineno + 1,
-                        tok
-                    ));
-                }
-                sequence.push(tok.chars().next().unwrap());
+fn read_grammar_source(path: &Path) -> Result<String, String> {
+    read_to_string(path)
+        .map_err(|e| format!("Failed to read grammar file '{}': {}", path.display(), e))
+}
+
+/// One line of a `.gmr` file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum GmrLine<'a> {
+    Blank,
+    Comment(&'a str),
+    /// include "file.gmr"
+    Include(&'a str),
+    /// player N
+    Player(usize),
+    /// key, Name (or the lenient `k Name` for single-character keys)
+    Mapping(Symbol, Cow<'a, str>),
+    /// Name: k k k | attribute=value ...
+    Move(MoveDef),
+    /// ignore k k
+    Ignore(Vec<Symbol>),
+    /// optional k k
+    Optional(Vec<Symbol>),
+    /// buffer 20f
+    Buffer(TimingWindow),
+    /// window 15f
+    Window(TimingWindow),
+}
+
+/// Characters with a meaning in `.gmr` lines, which keys and names can only
+/// hold when quoted or escaped
+const SPECIAL: [char; 6] = [',', ':', '|', '#', '"', '\\'];
+
+/// Byte offset of the first character of `text` outside quotes and not
+/// escaped for which `found` holds. `found` also gets the text before it.
+fn find_unquoted(text: &str, mut found: impl FnMut(char, &str) -> bool) -> Option<usize> {
+    let (mut quoted, mut escaped) = (false, false);
+    for (i, ch) in text.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if ch == '\\' {
+            escaped = true;
+        } else if ch == '"' {
+            quoted = !quoted;
+        } else if !quoted && found(ch, &text[..i]) {
+            return Some(i);
+        }
+    }
+    None
+}
+
+/// Split a line at its inline comment: a `#` outside quotes at the start of
+/// the line or after whitespace
+pub(crate) fn split_comment(line: &str) -> (&str, Option<&str>) {
+    let start = find_unquoted(line, |ch, before| {
+        ch == '#' && (before.is_empty() || before.ends_with(char::is_whitespace))
+    });
+    match start {
+        Some(start) => (line[..start].trim_end(), Some(&line[start..])),
+        None => (line, None),
+    }
+}
+
+/// Text of a key or name as written, without its quotes and escapes
+fn unquote(text: &str) -> Result<Cow<'_, str>, String> {
+    let text = text.trim();
+    if !text.contains(['"', '\\']) {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut out = String::with_capacity(text.len());
+    let (mut quoted, mut chars) = (false, text.chars());
+    while let Some(ch) = chars.next() {
+        match ch {
+            '"' => quoted = !quoted,
+            '\\' => match chars.next() {
+                Some(escaped) => out.push(escaped),
+                None => return Err(format!("nothing to escape at the end of '{}'", text)),
+            },
+            _ => out.push(ch),
+        }
+    }
+    if quoted {
+        return Err(format!("unterminated quote in '{}'", text));
+    }
+    Ok(Cow::Owned(out))
+}
+
+/// `text` as a `.gmr` key or name: as it is, or in quotes when it holds
+/// special characters or surrounding spaces
+pub(crate) fn quote(text: &str) -> Cow<'_, str> {
+    quote_if(text, &SPECIAL)
+}
+
+/// Notes as written after `notes=`, which run to the end of the line and so
+/// only need quotes for comments, quotes and backslashes
+pub(crate) fn quote_notes(notes: &str) -> Cow<'_, str> {
+    quote_if(notes, &['#', '"', '\\'])
+}
+
+fn quote_if<'a>(text: &'a str, special: &[char]) -> Cow<'a, str> {
+    if !text.is_empty() && !text.contains(special) && text.trim() == text {
+        return Cow::Borrowed(text);
+    }
+    let escaped = text.replace('\\', "\\\\").replace('"', "\\\"");
+    Cow::Owned(format!("\"{}\"", escaped))
+}
+
+/// Keys separated by whitespace outside quotes
+fn parse_keys(text: &str) -> Result<Vec<Symbol>, String> {
+    let mut keys = Vec::new();
+    let mut rest = text.trim_start();
+    while !rest.is_empty() {
+        let end = find_unquoted(rest, |ch, _| ch.is_whitespace()).unwrap_or(rest.len());
+        keys.push(Symbol::new(&unquote(&rest[..end])?)?);
+        rest = rest[end..].trim_start();
+    }
+    Ok(keys)
+}
+
+/// Whether `text` holds a ',' or ':' outside quotes
+fn has_separator(text: &str) -> bool {
+    find_unquoted(text, |ch, _| ch == ',' || ch == ':').is_some()
+}
+
+/// Keys of a leniency rule
+fn rule_keys(rule: &str, keys: &str) -> Result<Vec<Symbol>, String> {
+    let keys = parse_keys(keys).map_err(|e| format!("{}: {}", rule, e))?;
+    if keys.is_empty() {
+        return Err(format!("{} needs at least one key", rule));
+    }
+    Ok(keys)
+}
+
+/// File named by an `include` line, with or without quotes
+fn include_target(line: &str) -> Option<&str> {
+    let target = line.strip_prefix("include ")?.trim();
+    match target.strip_prefix('"') {
+        Some(quoted) => quoted.strip_suffix('"'),
+        None => (!has_separator(target)).then_some(target),
+    }
+}
+
+/// Tell what one line of a grammar file is. Errors carry no line number.
+pub(crate) fn parse_line(line: &str) -> Result<GmrLine<'_>, String> {
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(GmrLine::Blank);
+    }
+    if line.starts_with('#') {
+        return Ok(GmrLine::Comment(line));
+    }
+    let (line, _) = split_comment(line);
+    // An open quote would hide the separators that follow it
+    unquote(line)?;
+
+    // include "file.gmr", relative to this file
+    if let Some(target) = include_target(line) {
+        if target.is_empty() {
+            return Err("include needs a file name".to_string());
+        }
+        return Ok(GmrLine::Include(target));
+    }
+
+    // player section: player N
+    if let Some(number) = line
+        .strip_prefix("player ")
+        .filter(|rest| !has_separator(rest))
+    {
+        return match number.trim().parse() {
+            Ok(n) if (1..=MAX_PLAYERS).contains(&n) => Ok(GmrLine::Player(n)),
+            _ => Err(format!(
+                "player must be a number from 1 to {}: '{}'",
+                MAX_PLAYERS,
+                number.trim()
+            )),
+        };
+    }
+
+    // leniency rules: ignore k k, optional k k, buffer 20f, window 15f
+    if let Some((rule, rest)) = line
+        .split_once(char::is_whitespace)
+        .filter(|(_, rest)| !has_separator(rest))
+    {
+        match rule {
+            "ignore" => return rule_keys(rule, rest).map(GmrLine::Ignore),
+            "optional" => return rule_keys(rule, rest).map(GmrLine::Optional),
+            "buffer" | "window" => {
+                let window = TimingWindow::parse(rest).ok_or_else(|| {
+                    format!(
+                        "{} must be milliseconds or frames like 20f: '{}'",
+                        rule,
+                        rest.trim()
+                    )
+                })?;
+                return Ok(match rule {
+                    "buffer" => GmrLine::Buffer(window),
+                    _ => GmrLine::Window(window),
+                });
             }
-            grammar.moves.push(MoveDef {
-                name: name.to_string(),
-                sequence,
-            });
-            continue;
+            _ => {}
         }
+    }
 
-        // Unknown line format — be lenient and try `key name` (two parts)
-        let parts: Vec<&str> = line.split_whitespace().collect();
-        if parts.len() == 2 && parts[0].len() == 1 {
-            grammar
-                .mappings
-                .insert(parts[0].chars().next().unwrap(), parts[1].to_string());
-            continue;
+    // mapping: key, Name; a ':' before the comma makes it a move
+    let separator = find_unquoted(line, |ch, _| ch == ',' || ch == ':');
+    if let Some(pos) = separator.filter(|&pos| line[pos..].starts_with(',')) {
+        let (left, right) = line.split_at(pos);
+        let key = Symbol::new(&unquote(left)?)?;
+        return Ok(GmrLine::Mapping(key, unquote(&right[1..])?)); // skip comma
+    }
+
+    // move: Name: keys...
+    if let Some(pos) = separator {
+        let (name, seq) = line.split_at(pos);
+        let name = unquote(name)?;
+        if name.is_empty() {
+            return Err(format!(
+                "move name must not be empty in '{}'; write a ':' key in quotes or \
+                 escaped, as in \":\", Colon or \\:, Colon",
+                line
+            ));
+        }
+        let seq = &seq[1..];
+        let (seq, attributes) = match find_unquoted(seq, |ch, _| ch == '|') {
+            Some(bar) => (&seq[..bar], Some(&seq[bar + 1..])),
+            None => (seq, None),
+        };
+        if let Some(at) = find_unquoted(seq, |ch, _| ch == ',' || ch == ':') {
+            return Err(format!(
+                "move '{}': unquoted '{}' among its keys; write names and keys holding ':' \
+                 or ',' in quotes, as in \"Kano: Ball\": s d o",
+                name,
+                &seq[at..at + 1]
+            ));
+        }
+        let sequence = parse_keys(seq).map_err(|e| format!("move '{}': {}", name, e))?;
+        let mut m = MoveDef {
+            name: name.into_owned(),
+            sequence,
+            ..MoveDef::default()
+        };
+        let (attributes, notes) = split_notes(attributes.unwrap_or(""));
+        for attribute in attributes.split_whitespace() {
+            parse_move_attribute(&mut m, attribute)?;
         }
+        if let Some(notes) = notes {
+            parse_move_attribute(&mut m, notes.trim())?;
+        }
+        return Ok(GmrLine::Move(m));
+    }
 
-        return Err(format!("{}: unrecognized line: '{}'", lineno + 1, line));
+    // Unknown line format — be lenient and try `k name` (two parts)
+    let parts: Vec<&str> = line.split_whitespace().collect();
+    if parts.len() == 2 && parts[0].chars().count() == 1 {
+        return Ok(GmrLine::Mapping(Symbol::new(parts[0])?, unquote(parts[1])?));
     }
 
+    Err(format!("unrecognized line: '{}'", line))
+}
+
+/// Parse `.gmr` text read from `path`, which only serves to find the files
+/// it includes
+pub fn parse_grammar_text<P: AsRef<Path>>(contents: &str, path: P) -> Result<Grammar, String> {
+    let mut grammar = Grammar::new();
+    let path_ref = path.as_ref();
+    parse_source(
+        &mut grammar,
+        path_ref,
+        contents,
+        &mut Vec::new(),
+        &mut vec![path_ref.to_path_buf()],
+    )?;
     Ok(grammar)
 }
 
+/// Parse the lines of one grammar file into `grammar`. `chain` holds the
+/// files being parsed, outermost first, as (canonical path, shown path) to
+/// catch include cycles. Included files are added to `sources`.
+fn parse_source(
+    grammar: &mut Grammar,
+    path: &Path,
+    contents: &str,
+    chain: &mut Vec<(PathBuf, PathBuf)>,
+    sources: &mut Vec<PathBuf>,
+) -> Result<(), String> {
+    let canonical = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    chain.push((canonical, path.to_path_buf()));
+    let mut player = 1;
+    for (lineno, line) in contents.lines().enumerate() {
+        let at_line = |e: String| format!("{}: {}", lineno + 1, e);
+        match parse_line(line).map_err(at_line)? {
+            GmrLine::Blank | GmrLine::Comment(_) => {}
+            GmrLine::Include(target) => {
+                let included = path.parent().unwrap_or(Path::new("")).join(target);
+                let canonical = fs::canonicalize(&included).unwrap_or_else(|_| included.clone());
+                if let Some(pos) = chain.iter().position(|(seen, _)| *seen == canonical) {
+                    let cycle: Vec<String> = chain[pos..]
+                        .iter()
+                        .map(|(_, shown)| shown.display().to_string())
+                        .chain([included.display().to_string()])
+                        .collect();
+                    return Err(at_line(format!("include cycle: {}", cycle.join(" -> "))));
+                }
+                if !sources.contains(&included) {
+                    sources.push(included.clone());
+                }
+                let source = read_grammar_source(&included).map_err(at_line)?;
+                parse_source(grammar, &included, &source, chain, sources)
+                    .map_err(|e| at_line(format!("in '{}': {}", included.display(), e)))?;
+            }
+            GmrLine::Player(n) => player = n,
+            GmrLine::Mapping(key, token) => {
+                grammar.bind_key(player, key, &token).map_err(at_line)?
+            }
+            GmrLine::Move(m) => grammar.moves.push(m),
+            GmrLine::Ignore(keys) => grammar.leniency.ignore.extend(keys),
+            GmrLine::Optional(keys) => grammar.leniency.optional.extend(keys),
+            GmrLine::Buffer(window) => grammar.leniency.buffer = Some(window),
+            GmrLine::Window(window) => grammar.leniency.window = Some(window),
+        }
+    }
+
+    chain.pop();
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -133,9 +762,32 @@ mod tests {
         path.push("grammars/mk9.gmr");
         let g = parse_grammar_file(path).expect("parse mk9");
         // file contains at least these mappings
-        assert_eq!(g.mappings.get(&'w').map(|s| s.as_str()), Some("Up"));
-        assert_eq!(g.mappings.get(&'i').map(|s| s.as_str()), Some("[BK]"));
+        assert_eq!(g.mappings.get("w").map(|s| s.as_str()), Some("Up"));
+        assert_eq!(g.mappings.get("i").map(|s| s.as_str()), Some("[BK]"));
         // no moves in this simple grammar file
         assert!(g.moves.is_empty());
     }
+
+    #[test]
+    fn group_moves_by_character() {
+        let mut path = current_dir().unwrap();
+        path.push("grammars/mk9_with_moves.gmr");
+        let g = parse_grammar_file(path).expect("parse mk9_with_moves");
+
+        let saibot = &g.moves[3];
+        assert_eq!(saibot.character(), Some("Noob Saibot"));
+        assert_eq!(saibot.short_name(), "Saibot Blast");
+
+        let groups = g.moves_by_character();
+        assert_eq!(groups.len(), 7);
+        assert_eq!(groups[&Some("Jax")][0].short_name(), "Active Duty");
+
+        let plain = MoveDef {
+            name: "Uppercut".to_string(),
+            sequence: vec!['s'.into(), 'l'.into()],
+            ..MoveDef::default()
+        };
+        assert_eq!(plain.character(), None);
+        assert_eq!(plain.short_name(), "Uppercut");
+    }
 }
@@ -0,0 +1,452 @@
+use crate::tools::clock::{Frame, FrameClock, TimeSource};
+use crate::tools::dfa::{DFAConfig, MatchPolicy, State, Symbol, DFA};
+use crate::tools::history::{InputHistory, InputRecord};
+use crate::tools::parsing::{Grammar, MoveMetadata};
+use std::ops::Range;
+use std::time::Instant;
+
+/// Everything that happened while feeding a single token to a [`Recognizer`].
+/// Front-ends (console printer, SDL overlay) are driven by these events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepEvent {
+    /// Player whose recognizer handled the token, from 1
+    pub player: usize,
+    /// Key that was pressed
+    pub key: Symbol,
+    /// Token name the key maps to in the grammar
+    pub token: String,
+    /// State before the token was processed
+    pub from: State,
+    /// State after the token was processed
+    pub to: State,
+    /// Moves completed by this token
+    pub matches: Vec<String>,
+    /// Metadata of each completed move, in the order of `matches`; empty
+    /// for moves without any
+    pub metadata: Vec<MoveMetadata>,
+    /// Inputs that made each completed move, in the order of `matches`, as
+    /// indices into the recognizer's [`InputHistory`]. Ignored inputs along
+    /// the way are part of the span.
+    pub spans: Vec<Range<u64>>,
+    /// Index of the token in the recognizer's [`InputHistory`]
+    pub input: u64,
+    /// True when the token broke the current sequence and the recognizer
+    /// restarted from the start state
+    pub reset: bool,
+    /// Why the broken sequence did not complete a move, if one was underway
+    pub near_miss: Option<NearMiss>,
+    /// Game tick the token was pressed on, when fed through a [`FrameClock`]
+    pub frame: Option<Frame>,
+    /// The token did not continue the sequence, but the grammar lets it
+    /// come between two inputs, so the sequence carries on
+    pub ignored: bool,
+    /// Optional key the sequence went without to take this token
+    pub skipped: Option<Symbol>,
+}
+
+/// A sequence that was on its way to a move when a token broke it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearMiss {
+    /// State the sequence was broken in
+    pub state: State,
+    /// Keys that would have continued a move
+    pub expected: Vec<Symbol>,
+    /// Key that broke the sequence
+    pub got: Symbol,
+    /// Moves whose prefix was being followed, nearest first
+    pub following: Vec<String>,
+    /// Inputs of the broken sequence, as indices into the recognizer's
+    /// [`InputHistory`]
+    pub inputs: Range<u64>,
+}
+
+impl NearMiss {
+    /// Explain the miss from `state` on `got`. Returns `None` when no move
+    /// was underway: at the start state, or when the inputs so far already
+    /// completed a move.
+    pub fn new(dfa: &DFA, state: &State, got: Symbol, inputs: Range<u64>) -> Option<Self> {
+        if state == dfa.start_state() || dfa.get_matches(state).is_some() {
+            return None;
+        }
+        let following: Vec<String> = dfa
+            .pending_moves(state)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        if following.is_empty() {
+            return None;
+        }
+        Some(Self {
+            state: state.clone(),
+            expected: dfa.expected_symbols(state),
+            got,
+            following,
+            inputs,
+        })
+    }
+
+    /// Token names of the inputs of the broken sequence still in `history`
+    pub fn entered<'a>(&self, history: &'a InputHistory) -> Vec<&'a str> {
+        history.tokens(self.inputs.clone())
+    }
+
+    /// One-line explanation using the grammar's token names, e.g.
+    /// `Butt slam (Ermac): expected [BP], got [FP]`
+    pub fn describe(&self, grammar: &Grammar) -> String {
+        const SHOWN: usize = 3;
+        let mut moves = self
+            .following
+            .iter()
+            .take(SHOWN)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        if self.following.len() > SHOWN {
+            moves.push_str(&format!(" (+{} more)", self.following.len() - SHOWN));
+        }
+        let token = |key: &Symbol| {
+            grammar
+                .get_token_for_key(key.as_str())
+                .map(str::to_string)
+                .unwrap_or_else(|| key.to_string())
+        };
+        let expected: Vec<String> = self.expected.iter().map(token).collect();
+        format!(
+            "{}: expected {}, got {}",
+            moves,
+            expected.join(" or "),
+            token(&self.got)
+        )
+    }
+}
+
+/// Runtime state of a combo recognizer walking a [`DFA`].
+///
+/// The automaton itself is borrowed on every call so several recognizers can
+/// share one compiled DFA.
+#[derive(Debug, Clone)]
+pub struct Recognizer {
+    player: usize,
+    state: State,
+    /// Moves completed but held back by the match policy
+    held: Vec<String>,
+    /// Time of the last input of the held-back sequence
+    held_at: Option<Instant>,
+    /// Inputs of the held-back sequence
+    held_span: Range<u64>,
+    /// Index and time of the first input of the sequence underway
+    started: Option<(u64, Instant)>,
+    history: InputHistory,
+}
+
+/// Split the moves completed at `state` into those reported now and those
+/// held back while a preferred move may still follow
+fn resolve(
+    dfa: &DFA,
+    policy: MatchPolicy,
+    state: &State,
+    completed: Vec<String>,
+) -> (Vec<String>, Vec<String>) {
+    if completed.is_empty() {
+        return (Vec::new(), Vec::new());
+    }
+    match policy {
+        MatchPolicy::All => (completed, Vec::new()),
+        MatchPolicy::Longest => {
+            // Every transition leads on to a longer move
+            if dfa.transitions_from(state).next().is_none() {
+                (completed, Vec::new())
+            } else {
+                (Vec::new(), completed)
+            }
+        }
+        MatchPolicy::Priority => {
+            let best = completed
+                .iter()
+                .map(|name| dfa.priority(name))
+                .max()
+                .unwrap_or(0);
+            let top: Vec<String> = completed
+                .into_iter()
+                .filter(|name| dfa.priority(name) == best)
+                .collect();
+            if dfa.has_pending_move(state, |name| dfa.priority(name) > best) {
+                (Vec::new(), top)
+            } else {
+                (top, Vec::new())
+            }
+        }
+    }
+}
+
+impl Recognizer {
+    pub fn new(dfa: &DFA) -> Self {
+        Self::for_player(dfa, 1)
+    }
+
+    /// Recognizer of one player; its events carry the player number
+    pub fn for_player(dfa: &DFA, player: usize) -> Self {
+        Self {
+            player,
+            state: dfa.start_state().clone(),
+            held: Vec::new(),
+            held_at: None,
+            held_span: 0..0,
+            started: None,
+            history: InputHistory::default(),
+        }
+    }
+
+    /// Keep the last `capacity` inputs in the history instead of
+    /// [`crate::tools::history::DEFAULT_HISTORY_CAPACITY`]
+    pub fn with_history_capacity(mut self, capacity: usize) -> Self {
+        self.history = InputHistory::new(capacity);
+        self
+    }
+
+    /// Carry on `history`, e.g. the one of the recognizer this one replaces
+    pub fn with_history(mut self, history: InputHistory) -> Self {
+        self.history = history;
+        self
+    }
+
+    /// Inputs fed to the recognizer, including the ones that broke or
+    /// were ignored by a sequence
+    pub fn history(&self) -> &InputHistory {
+        &self.history
+    }
+
+    pub fn player(&self) -> usize {
+        self.player
+    }
+
+    /// Current DFA state
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Moves completed but held back while a longer or preferred move may
+    /// still follow
+    pub fn held(&self) -> &[String] {
+        &self.held
+    }
+
+    /// Go back to the start state, dropping held-back moves
+    pub fn reset(&mut self, dfa: &DFA) {
+        self.state = dfa.start_state().clone();
+        self.held.clear();
+        self.held_at = None;
+        self.started = None;
+    }
+
+    /// Feed one token now. See [`Recognizer::feed_at`].
+    pub fn feed<K: Into<Symbol>>(
+        &mut self,
+        dfa: &DFA,
+        key: K,
+        token_name: &str,
+        config: &DFAConfig,
+    ) -> StepEvent {
+        self.feed_at(dfa, key, token_name, config, Instant::now())
+    }
+
+    /// Feed one token pressed on the current tick of `clock`, and stamp the
+    /// event with that tick
+    pub fn feed_on<K: Into<Symbol>, S: TimeSource>(
+        &mut self,
+        dfa: &DFA,
+        key: K,
+        token_name: &str,
+        config: &DFAConfig,
+        clock: &FrameClock<S>,
+    ) -> StepEvent {
+        let at = clock.now();
+        self.step(
+            dfa,
+            key.into(),
+            token_name,
+            config,
+            at,
+            Some(clock.frame_at(at)),
+        )
+    }
+
+    /// Feed one token pressed at `at`. When the current state has no
+    /// transition for the key, the recognizer resets and retries the key from
+    /// the start state so that a broken sequence can immediately begin a new
+    /// move. Moves held back by the match policy are reported when the
+    /// sequence breaks or its continuation window has expired, and dropped
+    /// when a longer move completes.
+    ///
+    /// The leniency rules of the grammar come first: a sequence running past
+    /// the buffer window starts over, an optional key may be skipped to take
+    /// the token, and an ignored key leaves the sequence where it is.
+    pub fn feed_at<K: Into<Symbol>>(
+        &mut self,
+        dfa: &DFA,
+        key: K,
+        token_name: &str,
+        config: &DFAConfig,
+        at: Instant,
+    ) -> StepEvent {
+        self.step(dfa, key.into(), token_name, config, at, None)
+    }
+
+    fn step(
+        &mut self,
+        dfa: &DFA,
+        key: Symbol,
+        token_name: &str,
+        config: &DFAConfig,
+        at: Instant,
+        frame: Option<Frame>,
+    ) -> StepEvent {
+        let from = self.state.clone();
+        let index = self.history.next_index();
+        let held_span = self.held_span.clone();
+        let mut matches = self.poll(dfa, config, at);
+        let mut expired = !matches.is_empty();
+
+        let overdue = self
+            .started
+            .map(|(_, started)| started)
+            .zip(config.buffer_window)
+            .is_some_and(|(started, window)| at.saturating_duration_since(started) > window);
+        if overdue {
+            if config.debug {
+                println!("Sequence outlasted the buffer window, starting over");
+            }
+            matches.append(&mut self.held);
+            self.held_at = None;
+            self.state = dfa.start_state().clone();
+            expired = true;
+        }
+
+        let began = self.state == *dfa.start_state();
+        let (mut next, mut completed) = dfa.step(&self.state, &key, token_name, config);
+        let mut skipped = None;
+        let mut ignored = false;
+        if next.is_none() && !began {
+            if let Some((optional, after)) = dfa.skip_optional(&self.state, &key) {
+                if config.debug {
+                    println!("Skipping optional key '{}'", optional);
+                }
+                (next, completed) = dfa.step(after, &key, token_name, config);
+                skipped = Some(optional.clone());
+            } else if dfa.is_ignored(&key) {
+                if config.debug {
+                    println!("Ignoring \"{}\" in the sequence", token_name);
+                }
+                ignored = true;
+            }
+        }
+
+        let mut near_miss = None;
+        let mut restarted = began;
+        let (to, completed, reset) = if ignored {
+            (self.state.clone(), Vec::new(), expired)
+        } else if let Some(next) = next {
+            (next.clone(), completed.to_vec(), expired)
+        } else {
+            restarted = true;
+            let first = self.started.map_or(index, |(first, _)| first);
+            near_miss = NearMiss::new(dfa, &self.state, key.clone(), first..index);
+            if config.debug {
+                if let Some(miss) = &near_miss {
+                    println!(
+                        "Near miss at {}: expected {:?}, following {}",
+                        miss.state,
+                        miss.expected,
+                        miss.following.join(", ")
+                    );
+                }
+            }
+            // The continuation broke, so the held-back moves stand
+            matches.append(&mut self.held);
+            let (next, completed) = dfa.step(dfa.start_state(), &key, token_name, config);
+            match next {
+                Some(next) => (next.clone(), completed.to_vec(), true),
+                None => (dfa.start_state().clone(), Vec::new(), true),
+            }
+        };
+
+        // A longer move completing supersedes the held-back ones
+        if !completed.is_empty() {
+            self.held.clear();
+        }
+        // Moves released so far are the held-back ones
+        let mut spans = vec![held_span; matches.len()];
+        let first = match self.started {
+            Some((first, _)) if !restarted => first,
+            _ => index,
+        };
+        let (report, hold) = resolve(dfa, config.policy, &to, completed);
+        spans.extend(report.iter().map(|_| first..index + 1));
+        matches.extend(report);
+        if !hold.is_empty() {
+            if config.debug {
+                println!("Holding {} for a longer move", hold.join(", "));
+            }
+            self.held = hold;
+            self.held_span = first..index + 1;
+        }
+        if !ignored {
+            self.held_at = (!self.held.is_empty()).then_some(at);
+        }
+        if to == *dfa.start_state() {
+            self.started = None;
+        } else if restarted {
+            self.started = Some((index, at));
+        }
+
+        let mut record = InputRecord::new(self.player, key.clone(), token_name, at);
+        record.frame = frame;
+        record.reset = reset;
+        self.history.push(record);
+
+        self.state = to.clone();
+        let metadata = matches
+            .iter()
+            .map(|name| dfa.metadata(name).cloned().unwrap_or_default())
+            .collect();
+        StepEvent {
+            player: self.player,
+            key,
+            token: token_name.to_string(),
+            from,
+            to,
+            matches,
+            metadata,
+            spans,
+            input: index,
+            reset,
+            near_miss,
+            frame,
+            ignored,
+            skipped,
+        }
+    }
+
+    /// Report the held-back moves if no input came within the continuation
+    /// window since `now`, and go back to the start state. Front-ends call
+    /// this between inputs.
+    pub fn poll(&mut self, dfa: &DFA, config: &DFAConfig, now: Instant) -> Vec<String> {
+        match self.held_at {
+            Some(at) if now.saturating_duration_since(at) > config.continuation_window => {
+                self.flush(dfa)
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    /// Report the held-back moves right away, e.g. at the end of the input
+    pub fn flush(&mut self, dfa: &DFA) -> Vec<String> {
+        let released = std::mem::take(&mut self.held);
+        if !released.is_empty() {
+            self.state = dfa.start_state().clone();
+            self.started = None;
+        }
+        self.held_at = None;
+        released
+    }
+}
@@ -0,0 +1,87 @@
+use crate::tools::dfa::DFA;
+use crate::tools::parsing::{parse_grammar_with_sources, Grammar};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the grammar files are checked for changes
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches a grammar file and the files it includes by polling their
+/// modification times, and parses the grammar again when one changes
+#[derive(Debug, Clone)]
+pub struct GrammarWatcher {
+    path: PathBuf,
+    /// Files read by the last load, with their modification time then;
+    /// `None` for files that could not be read
+    sources: Vec<(PathBuf, Option<SystemTime>)>,
+    interval: Duration,
+    last_poll: Option<Instant>,
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+impl GrammarWatcher {
+    /// Watch the grammar at `path`. Call [`GrammarWatcher::load`] once to
+    /// find the files it includes.
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        let path = path.into();
+        Self {
+            sources: vec![(path.clone(), modified(&path))],
+            path,
+            interval: DEFAULT_POLL_INTERVAL,
+            last_poll: None,
+        }
+    }
+
+    pub fn with_interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Files watched, the grammar file first
+    pub fn sources(&self) -> impl Iterator<Item = &Path> {
+        self.sources.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// Parse the grammar and build its automaton, then watch every file
+    /// read on the way, also when parsing failed, so that fixing any of
+    /// them triggers the next load
+    pub fn load(&mut self) -> Result<(Grammar, DFA), String> {
+        let (result, sources) = parse_grammar_with_sources(&self.path);
+        self.sources = sources
+            .into_iter()
+            .map(|path| {
+                let stamp = modified(&path);
+                (path, stamp)
+            })
+            .collect();
+        result.map(|grammar| {
+            let dfa = DFA::from_grammar(&grammar);
+            (grammar, dfa)
+        })
+    }
+
+    /// Whether a watched file was modified, created or removed since the
+    /// last load
+    pub fn changed(&self) -> bool {
+        self.sources
+            .iter()
+            .any(|(path, stamp)| modified(path) != *stamp)
+    }
+
+    /// Load the grammar again if a watched file changed. Files are checked
+    /// at most once per interval; `None` means nothing to do.
+    pub fn poll(&mut self, now: Instant) -> Option<Result<(Grammar, DFA), String>> {
+        if self
+            .last_poll
+            .is_some_and(|last| now.saturating_duration_since(last) < self.interval)
+        {
+            return None;
+        }
+        self.last_poll = Some(now);
+        self.changed().then(|| self.load())
+    }
+}
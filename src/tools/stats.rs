@@ -0,0 +1,325 @@
+use crate::tools::dfa::DFA;
+use crate::tools::drill::{AttemptResult, Outcome};
+use crate::tools::parsing::{Grammar, MoveDef};
+use crate::tools::recognizer::StepEvent;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Character heading of moves whose name has no `(Character)` suffix
+pub const NO_CHARACTER: &str = "Other";
+
+/// Practice totals of one move
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MoveStats {
+    pub attempts: u64,
+    pub completions: u64,
+    /// Summed execution time of the completed attempts
+    pub total_time_ms: u64,
+    /// How often an attempt went wrong at each step of the sequence
+    pub mistakes: Vec<u64>,
+}
+
+impl MoveStats {
+    /// Share of attempts that were completed, between 0 and 1
+    pub fn completion_rate(&self) -> f64 {
+        if self.attempts == 0 {
+            0.0
+        } else {
+            self.completions as f64 / self.attempts as f64
+        }
+    }
+
+    /// Average execution time of the completed attempts
+    pub fn average_time(&self) -> Option<Duration> {
+        (self.completions > 0).then(|| Duration::from_millis(self.total_time_ms / self.completions))
+    }
+
+    /// Step (0-based) where attempts went wrong most often, with its count.
+    /// Ties go to the earlier step.
+    pub fn most_common_mistake(&self) -> Option<(usize, u64)> {
+        self.mistakes
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|&(_, count)| count > 0)
+            .fold(None, |best, (step, count)| match best {
+                Some((_, best_count)) if best_count >= count => best,
+                _ => Some((step, count)),
+            })
+    }
+
+    fn record(&mut self, attempt: &AttemptRecord) {
+        self.attempts += 1;
+        if attempt.completed {
+            self.completions += 1;
+            self.total_time_ms += attempt.time.unwrap_or_default().as_millis() as u64;
+        }
+        if let Some(step) = attempt.mistake_step {
+            if self.mistakes.len() <= step {
+                self.mistakes.resize(step + 1, 0);
+            }
+            self.mistakes[step] += 1;
+        }
+    }
+}
+
+/// How one attempt at a move went
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AttemptRecord {
+    pub completed: bool,
+    /// Execution time, from the first input to the last
+    pub time: Option<Duration>,
+    /// Step (0-based) of the first input that went wrong
+    pub mistake_step: Option<usize>,
+}
+
+impl From<&AttemptResult> for AttemptRecord {
+    fn from(result: &AttemptResult) -> Self {
+        // An attempt that simply stopped went wrong where it stopped
+        let mistake_step = match (result.wrong_inputs.first(), result.outcome) {
+            (Some(wrong), _) => Some(wrong.step),
+            (None, Outcome::Incomplete { entered }) => Some(entered),
+            (None, _) => None,
+        };
+        Self {
+            completed: result.is_completed(),
+            time: result.duration,
+            mistake_step,
+        }
+    }
+}
+
+/// Statistics of one grammar: character -> move name -> totals
+pub type GrammarStats = BTreeMap<String, BTreeMap<String, MoveStats>>;
+
+/// Statistics of every grammar practised, as stored on disk
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatsStore {
+    #[serde(default)]
+    grammars: BTreeMap<String, GrammarStats>,
+}
+
+/// Key under which the statistics of a grammar file are stored: its file
+/// name, so that statistics survive moving the grammars folder
+pub fn grammar_key<P: AsRef<Path>>(path: P) -> String {
+    let path = path.as_ref();
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| path.display().to_string())
+}
+
+/// Where statistics are kept unless a file is given explicitly:
+/// `$FT_ALITY_STATS`, else `stats.toml` in `$XDG_DATA_HOME/ft_ality` or
+/// `~/.local/share/ft_ality`
+pub fn default_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os("FT_ALITY_STATS") {
+        return Some(PathBuf::from(path));
+    }
+    let data_home = env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_home.join("ft_ality").join("stats.toml"))
+}
+
+impl StatsStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load statistics from `path`. A missing file is an empty store.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path_ref = path.as_ref();
+        let contents = match fs::read_to_string(path_ref) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(e) => {
+                return Err(format!(
+                    "Failed to read stats file '{}': {}",
+                    path_ref.display(),
+                    e
+                ))
+            }
+        };
+        toml::from_str(&contents)
+            .map_err(|e| format!("Invalid stats file '{}': {}", path_ref.display(), e))
+    }
+
+    /// Write the statistics to `path`, creating its folder if needed
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), String> {
+        let path_ref = path.as_ref();
+        let write_error = |e: &dyn std::fmt::Display| {
+            format!("Failed to write stats file '{}': {}", path_ref.display(), e)
+        };
+        if let Some(dir) = path_ref.parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| write_error(&e))?;
+        }
+        let contents = toml::to_string(self).map_err(|e| write_error(&e))?;
+        fs::write(path_ref, contents).map_err(|e| write_error(&e))
+    }
+
+    /// Add one attempt at `m` to the statistics of grammar `key`
+    pub fn record(&mut self, key: &str, m: &MoveDef, attempt: &AttemptRecord) {
+        self.grammars
+            .entry(key.to_string())
+            .or_default()
+            .entry(m.character().unwrap_or(NO_CHARACTER).to_string())
+            .or_default()
+            .entry(m.short_name().to_string())
+            .or_default()
+            .record(attempt);
+    }
+
+    /// Statistics of grammar `key`, if it was ever practised
+    pub fn grammar(&self, key: &str) -> Option<&GrammarStats> {
+        self.grammars.get(key)
+    }
+
+    /// Statistics of one move
+    pub fn move_stats(&self, key: &str, m: &MoveDef) -> Option<&MoveStats> {
+        self.grammar(key)?
+            .get(m.character().unwrap_or(NO_CHARACTER))?
+            .get(m.short_name())
+    }
+
+    /// Forget the statistics of grammar `key`, or only of one of its
+    /// characters. Returns how many moves were forgotten.
+    pub fn reset(&mut self, key: &str, character: Option<&str>) -> usize {
+        match character {
+            None => self
+                .grammars
+                .remove(key)
+                .map_or(0, |characters| characters.values().map(BTreeMap::len).sum()),
+            Some(wanted) => {
+                let Some(characters) = self.grammars.get_mut(key) else {
+                    return 0;
+                };
+                let names: Vec<String> = characters
+                    .keys()
+                    .filter(|c| c.eq_ignore_ascii_case(wanted))
+                    .cloned()
+                    .collect();
+                let forgotten = names
+                    .iter()
+                    .filter_map(|name| characters.remove(name))
+                    .map(|moves| moves.len())
+                    .sum();
+                if characters.is_empty() {
+                    self.grammars.remove(key);
+                }
+                forgotten
+            }
+        }
+    }
+}
+
+/// An attempt being followed by the [`StatsTracker`]
+#[derive(Debug, Clone, Copy)]
+struct ActiveAttempt {
+    started: Instant,
+    progress: usize,
+}
+
+/// Turns the recognizer's step events into per-move attempts. An attempt at
+/// a move starts with its first input and ends when the move completes or
+/// the sequence breaks; keys the leniency rules let slide do not break it.
+/// A broken attempt is not counted when the inputs so far completed another
+/// move, since the player most likely meant that one.
+#[derive(Debug, Clone)]
+pub struct StatsTracker {
+    moves: Vec<MoveDef>,
+    active: BTreeMap<usize, ActiveAttempt>,
+}
+
+impl StatsTracker {
+    pub fn new(grammar: &Grammar) -> Self {
+        Self {
+            moves: grammar
+                .moves
+                .iter()
+                .filter(|m| !m.sequence.is_empty())
+                .cloned()
+                .collect(),
+            active: BTreeMap::new(),
+        }
+    }
+
+    /// Follow one recognizer step and return the attempts it ended
+    pub fn observe(
+        &mut self,
+        dfa: &DFA,
+        event: &StepEvent,
+        at: Instant,
+    ) -> Vec<(&MoveDef, AttemptRecord)> {
+        let mut ended = Vec::new();
+        for (i, m) in self.moves.iter().enumerate() {
+            let progress = dfa.progress(&event.to, &m.sequence);
+            let previous = self.active.get(&i).copied();
+
+            if let Some(attempt) = previous {
+                // A skipped optional key counts as entered
+                let skipped = usize::from(event.skipped.is_some());
+                if event.ignored && progress == attempt.progress {
+                    // The grammar lets the key come between two inputs
+                } else if progress == attempt.progress + 1
+                    || progress == attempt.progress + 1 + skipped
+                {
+                    self.active.insert(
+                        i,
+                        ActiveAttempt {
+                            progress,
+                            ..attempt
+                        },
+                    );
+                } else {
+                    self.active.remove(&i);
+                    // Inputs that completed another move, before or with
+                    // this step, were not meant for this one
+                    let completed_other = dfa.get_matches(&event.from).is_some()
+                        || dfa.get_matches(&event.to).is_some();
+                    if !completed_other {
+                        ended.push((
+                            i,
+                            AttemptRecord {
+                                completed: false,
+                                time: None,
+                                mistake_step: Some(attempt.progress),
+                            },
+                        ));
+                    }
+                }
+            }
+            if progress > 0 && !self.active.contains_key(&i) {
+                self.active.insert(
+                    i,
+                    ActiveAttempt {
+                        started: at,
+                        progress,
+                    },
+                );
+            }
+
+            if let Some(attempt) = self.active.get(&i).copied() {
+                if attempt.progress == m.sequence.len() {
+                    self.active.remove(&i);
+                    ended.push((
+                        i,
+                        AttemptRecord {
+                            completed: true,
+                            time: Some(at.saturating_duration_since(attempt.started)),
+                            mistake_step: None,
+                        },
+                    ));
+                }
+            }
+        }
+        ended
+            .into_iter()
+            .map(|(i, record)| (&self.moves[i], record))
+            .collect()
+    }
+}
@@ -0,0 +1,78 @@
+use std::borrow::Borrow;
+use std::fmt;
+
+/// A key of a grammar, as used by mappings, move sequences and the automaton:
+/// one character such as `s` or `é`, or a name such as `df` or `LeftShift`
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Symbol(String);
+
+impl Symbol {
+    /// A key named `name`, which must be non-empty and hold no whitespace
+    pub fn new(name: &str) -> Result<Self, String> {
+        if name.is_empty() {
+            return Err("key must not be empty".to_string());
+        }
+        if name.contains(char::is_whitespace) {
+            return Err(format!("invalid key '{}': keys cannot hold spaces", name));
+        }
+        Ok(Self(name.to_string()))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// The character of a single-character key
+    pub fn as_char(&self) -> Option<char> {
+        let mut chars = self.0.chars();
+        chars.next().filter(|_| chars.next().is_none())
+    }
+
+    /// Number of characters of the key name
+    pub fn width(&self) -> usize {
+        self.0.chars().count()
+    }
+}
+
+/// The key of a character typed or pressed, unchecked
+impl From<char> for Symbol {
+    fn from(ch: char) -> Self {
+        Self(ch.to_string())
+    }
+}
+
+impl From<&Symbol> for Symbol {
+    fn from(symbol: &Symbol) -> Self {
+        symbol.clone()
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq<char> for Symbol {
+    fn eq(&self, other: &char) -> bool {
+        self.as_char() == Some(*other)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        self.0 == other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        self.0 == *other
+    }
+}
@@ -0,0 +1,306 @@
+use crate::tools::layout::LayoutStyle;
+use sdl2::pixels::Color;
+use serde::Deserialize;
+use std::fs::read_to_string;
+use std::path::Path;
+
+/// Colours, font size and panel arrangement of the SDL overlay
+#[derive(Debug, Clone, PartialEq)]
+pub struct Theme {
+    pub background: Color,
+    pub text: Color,
+    pub header: Color,
+    pub border: Color,
+    pub panel: Color,
+    /// Text drawn on top of the header and instruction bars
+    pub dark_text: Color,
+    /// Icon colour of directional tokens (Up, Down, Forward, ...)
+    pub direction: Color,
+    /// Icon colour of bracketed button tokens ([BK], [FP], ...)
+    pub button: Color,
+    /// Icon colour of any other token
+    pub other_token: Color,
+    /// Marker for inputs that broke the sequence being followed
+    pub reset: Color,
+    /// Background of moves the player is currently following
+    pub candidate: Color,
+    /// Background of a move that just completed
+    pub flash: Color,
+    /// Scale of the built-in font for panel text (the title is one step larger)
+    pub font_scale: u32,
+    pub layout: LayoutStyle,
+}
+
+/// Names accepted by [`Theme::preset`]
+pub const PRESETS: [&str; 4] = ["dark", "light", "high-contrast", "colorblind"];
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self::dark()
+    }
+}
+
+impl Theme {
+    /// The original overlay colours
+    pub fn dark() -> Self {
+        Self {
+            background: Color::RGB(20, 20, 30),
+            text: Color::RGB(200, 200, 255),
+            header: Color::RGB(255, 200, 100),
+            border: Color::RGB(100, 100, 150),
+            panel: Color::RGB(40, 40, 60),
+            dark_text: Color::RGB(20, 20, 30),
+            direction: Color::RGB(70, 110, 200),
+            button: Color::RGB(200, 90, 70),
+            other_token: Color::RGB(110, 110, 130),
+            reset: Color::RGB(230, 60, 60),
+            candidate: Color::RGB(60, 60, 95),
+            flash: Color::RGB(255, 240, 180),
+            font_scale: 2,
+            layout: LayoutStyle::Auto,
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            background: Color::RGB(235, 235, 240),
+            text: Color::RGB(30, 30, 50),
+            header: Color::RGB(60, 90, 160),
+            border: Color::RGB(120, 120, 140),
+            panel: Color::RGB(250, 250, 252),
+            dark_text: Color::RGB(250, 250, 252),
+            direction: Color::RGB(150, 180, 240),
+            button: Color::RGB(240, 160, 140),
+            other_token: Color::RGB(200, 200, 210),
+            reset: Color::RGB(200, 30, 30),
+            candidate: Color::RGB(215, 225, 250),
+            flash: Color::RGB(255, 220, 120),
+            font_scale: 2,
+            layout: LayoutStyle::Auto,
+        }
+    }
+
+    /// Pure black and white with saturated accents and a larger font
+    pub fn high_contrast() -> Self {
+        Self {
+            background: Color::RGB(0, 0, 0),
+            text: Color::RGB(255, 255, 255),
+            header: Color::RGB(255, 255, 0),
+            border: Color::RGB(255, 255, 255),
+            panel: Color::RGB(0, 0, 0),
+            dark_text: Color::RGB(0, 0, 0),
+            direction: Color::RGB(0, 90, 255),
+            button: Color::RGB(200, 0, 0),
+            other_token: Color::RGB(90, 90, 90),
+            reset: Color::RGB(255, 0, 255),
+            candidate: Color::RGB(0, 80, 0),
+            flash: Color::RGB(0, 255, 0),
+            font_scale: 3,
+            layout: LayoutStyle::Auto,
+        }
+    }
+
+    /// Dark theme whose accents come from the Okabe-Ito palette, which stays
+    /// distinguishable for the common forms of colour blindness
+    pub fn colorblind() -> Self {
+        Self {
+            header: Color::RGB(230, 159, 0),
+            direction: Color::RGB(0, 114, 178),
+            button: Color::RGB(213, 94, 0),
+            other_token: Color::RGB(120, 120, 120),
+            reset: Color::RGB(204, 121, 167),
+            candidate: Color::RGB(30, 70, 90),
+            flash: Color::RGB(240, 228, 66),
+            ..Self::dark()
+        }
+    }
+
+    /// Look up a built-in theme by name
+    pub fn preset(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "dark" => Some(Self::dark()),
+            "light" => Some(Self::light()),
+            "high-contrast" | "high_contrast" | "highcontrast" => Some(Self::high_contrast()),
+            "colorblind" | "colourblind" => Some(Self::colorblind()),
+            _ => None,
+        }
+    }
+
+    /// Load a theme from a preset name or a TOML theme file
+    pub fn load(spec: &str) -> Result<Self, String> {
+        if let Some(theme) = Self::preset(spec) {
+            return Ok(theme);
+        }
+        if !Path::new(spec).exists() {
+            return Err(format!(
+                "Unknown theme '{}': expected one of {} or a path to a .toml theme file",
+                spec,
+                PRESETS.join(", ")
+            ));
+        }
+        Self::from_file(spec)
+    }
+
+    /// Load a TOML theme file
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let path_ref = path.as_ref();
+        let contents = read_to_string(path_ref)
+            .map_err(|e| format!("Failed to read theme file '{}': {}", path_ref.display(), e))?;
+        Self::from_toml(&contents)
+            .map_err(|e| format!("Invalid theme file '{}': {}", path_ref.display(), e))
+    }
+
+    /// Parse a TOML theme document. Every setting is optional and falls back
+    /// to the `preset` the document names (dark if none):
+    ///
+    /// ```toml
+    /// preset = "dark"
+    /// font_scale = 2
+    /// layout = "auto"        # auto, wide or compact
+    ///
+    /// [colors]
+    /// background = "#14141e"
+    /// header = "#ffc864"
+    /// ```
+    pub fn from_toml(contents: &str) -> Result<Self, String> {
+        let file: ThemeFile = toml::from_str(contents).map_err(|e| e.to_string())?;
+
+        let mut theme = match &file.preset {
+            Some(name) => Self::preset(name).ok_or_else(|| {
+                format!(
+                    "unknown preset '{}': expected one of {}",
+                    name,
+                    PRESETS.join(", ")
+                )
+            })?,
+            None => Self::default(),
+        };
+
+        if let Some(scale) = file.font_scale {
+            if !(1..=4).contains(&scale) {
+                return Err(format!("font_scale must be between 1 and 4, got {}", scale));
+            }
+            theme.font_scale = scale;
+        }
+        if let Some(layout) = &file.layout {
+            theme.layout = LayoutStyle::from_name(layout).ok_or_else(|| {
+                format!(
+                    "unknown layout '{}': expected auto, wide or compact",
+                    layout
+                )
+            })?;
+        }
+
+        let colors = file.colors.unwrap_or_default();
+        let slots: [(&str, &Option<String>, &mut Color); 12] = [
+            ("background", &colors.background, &mut theme.background),
+            ("text", &colors.text, &mut theme.text),
+            ("header", &colors.header, &mut theme.header),
+            ("border", &colors.border, &mut theme.border),
+            ("panel", &colors.panel, &mut theme.panel),
+            ("dark_text", &colors.dark_text, &mut theme.dark_text),
+            ("direction", &colors.direction, &mut theme.direction),
+            ("button", &colors.button, &mut theme.button),
+            ("other_token", &colors.other_token, &mut theme.other_token),
+            ("reset", &colors.reset, &mut theme.reset),
+            ("candidate", &colors.candidate, &mut theme.candidate),
+            ("flash", &colors.flash, &mut theme.flash),
+        ];
+        for (name, value, slot) in slots {
+            if let Some(value) = value {
+                *slot = parse_color(value).map_err(|e| format!("colors.{}: {}", name, e))?;
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+/// Parse `#RRGGBB` or `#RRGGBBAA`
+fn parse_color(value: &str) -> Result<Color, String> {
+    let hex = value.trim().strip_prefix('#').unwrap_or(value.trim());
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(format!(
+            "expected a colour like \"#RRGGBB\" or \"#RRGGBBAA\", got \"{}\"",
+            value
+        ));
+    }
+    let byte = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap_or(0);
+    let alpha = if hex.len() == 8 { byte(6) } else { 255 };
+    Ok(Color::RGBA(byte(0), byte(2), byte(4), alpha))
+}
+
+/// On-disk representation of a theme; every field overrides the preset
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ThemeFile {
+    preset: Option<String>,
+    font_scale: Option<u32>,
+    layout: Option<String>,
+    colors: Option<ColorOverrides>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct ColorOverrides {
+    background: Option<String>,
+    text: Option<String>,
+    header: Option<String>,
+    border: Option<String>,
+    panel: Option<String>,
+    dark_text: Option<String>,
+    direction: Option<String>,
+    button: Option<String>,
+    other_token: Option<String>,
+    reset: Option<String>,
+    candidate: Option<String>,
+    flash: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_preset_name_resolves() {
+        for name in PRESETS {
+            assert!(Theme::preset(name).is_some(), "{name}");
+        }
+        assert_eq!(Theme::load("dark"), Ok(Theme::default()));
+        assert!(Theme::load("neon")
+            .unwrap_err()
+            .contains("Unknown theme 'neon'"));
+    }
+
+    #[test]
+    fn toml_overrides_the_named_preset() {
+        let theme = Theme::from_toml(
+            r##"
+            preset = "light"
+            font_scale = 3
+            layout = "compact"
+
+            [colors]
+            header = "#102030"
+            flash = "#FF000080"
+            "##,
+        )
+        .expect("valid theme");
+
+        assert_eq!(theme.header, Color::RGB(0x10, 0x20, 0x30));
+        assert_eq!(theme.flash, Color::RGBA(255, 0, 0, 0x80));
+        assert_eq!(theme.font_scale, 3);
+        assert_eq!(theme.layout, LayoutStyle::Compact);
+        assert_eq!(theme.background, Theme::light().background);
+    }
+
+    #[test]
+    fn invalid_theme_documents_are_rejected() {
+        let bad_color = Theme::from_toml("[colors]\nheader = \"orange\"").unwrap_err();
+        assert!(bad_color.contains("colors.header"), "{bad_color}");
+        assert!(Theme::from_toml("[colors]\nhedaer = \"#000000\"").is_err());
+        assert!(Theme::from_toml("font_scale = 9").is_err());
+        assert!(Theme::from_toml("preset = \"neon\"").is_err());
+        assert!(Theme::from_toml("layout = \"diagonal\"").is_err());
+    }
+}
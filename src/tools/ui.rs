@@ -1,71 +1,896 @@
 extern crate sdl2;
 
-use crate::tools::parsing::Grammar;
+use crate::tools::dfa::{State, DFA};
+use crate::tools::drill::Drill;
+use crate::tools::font;
+use crate::tools::history::{InputHistory, InputRecord};
+use crate::tools::layout::{self, Layout};
+use crate::tools::parsing::{Grammar, MoveDef};
+use crate::tools::recognizer::StepEvent;
+use crate::tools::theme::Theme;
 use sdl2::pixels::Color;
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::rect::Rect;
-use sdl2::render::Canvas;
-use sdl2::video::Window;
+use sdl2::render::{BlendMode, Canvas, RenderTarget};
+use sdl2::surface::Surface;
+use std::collections::VecDeque;
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 /// UI Constants
-const WINDOW_WIDTH: u32 = 800;
-const WINDOW_HEIGHT: u32 = 600;
-const BACKGROUND_COLOR: Color = Color::RGB(20, 20, 30);
-const TEXT_COLOR: Color = Color::RGB(200, 200, 255);
-const HEADER_COLOR: Color = Color::RGB(255, 200, 100);
-const BORDER_COLOR: Color = Color::RGB(100, 100, 150);
-
-/// Render key mappings to the SDL canvas
-pub fn render_key_mappings(canvas: &mut Canvas<Window>, grammar: &Grammar) {
-    // Clear the screen
-    canvas.set_draw_color(BACKGROUND_COLOR);
+const DEFAULT_WINDOW_WIDTH: u32 = 1000;
+const DEFAULT_WINDOW_HEIGHT: u32 = 600;
+
+/// How many inputs the history keeps (older ones scroll out)
+const HISTORY_CAPACITY: usize = 32;
+/// How long a recognized move stays in the feed before it has fully faded
+const MATCH_LIFETIME: Duration = Duration::from_millis(2500);
+/// How long a completed move flashes in the move list
+const FLASH_DURATION: Duration = Duration::from_millis(400);
+
+/// One recognized move shown in the match feed
+#[derive(Debug, Clone)]
+pub struct FeedEntry {
+    pub player: usize,
+    pub name: String,
+    pub at: Instant,
+}
+
+/// Everything the live overlay displays, updated from recognizer events
+#[derive(Debug, Clone)]
+pub struct UiModel {
+    history: InputHistory,
+    feed: VecDeque<FeedEntry>,
+    /// DFA state of each player, player 1 first
+    states: Vec<State>,
+    move_list_scroll: usize,
+}
+
+impl UiModel {
+    pub fn new(start: &State) -> Self {
+        Self {
+            history: InputHistory::new(HISTORY_CAPACITY),
+            feed: VecDeque::new(),
+            states: vec![start.clone()],
+            move_list_scroll: 0,
+        }
+    }
+
+    /// Follow `count` players, all starting in the start state
+    pub fn with_players(mut self, count: usize) -> Self {
+        let start = self.states[0].clone();
+        self.states.resize(count.max(1), start);
+        self
+    }
+
+    /// Number of players followed
+    pub fn players(&self) -> usize {
+        self.states.len()
+    }
+
+    fn set_state(&mut self, player: usize, state: &State) {
+        let index = player.max(1) - 1;
+        if index >= self.states.len() {
+            self.states.resize(index + 1, state.clone());
+        }
+        self.states[index] = state.clone();
+    }
+
+    /// Record a recognizer event that happened at `now`
+    pub fn record(&mut self, event: &StepEvent, now: Instant) {
+        let mut record = InputRecord::new(event.player, event.key.clone(), &event.token, now);
+        record.frame = event.frame;
+        record.reset = event.reset;
+        self.history.push(record);
+
+        for name in &event.matches {
+            self.feed.push_front(FeedEntry {
+                player: event.player,
+                name: name.clone(),
+                at: now,
+            });
+        }
+        self.set_state(event.player, &event.to);
+    }
+
+    /// Record moves reported at `now` without a new input, as (player, move),
+    /// after which those players are back in the `start` state
+    pub fn release(&mut self, released: &[(usize, String)], start: &State, now: Instant) {
+        for (player, name) in released {
+            self.feed.push_front(FeedEntry {
+                player: *player,
+                name: name.clone(),
+                at: now,
+            });
+            self.set_state(*player, start);
+        }
+    }
+
+    /// Put `count` players back in the `start` state of a new automaton,
+    /// keeping the history and feed
+    pub fn restart(&mut self, start: &State, count: usize) {
+        self.states = vec![start.clone(); count.max(1)];
+        self.move_list_scroll = 0;
+    }
+
+    /// Drop feed entries that have fully faded out
+    pub fn prune(&mut self, now: Instant) {
+        self.feed
+            .retain(|entry| now.saturating_duration_since(entry.at) < MATCH_LIFETIME);
+    }
+
+    /// Inputs of every player, in the order they were recorded
+    pub fn history(&self) -> &InputHistory {
+        &self.history
+    }
+
+    /// Recognized moves still visible, newest first
+    pub fn feed(&self) -> impl Iterator<Item = &FeedEntry> {
+        self.feed.iter()
+    }
+
+    /// True if `name` was recognized less than `window` before `now`
+    pub fn completed_within(&self, name: &str, now: Instant, window: Duration) -> bool {
+        self.feed
+            .iter()
+            .any(|entry| entry.name == name && now.saturating_duration_since(entry.at) < window)
+    }
+
+    /// DFA state of player 1 after its last input
+    pub fn state(&self) -> &State {
+        &self.states[0]
+    }
+
+    /// DFA state of every player, player 1 first
+    pub fn states(&self) -> &[State] {
+        &self.states
+    }
+
+    /// Scroll the move list by `delta` rows, staying within `0..=max`
+    pub fn scroll_move_list(&mut self, delta: i32, max: usize) {
+        let target = self.move_list_scroll.min(max) as i64 + delta as i64;
+        self.move_list_scroll = target.clamp(0, max as i64) as usize;
+    }
+
+    /// First visible row of the move list
+    pub fn move_list_scroll(&self) -> usize {
+        self.move_list_scroll
+    }
+}
+
+/// Pick an icon colour for a token from its name
+fn token_color(theme: &Theme, token: &str) -> Color {
+    const DIRECTIONS: [&str; 6] = ["up", "down", "left", "right", "forward", "back"];
+    let lower = token.to_ascii_lowercase();
+    if DIRECTIONS.iter().any(|d| lower.contains(d)) {
+        theme.direction
+    } else if token.starts_with('[') {
+        theme.button
+    } else {
+        theme.other_token
+    }
+}
+
+/// Space above the first row of a panel, leaving room for its heading
+fn panel_top_padding(scale: u32) -> u32 {
+    8 + font::text_height(scale) + 10
+}
+
+/// Fill a panel and draw its heading
+fn draw_panel<T: RenderTarget>(canvas: &mut Canvas<T>, theme: &Theme, area: Rect, title: &str) {
+    canvas.set_draw_color(theme.panel);
+    canvas.fill_rect(area).ok();
+    font::draw_text(
+        canvas,
+        area.x() + 8,
+        area.y() + 8,
+        theme.font_scale,
+        title,
+        theme.header,
+    );
+}
+
+/// Draw the frame shared by every screen: background, border and header bar
+fn draw_chrome<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    layout: &Layout,
+    title: &str,
+) {
+    canvas.set_draw_color(theme.background);
     canvas.clear();
 
-    // Draw border
-    canvas.set_draw_color(BORDER_COLOR);
-    canvas
-        .draw_rect(Rect::new(10, 10, WINDOW_WIDTH - 20, WINDOW_HEIGHT - 20))
-        .ok();
-
-    // Note: Full text rendering would require SDL2_ttf
-    // For now, we draw colored boxes to represent the UI layout
-
-    // Header area (title)
-    canvas.set_draw_color(HEADER_COLOR);
-    canvas
-        .fill_rect(Rect::new(20, 20, WINDOW_WIDTH - 40, 60))
-        .ok();
-
-    // Key mappings area
-    canvas.set_draw_color(Color::RGB(40, 40, 60));
-    let y_offset = 100;
-    let row_height = 30;
-    let mappings_count = grammar.mappings.len().min(15); // Limit display
-
-    for i in 0..mappings_count {
-        let rect = Rect::new(
-            30,
-            y_offset + (i as i32 * row_height),
-            WINDOW_WIDTH - 60,
-            (row_height - 5) as u32,
+    canvas.set_draw_color(theme.border);
+    canvas.draw_rect(layout.border).ok();
+
+    let scale = theme.font_scale + 1;
+    canvas.set_draw_color(theme.header);
+    canvas.fill_rect(layout.header).ok();
+    font::draw_text(
+        canvas,
+        layout.header.x() + 14,
+        layout.header.y()
+            + (layout
+                .header
+                .height()
+                .saturating_sub(font::text_height(scale))) as i32
+                / 2,
+        scale,
+        title,
+        theme.dark_text,
+    );
+}
+
+/// Draw a label at the right end of the header bar
+fn draw_header_label<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    layout: &Layout,
+    label: &str,
+) {
+    let scale = theme.font_scale;
+    let header = layout.header;
+    font::draw_text(
+        canvas,
+        header.right() - 14 - font::text_width(label, scale) as i32,
+        header.y() + (header.height().saturating_sub(font::text_height(scale))) as i32 / 2,
+        scale,
+        label,
+        theme.dark_text,
+    );
+}
+
+/// Largest font scale up to `preferred` for which `fits` holds, or 1
+fn fitting_scale(preferred: u32, fits: impl Fn(u32) -> bool) -> u32 {
+    (1..=preferred)
+        .rev()
+        .find(|&scale| fits(scale))
+        .unwrap_or(1)
+}
+
+const LIVE_INSTRUCTIONS: &str = "PRESS MAPPED KEYS TO INPUT - PGUP/PGDN SCROLL - ESC TO QUIT";
+const DRILL_INSTRUCTIONS: &str = "ENTER THE PROMPTED MOVE - ESC TO QUIT";
+
+/// Draw the instructions bar at the bottom of the window
+fn draw_instructions<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    layout: &Layout,
+    instructions: &str,
+) {
+    let area = layout.instructions;
+    canvas.set_draw_color(theme.text);
+    canvas.fill_rect(area).ok();
+    let scale = fitting_scale(theme.font_scale, |scale| {
+        font::text_width(instructions, scale) + 28 <= area.width()
+    });
+    canvas.set_clip_rect(area);
+    font::draw_text(
+        canvas,
+        area.x() + 14,
+        area.y() + (area.height().saturating_sub(font::text_height(scale))) as i32 / 2,
+        scale,
+        instructions,
+        theme.dark_text,
+    );
+    canvas.set_clip_rect(None);
+}
+
+/// Draw one cell per key mapping inside `area`. Mappings flow into as many
+/// columns as needed so that every mapping stays visible, switching to the
+/// smaller font when the columns get too narrow for the regular one.
+fn draw_mappings_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    grammar: &Grammar,
+    area: Rect,
+) {
+    const COLUMN_GAP: u32 = 10;
+    const MIN_REGULAR_COLUMN_WIDTH: u32 = 150;
+
+    let columns_for = |row_height: u32| {
+        let rows = layout::rows_that_fit(area.height(), 0, row_height).max(1);
+        let columns = grammar.mappings.len().div_ceil(rows).max(1) as u32;
+        let width = (area.width().saturating_sub(COLUMN_GAP * (columns - 1)) / columns).max(1);
+        (rows, width)
+    };
+
+    // Rows of the preferred size get more padding than the fallback ones
+    let row_height_for = |scale: u32| {
+        let padding = if scale == theme.font_scale { 16 } else { 11 };
+        font::text_height(scale) + padding
+    };
+    let scale = fitting_scale(theme.font_scale, |scale| {
+        columns_for(row_height_for(scale)).1 >= MIN_REGULAR_COLUMN_WIDTH
+    });
+    let row_height = row_height_for(scale);
+    let (rows, column_width) = columns_for(row_height);
+    let text_offset = (row_height - 5 - font::text_height(scale)) as i32 / 2;
+    // Tokens line up after the widest key, named keys being longer
+    let key_width = grammar
+        .mappings
+        .keys()
+        .map(|key| font::text_width(key.as_str(), scale) + 6 * scale)
+        .fold(16 * scale, u32::max);
+
+    for (i, (key, token)) in grammar.mappings.iter().enumerate() {
+        let column = (i / rows) as u32;
+        let x = area.x() + (column * (column_width + COLUMN_GAP)) as i32;
+        let y = area.y() + ((i % rows) as u32 * row_height) as i32;
+        let cell = Rect::new(x, y, column_width, row_height - 5);
+
+        canvas.set_draw_color(theme.panel);
+        canvas.fill_rect(cell).ok();
+        canvas.set_clip_rect(cell);
+        font::draw_text(
+            canvas,
+            x + 8,
+            y + text_offset,
+            scale,
+            key.as_str(),
+            theme.header,
+        );
+        font::draw_text(
+            canvas,
+            x + 8 + key_width as i32,
+            y + text_offset,
+            scale,
+            token,
+            theme.text,
+        );
+        canvas.set_clip_rect(None);
+    }
+}
+
+/// Short label of a player, e.g. `P2`
+fn player_label(player: usize) -> String {
+    format!("P{}", player)
+}
+
+/// Draw the scrolling input history, newest input at the top
+fn draw_history_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    model: &UiModel,
+    area: Rect,
+) {
+    canvas.set_clip_rect(area);
+    draw_panel(canvas, theme, area, "INPUTS");
+
+    let scale = theme.font_scale;
+    let text_height = font::text_height(scale);
+    let row_height = text_height + 8;
+    let top_padding = panel_top_padding(scale) - 2;
+    let rows = layout::rows_that_fit(area.height(), top_padding, row_height);
+
+    let history = model.history();
+    for (i, entry) in history.iter().rev().take(rows).enumerate() {
+        let y = area.y() + (top_padding + i as u32 * row_height) as i32;
+        let mut x = area.x() + 8;
+        if model.players() > 1 {
+            let label = player_label(entry.player);
+            font::draw_text(canvas, x, y + 2, scale, &label, theme.header);
+            x += font::text_width(&label, scale) as i32 + 6;
+        }
+        let icon_width = font::text_width(&entry.token, scale) + 12;
+        canvas.set_draw_color(token_color(theme, &entry.token));
+        canvas
+            .fill_rect(Rect::new(x, y, icon_width, text_height + 4))
+            .ok();
+        font::draw_text(canvas, x + 6, y + 2, scale, &entry.token, theme.text);
+
+        x += icon_width as i32 + 10;
+        let gap = match (history.frame_gap(entry.index), history.gap(entry.index)) {
+            (Some(frames), _) => Some(format!("+{}F", frames)),
+            (None, Some(gap)) => Some(format!("+{}MS", gap.as_millis())),
+            (None, None) => None,
+        };
+        if let Some(label) = gap {
+            font::draw_text(canvas, x, y + 2, scale, &label, theme.border);
+            x += font::text_width(&label, scale) as i32 + 10;
+        }
+        if entry.reset {
+            font::draw_text(canvas, x, y + 2, scale, "RESET", theme.reset);
+        }
+    }
+    canvas.set_clip_rect(None);
+}
+
+/// Draw recognized moves with their damage and frame data, fading them out
+/// as they age
+fn draw_feed_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    dfa: &DFA,
+    model: &UiModel,
+    area: Rect,
+    now: Instant,
+) {
+    canvas.set_clip_rect(area);
+    draw_panel(canvas, theme, area, "RECOGNIZED");
+
+    let scale = theme.font_scale;
+    let row_height = font::text_height(scale) + 12;
+    let top_padding = panel_top_padding(scale);
+    let rows = layout::rows_that_fit(area.height(), top_padding, row_height);
+
+    for (i, entry) in model.feed().take(rows).enumerate() {
+        let age = now.saturating_duration_since(entry.at).as_secs_f32();
+        let remaining = (1.0 - age / MATCH_LIFETIME.as_secs_f32()).clamp(0.0, 1.0);
+        let fade = |color: Color| {
+            Color::RGBA(
+                color.r,
+                color.g,
+                color.b,
+                (remaining * color.a as f32) as u8,
+            )
+        };
+        let label = if model.players() > 1 {
+            format!("{} {} !!", player_label(entry.player), entry.name)
+        } else {
+            format!("{} !!", entry.name)
+        };
+        let y = area.y() + (top_padding + i as u32 * row_height) as i32;
+        font::draw_text(canvas, area.x() + 8, y, scale, &label, fade(theme.header));
+
+        let tag = dfa
+            .metadata(&entry.name)
+            .map(|m| m.tag())
+            .unwrap_or_default();
+        if !tag.is_empty() {
+            let x = area.x() + 8 + font::text_width(&format!("{} ", label), scale) as i32;
+            font::draw_text(canvas, x, y, scale, &tag, fade(theme.border));
+        }
+    }
+    canvas.set_clip_rect(None);
+}
+
+/// One line of the move list: either a character heading or one of its moves
+enum MoveListRow<'a> {
+    Character(Option<&'a str>),
+    Move(&'a MoveDef),
+}
+
+fn move_list_rows(grammar: &Grammar) -> Vec<MoveListRow<'_>> {
+    let mut rows = Vec::new();
+    for (character, moves) in grammar.moves_by_character() {
+        rows.push(MoveListRow::Character(character));
+        rows.extend(moves.into_iter().map(MoveListRow::Move));
+    }
+    rows
+}
+
+fn move_list_row_height(scale: u32) -> u32 {
+    font::text_height(scale) + 6
+}
+
+/// Largest scroll offset of the move list for the given layout
+pub fn move_list_max_scroll(grammar: &Grammar, layout: &Layout, theme: &Theme) -> usize {
+    let visible = layout::rows_that_fit(
+        layout.move_list.height(),
+        panel_top_padding(theme.font_scale),
+        move_list_row_height(theme.font_scale),
+    );
+    move_list_rows(grammar).len().saturating_sub(visible)
+}
+
+/// Draw every move of the grammar grouped by character. Moves the player is
+/// currently following are highlighted up to the number of inputs entered,
+/// and a move flashes briefly when it completes. Rows that do not fit are
+/// reached by scrolling.
+fn draw_move_list_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    grammar: &Grammar,
+    dfa: &DFA,
+    model: &UiModel,
+    area: Rect,
+    now: Instant,
+) {
+    canvas.set_clip_rect(area);
+    draw_panel(canvas, theme, area, "MOVE LIST");
+
+    let scale = theme.font_scale;
+    let row_height = move_list_row_height(scale);
+    let top_padding = panel_top_padding(scale);
+    let rows = move_list_rows(grammar);
+    let visible = layout::rows_that_fit(area.height(), top_padding, row_height);
+    let offset = model
+        .move_list_scroll()
+        .min(rows.len().saturating_sub(visible));
+
+    if rows.len() > visible {
+        let label = format!(
+            "{}-{}/{}",
+            offset + 1,
+            (offset + visible).min(rows.len()),
+            rows.len()
+        );
+        font::draw_text(
+            canvas,
+            area.right() - 8 - font::text_width(&label, scale) as i32,
+            area.y() + 8,
+            scale,
+            &label,
+            theme.border,
+        );
+    }
+
+    for (i, row) in rows.iter().skip(offset).take(visible).enumerate() {
+        let y = area.y() + (top_padding + i as u32 * row_height) as i32;
+        match row {
+            MoveListRow::Character(character) => {
+                font::draw_text(
+                    canvas,
+                    area.x() + 8,
+                    y + 3,
+                    scale,
+                    character.unwrap_or("OTHER"),
+                    theme.border,
+                );
+            }
+            MoveListRow::Move(m) => {
+                let entered = model
+                    .states()
+                    .iter()
+                    .map(|state| dfa.progress(state, &m.sequence))
+                    .max()
+                    .unwrap_or(0);
+                let highlight = Rect::new(area.x() + 4, y, area.width() - 8, row_height - 2);
+                if model.completed_within(&m.name, now, FLASH_DURATION) {
+                    canvas.set_draw_color(theme.flash);
+                    canvas.fill_rect(highlight).ok();
+                } else if entered > 0 {
+                    canvas.set_draw_color(theme.candidate);
+                    canvas.fill_rect(highlight).ok();
+                }
+
+                // Sequence keys, right aligned, entered keys highlighted
+                let keys: Vec<String> = m.sequence.iter().map(|k| k.to_string()).collect();
+                let sequence_width = font::text_width(&keys.join(" "), scale) as i32;
+
+                // Long names are cut short instead of running into the keys
+                let name_width = (area.right() - 12 - sequence_width - (area.x() + 20) - 8).max(0);
+                canvas.set_clip_rect(Rect::new(area.x() + 20, y, name_width as u32, row_height));
+                font::draw_text(
+                    canvas,
+                    area.x() + 20,
+                    y + 3,
+                    scale,
+                    m.short_name(),
+                    theme.text,
+                );
+                let tag = m.metadata.tag();
+                if !tag.is_empty() {
+                    let x = area.x()
+                        + 20
+                        + font::text_width(&format!("{} ", m.short_name()), scale) as i32;
+                    font::draw_text(canvas, x, y + 3, scale, &tag, theme.border);
+                }
+                canvas.set_clip_rect(area);
+
+                let mut x = area.right() - 12 - sequence_width;
+                for (i, key) in keys.iter().enumerate() {
+                    let color = if i < entered {
+                        theme.header
+                    } else {
+                        theme.border
+                    };
+                    font::draw_text(canvas, x, y + 3, scale, key, color);
+                    x += font::text_width(key, scale) as i32
+                        + font::text_width(" ", scale) as i32
+                        + scale as i32;
+                }
+            }
+        }
+    }
+    canvas.set_clip_rect(None);
+}
+
+/// Render key mappings to the SDL canvas, using the whole window body
+pub fn render_key_mappings<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    grammar: &Grammar,
+) {
+    let (width, height) = canvas
+        .output_size()
+        .unwrap_or((DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT));
+    let layout = Layout::compute_with_style(width, height, theme.layout);
+    draw_chrome(canvas, theme, &layout, "KEY MAPPINGS");
+    draw_mappings_panel(canvas, theme, grammar, layout.body);
+    draw_instructions(canvas, theme, &layout, LIVE_INSTRUCTIONS);
+    canvas.present();
+}
+
+/// Render one frame of the live training overlay: key mappings, input
+/// history with timing gaps, current DFA state, the recognized move feed and
+/// the move list with per-move progress
+pub fn render_frame<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    layout: &Layout,
+    theme: &Theme,
+    grammar: &Grammar,
+    dfa: &DFA,
+    model: &UiModel,
+    now: Instant,
+) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    draw_chrome(canvas, theme, layout, "TRAINING MODE");
+
+    // Mid-sequence, hint at the keys that keep a move going
+    let mut label = format!("STATE {}", model.state());
+    let expected = dfa.expected_symbols(model.state());
+    if model.players() > 1 {
+        let states: Vec<String> = model
+            .states()
+            .iter()
+            .enumerate()
+            .map(|(i, state)| format!("{} {}", player_label(i + 1), state))
+            .collect();
+        label = states.join("  ");
+    } else if model.state() != dfa.start_state() && !expected.is_empty() {
+        let keys: Vec<String> = expected.iter().map(|k| k.to_string()).collect();
+        label.push_str(&format!("  NEXT {}", keys.join("/")));
+    }
+    draw_header_label(canvas, theme, layout, &label);
+
+    draw_mappings_panel(canvas, theme, grammar, layout.mappings);
+    draw_history_panel(canvas, theme, model, layout.history);
+    draw_feed_panel(canvas, theme, dfa, model, layout.feed, now);
+    draw_move_list_panel(canvas, theme, grammar, dfa, model, layout.move_list, now);
+    draw_instructions(canvas, theme, layout, LIVE_INSTRUCTIONS);
+
+    canvas.present();
+}
+
+/// Draw the prompted move with one icon per input; entered inputs are lit
+fn draw_prompt_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    grammar: &Grammar,
+    drill: &Drill,
+    area: Rect,
+) {
+    canvas.set_clip_rect(area);
+    let title = match drill.count() {
+        Some(count) => format!("PROMPT {}/{}", drill.prompted(), count),
+        None => format!("PROMPT {}", drill.prompted()),
+    };
+    draw_panel(canvas, theme, area, &title);
+
+    let scale = theme.font_scale;
+    let mut y = area.y() + panel_top_padding(scale) as i32;
+    let Some(m) = drill.prompt() else {
+        font::draw_text(canvas, area.x() + 8, y, scale, "SESSION OVER", theme.text);
+        canvas.set_clip_rect(None);
+        return;
+    };
+
+    let name_scale = fitting_scale(scale + 1, |s| {
+        font::text_width(m.short_name(), s) + 16 <= area.width()
+    });
+    font::draw_text(
+        canvas,
+        area.x() + 8,
+        y,
+        name_scale,
+        m.short_name(),
+        theme.header,
+    );
+    y += font::text_height(name_scale) as i32 + 6;
+    if let Some(character) = m.character() {
+        font::draw_text(canvas, area.x() + 8, y, scale, character, theme.border);
+        y += font::text_height(scale) as i32 + 6;
+    }
+    y += 6;
+
+    // Input icons, wrapping onto new rows when the panel is too narrow
+    let icon_height = font::text_height(scale) + 8;
+    let mut x = area.x() + 8;
+    for (i, token) in grammar.token_names(&m.sequence).into_iter().enumerate() {
+        let icon_width = font::text_width(token, scale) + 12;
+        if x + icon_width as i32 > area.right() - 8 && x > area.x() + 8 {
+            x = area.x() + 8;
+            y += icon_height as i32 + 6;
+        }
+        let (fill, text) = if i < drill.progress() {
+            (theme.flash, theme.dark_text)
+        } else {
+            (token_color(theme, token), theme.text)
+        };
+        canvas.set_draw_color(fill);
+        canvas
+            .fill_rect(Rect::new(x, y, icon_width, icon_height))
+            .ok();
+        font::draw_text(canvas, x + 6, y + 4, scale, token, text);
+        x += icon_width as i32 + 6;
+    }
+    canvas.set_clip_rect(None);
+}
+
+/// Draw the verdict of the last attempt, one detail per line
+fn draw_attempt_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    grammar: &Grammar,
+    drill: &Drill,
+    area: Rect,
+) {
+    canvas.set_clip_rect(area);
+    draw_panel(canvas, theme, area, "LAST ATTEMPT");
+
+    if let Some(result) = drill.last_result() {
+        let scale = theme.font_scale;
+        let row_height = font::text_height(scale) + 8;
+        let top_padding = panel_top_padding(scale);
+        let rows = layout::rows_that_fit(area.height(), top_padding, row_height);
+        let verdict_color = if result.is_success() {
+            theme.header
+        } else {
+            theme.reset
+        };
+
+        let description = result.describe(grammar);
+        let lines = std::iter::once(result.move_name.as_str()).chain(description.split(", "));
+        for (i, line) in lines.take(rows).enumerate() {
+            let color = match i {
+                0 => theme.text,
+                1 => verdict_color,
+                _ => theme.border,
+            };
+            let line_scale =
+                fitting_scale(scale, |s| font::text_width(line, s) + 16 <= area.width());
+            font::draw_text(
+                canvas,
+                area.x() + 8,
+                area.y() + (top_padding + i as u32 * row_height) as i32,
+                line_scale,
+                line,
+                color,
+            );
+        }
+    }
+    canvas.set_clip_rect(None);
+}
+
+/// Draw clean/attempted counts and accuracy of every drilled move
+fn draw_session_panel<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    theme: &Theme,
+    drill: &Drill,
+    area: Rect,
+) {
+    canvas.set_clip_rect(area);
+    draw_panel(canvas, theme, area, "SESSION");
+
+    let scale = theme.font_scale;
+    let row_height = move_list_row_height(scale);
+    let top_padding = panel_top_padding(scale);
+    let summary = drill.summary();
+    let rows: Vec<(String, String)> = summary
+        .moves
+        .iter()
+        .map(|(m, tally)| {
+            (
+                m.short_name().to_string(),
+                format!(
+                    "{}/{} {:.0}%",
+                    tally.clean,
+                    tally.attempts,
+                    tally.accuracy() * 100.0
+                ),
+            )
+        })
+        .collect();
+    // Keep the total visible: show the most recent rows that fit above it
+    let visible = layout::rows_that_fit(area.height(), top_padding, row_height).saturating_sub(1);
+    let skip = rows.len().saturating_sub(visible);
+
+    let mut y = area.y() + top_padding as i32;
+    for (name, score) in rows.iter().skip(skip) {
+        let score_x = area.right() - 12 - font::text_width(score, scale) as i32;
+        canvas.set_clip_rect(Rect::new(
+            area.x() + 8,
+            y,
+            (score_x - area.x() - 16).max(1) as u32,
+            row_height,
+        ));
+        font::draw_text(canvas, area.x() + 8, y + 3, scale, name, theme.text);
+        canvas.set_clip_rect(area);
+        font::draw_text(canvas, score_x, y + 3, scale, score, theme.border);
+        y += row_height as i32;
+    }
+
+    if summary.attempts() > 0 {
+        let total = format!(
+            "{}/{} {:.0}%",
+            summary.clean(),
+            summary.attempts(),
+            summary.clean() as f64 * 100.0 / summary.attempts() as f64
+        );
+        font::draw_text(canvas, area.x() + 8, y + 3, scale, "TOTAL", theme.header);
+        font::draw_text(
+            canvas,
+            area.right() - 12 - font::text_width(&total, scale) as i32,
+            y + 3,
+            scale,
+            &total,
+            theme.header,
         );
-        canvas.fill_rect(rect).ok();
     }
+    canvas.set_clip_rect(None);
+}
 
-    // Instructions area at bottom
-    canvas.set_draw_color(TEXT_COLOR);
-    canvas
-        .fill_rect(Rect::new(
-            20,
-            WINDOW_HEIGHT as i32 - 100,
-            WINDOW_WIDTH - 40,
-            80,
-        ))
-        .ok();
+/// Render one frame of a drill session: key mappings, the prompted move with
+/// the inputs entered so far, the verdict of the last attempt and the
+/// session totals
+pub fn render_drill_frame<T: RenderTarget>(
+    canvas: &mut Canvas<T>,
+    layout: &Layout,
+    theme: &Theme,
+    grammar: &Grammar,
+    drill: &Drill,
+) {
+    canvas.set_blend_mode(BlendMode::Blend);
+    draw_chrome(canvas, theme, layout, "DRILL MODE");
+    let summary = drill.summary();
+    if summary.attempts() > 0 {
+        let label = format!(
+            "ACCURACY {:.0}%",
+            summary.clean() as f64 * 100.0 / summary.attempts() as f64
+        );
+        draw_header_label(canvas, theme, layout, &label);
+    }
+
+    draw_mappings_panel(canvas, theme, grammar, layout.mappings);
+    draw_prompt_panel(canvas, theme, grammar, drill, layout.history);
+    draw_attempt_panel(canvas, theme, grammar, drill, layout.feed);
+    draw_session_panel(canvas, theme, drill, layout.move_list);
+    draw_instructions(canvas, theme, layout, DRILL_INSTRUCTIONS);
 
     canvas.present();
 }
 
-/// Get window dimensions
+/// Create an off-screen software canvas. Rendering into it does not need a
+/// display, so the UI can be drawn on headless hosts.
+pub fn create_offscreen_canvas(
+    width: u32,
+    height: u32,
+) -> Result<Canvas<Surface<'static>>, String> {
+    let surface = Surface::new(width, height, PixelFormatEnum::RGB888)?;
+    surface.into_canvas()
+}
+
+/// Write the contents of an off-screen canvas to a BMP file
+pub fn save_bmp<P: AsRef<Path>>(canvas: &Canvas<Surface>, path: P) -> Result<(), String> {
+    canvas.surface().save_bmp(path.as_ref()).map_err(|e| {
+        format!(
+            "Failed to write frame to '{}': {}",
+            path.as_ref().display(),
+            e
+        )
+    })
+}
+
+/// Render one overlay frame off-screen at the default window size and dump
+/// it as a BMP file
+pub fn dump_frame_bmp<P: AsRef<Path>>(
+    path: P,
+    theme: &Theme,
+    grammar: &Grammar,
+    dfa: &DFA,
+    model: &UiModel,
+    now: Instant,
+) -> Result<(), String> {
+    let mut canvas = create_offscreen_canvas(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)?;
+    let layout =
+        Layout::compute_with_style(DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT, theme.layout);
+    render_frame(&mut canvas, &layout, theme, grammar, dfa, model, now);
+    save_bmp(&canvas, path)
+}
+
+/// Get the default window dimensions
 pub fn get_window_size() -> (u32, u32) {
-    (WINDOW_WIDTH, WINDOW_HEIGHT)
+    (DEFAULT_WINDOW_WIDTH, DEFAULT_WINDOW_HEIGHT)
 }
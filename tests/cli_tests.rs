@@ -0,0 +1,175 @@
+mod common;
+
+use automate_refuse_de_nier::tools::cli::{
+    parse_args, Command, ExportFormat, GenerateOptions, MatchOptions, RunOptions, EXIT_GRAMMAR,
+    EXIT_OK, EXIT_USAGE,
+};
+use automate_refuse_de_nier::tools::clock::TimingWindow;
+use automate_refuse_de_nier::tools::dfa::MatchPolicy;
+use automate_refuse_de_nier::tools::generate::GeneratorConfig;
+use common::{run_binary, run_binary_with_input};
+use std::path::PathBuf;
+
+#[test]
+fn grammar_path_alone_means_run() {
+    let command = parse_args(["grammars/mk9.gmr", "--gui", "--theme=light"]).expect("valid");
+    assert_eq!(
+        command,
+        Command::Run(RunOptions {
+            grammar: PathBuf::from("grammars/mk9.gmr"),
+            gui: true,
+            theme: Some("light".to_string()),
+            ..RunOptions::default()
+        })
+    );
+    // Options may come before the grammar path
+    assert_eq!(
+        parse_args(["run", "--debug", "grammars/mk9.gmr"]),
+        parse_args(["grammars/mk9.gmr", "--debug"])
+    );
+}
+
+#[test]
+fn subcommands_parse_their_own_options() {
+    assert_eq!(
+        parse_args(["export", "g.gmr", "-f", "table", "-o", "out.txt"]),
+        Ok(Command::Export {
+            grammar: PathBuf::from("g.gmr"),
+            format: ExportFormat::Table,
+            output: Some(PathBuf::from("out.txt")),
+        })
+    );
+    assert_eq!(
+        parse_args(["list-moves", "g.gmr", "--character", "Jax"]),
+        Ok(Command::ListMoves {
+            grammar: PathBuf::from("g.gmr"),
+            character: Some("Jax".to_string()),
+        })
+    );
+    assert_eq!(
+        parse_args(["check", "--help"]),
+        Ok(Command::Help(Some("check".to_string())))
+    );
+    assert_eq!(parse_args(["-V"]), Ok(Command::Version));
+    assert_eq!(
+        parse_args(["replay", "g.gmr", "--match", "longest", "--window=100"]),
+        Ok(Command::Replay {
+            grammar: PathBuf::from("g.gmr"),
+            input: None,
+            debug: false,
+            matching: MatchOptions {
+                policy: MatchPolicy::Longest,
+                window: Some(TimingWindow::Millis(100)),
+            },
+        })
+    );
+    assert_eq!(
+        parse_args(["run", "g.gmr", "--window", "15f", "--fps", "30"]),
+        Ok(Command::Run(RunOptions {
+            grammar: PathBuf::from("g.gmr"),
+            matching: MatchOptions {
+                window: Some(TimingWindow::Frames(15)),
+                ..MatchOptions::default()
+            },
+            fps: Some(30),
+            ..RunOptions::default()
+        }))
+    );
+    assert_eq!(
+        parse_args(["generate", "big.gmr", "--moves", "50", "--length", "4", "-i", "in.txt"]),
+        Ok(Command::Generate(GenerateOptions {
+            grammar: PathBuf::from("big.gmr"),
+            config: GeneratorConfig {
+                moves_per_character: 50,
+                min_length: 4,
+                max_length: 4,
+                ..GeneratorConfig::default()
+            },
+            input: Some(PathBuf::from("in.txt")),
+            expected: None,
+            presses: 1000,
+        }))
+    );
+}
+
+#[test]
+fn mistakes_are_usage_errors() {
+    let typo = parse_args(["grammars/mk9.gmr", "--gu1"]).unwrap_err();
+    assert_eq!(typo.code, EXIT_USAGE);
+    assert!(typo.message.contains("did you mean '--gui'"), "{}", typo);
+
+    for args in [
+        vec!["chek", "g.gmr"],
+        vec!["check"],
+        vec!["check", "a.gmr", "b.gmr"],
+        vec!["run", "g.gmr", "--theme"],
+        vec!["run", "g.gmr", "--gui=yes"],
+        vec!["run", "g.gmr", "--gui", "--gui"],
+        vec!["export", "g.gmr", "--format", "svg"],
+        vec!["check", "g.gmr", "--gui"],
+        vec!["replay", "g.gmr", "--match", "first"],
+        vec!["run", "g.gmr", "--window", "soon"],
+        vec!["run", "g.gmr", "--window", "1.5f"],
+        vec!["run", "g.gmr", "--fps", "0"],
+        vec!["run", "g.gmr", "--fps", "5000"],
+        vec!["generate", "g.gmr", "--keys", "40"],
+        vec!["generate", "g.gmr", "--length", "6-2"],
+        vec!["generate", "g.gmr", "--characters", "0"],
+        vec!["generate", "g.gmr", "--shared", "150"],
+    ] {
+        let err = parse_args(args.clone()).expect_err("invalid command line");
+        assert_eq!(err.code, EXIT_USAGE, "{args:?}: {err}");
+    }
+}
+
+#[test]
+fn binary_exit_codes_match_failure_class() {
+    let help = run_binary(&["--help"]);
+    assert_eq!(help.status.code(), Some(EXIT_OK as i32));
+    assert!(String::from_utf8_lossy(&help.stdout).contains("list-moves"));
+
+    let check = run_binary(&["check", "grammars/mk9_with_moves.gmr"]);
+    assert_eq!(check.status.code(), Some(EXIT_OK as i32));
+    assert!(String::from_utf8_lossy(&check.stdout).contains("OK"));
+
+    assert_eq!(
+        run_binary(&["check", "grammars/missing.gmr"]).status.code(),
+        Some(EXIT_GRAMMAR as i32)
+    );
+    assert_eq!(
+        run_binary(&["run", "grammars/mk9.gmr", "--gu1"])
+            .status
+            .code(),
+        Some(EXIT_USAGE as i32)
+    );
+}
+
+#[test]
+fn replay_prints_recognized_moves() {
+    let output = run_binary_with_input(&["replay", "grammars/mk9_with_moves.gmr"], b"sso\n");
+
+    assert_eq!(output.status.code(), Some(EXIT_OK as i32));
+    assert!(String::from_utf8_lossy(&output.stdout).contains("Butt slam"));
+}
+
+#[test]
+fn replay_labels_moves_by_player() {
+    // Both players enter Butt slam with their sequences interleaved
+    let output = run_binary_with_input(&["replay", "grammars/mk9_two_players.gmr"], b"s5s5ob\n");
+
+    assert_eq!(output.status.code(), Some(EXIT_OK as i32));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("P1 Butt slam (Ermac) !!"), "{stdout}");
+    assert!(stdout.contains("P2 Butt slam (Ermac) !!"), "{stdout}");
+}
+
+#[test]
+fn move_metadata_shows_in_move_lists() {
+    let output = run_binary(&["list-moves", "grammars/mk9_with_moves.toml", "-c", "ermac"]);
+    assert_eq!(output.status.code(), Some(EXIT_OK as i32));
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("  Butt slam: Down, Down, [BP] (s s o)\n    special\n"),
+        "{stdout}"
+    );
+}
@@ -0,0 +1,185 @@
+use automate_refuse_de_nier::tools::clock::{FrameClock, ManualTime, TimingWindow, DEFAULT_FPS};
+use automate_refuse_de_nier::tools::dfa::MatchPolicy;
+use automate_refuse_de_nier::tools::document::{parse_grammar_document, GrammarFormat};
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::parse_grammar_text;
+use automate_refuse_de_nier::tools::ui::UiModel;
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use std::time::Duration;
+
+fn clock_at(fps: u32) -> (ManualTime, FrameClock<ManualTime>) {
+    let time = ManualTime::new();
+    let clock = FrameClock::with_source(time.clone(), fps);
+    (time, clock)
+}
+
+#[test]
+fn frames_count_ticks_since_the_start() {
+    let (time, clock) = clock_at(DEFAULT_FPS);
+    assert_eq!(clock.frame(), 0);
+
+    time.advance(Duration::from_millis(16));
+    assert_eq!(clock.frame(), 0, "a tick lasts 16.67ms at 60 fps");
+    time.advance(Duration::from_millis(1));
+    assert_eq!(clock.frame(), 1);
+    time.advance(Duration::from_secs(1));
+    assert_eq!(clock.frame(), 61);
+
+    assert_eq!(
+        clock.instant_of(61),
+        clock.instant_of(0) + Duration::from_nanos(1_016_666_667)
+    );
+    assert_eq!(clock.frame_at(clock.instant_of(90)), 90);
+    assert_eq!(clock.duration_of(30), Duration::from_millis(500));
+    assert_eq!(clock.frames_in(Duration::from_millis(250)), 15);
+}
+
+#[test]
+fn waiting_paces_one_tick_at_a_time_and_skips_missed_ones() {
+    let (time, clock) = clock_at(30);
+    assert_eq!(clock.wait_next_frame(), 1);
+    assert_eq!(clock.now(), clock.instant_of(1));
+    assert_eq!(clock.wait_next_frame(), 2);
+
+    // A slow iteration overruns two ticks: the clock does not catch up
+    time.advance(Duration::from_millis(80));
+    assert_eq!(clock.frame(), 4);
+    assert_eq!(clock.wait_next_frame(), 5);
+    assert_eq!(clock.now(), clock.instant_of(5));
+}
+
+#[test]
+fn timing_windows_read_milliseconds_or_frames() {
+    assert_eq!(TimingWindow::parse("250"), Some(TimingWindow::Millis(250)));
+    assert_eq!(
+        TimingWindow::parse("100ms"),
+        Some(TimingWindow::Millis(100))
+    );
+    assert_eq!(TimingWindow::parse("15F"), Some(TimingWindow::Frames(15)));
+    assert_eq!(TimingWindow::parse("f"), None);
+    assert_eq!(TimingWindow::parse("-3f"), None);
+    assert_eq!(TimingWindow::parse("soon"), None);
+
+    assert_eq!(
+        TimingWindow::Frames(15).duration(60),
+        Duration::from_millis(250)
+    );
+    assert_eq!(
+        TimingWindow::Frames(15).duration(30),
+        Duration::from_millis(500)
+    );
+    assert_eq!(
+        TimingWindow::Millis(80).duration(30),
+        Duration::from_millis(80)
+    );
+}
+
+#[test]
+fn inputs_are_stamped_with_their_frame() {
+    let dfa = DFA::from_moves(vec![(vec!['s', 'o'], "Uppercut".to_string())]);
+    let config = DFAConfig::default();
+    let (time, clock) = clock_at(DEFAULT_FPS);
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut model = UiModel::new(dfa.start_state());
+
+    for _ in 0..3 {
+        clock.wait_next_frame();
+    }
+    let down = recognizer.feed_on(&dfa, 's', "Down", &config, &clock);
+    assert_eq!(down.frame, Some(3));
+    model.record(&down, clock.now());
+
+    time.advance(clock.duration_of(7));
+    let punch = recognizer.feed_on(&dfa, 'o', "[BP]", &config, &clock);
+    assert_eq!(punch.frame, Some(10));
+    assert_eq!(punch.matches, vec!["Uppercut".to_string()]);
+    model.record(&punch, clock.now());
+
+    let latest = model.history().latest().unwrap();
+    assert_eq!(latest.frame, Some(10));
+    assert_eq!(model.history().frame_gap(latest.index), Some(7));
+
+    // Inputs fed without a clock carry no frame
+    let unstamped = recognizer.feed(&dfa, 's', "Down", &config);
+    assert_eq!(unstamped.frame, None);
+}
+
+#[test]
+fn continuation_windows_in_frames_are_frame_accurate() {
+    let dfa = DFA::from_moves(vec![
+        (vec!['o'], "Claw Slam".to_string()),
+        (vec!['o', 'l'], "Saibot Blast".to_string()),
+    ]);
+    let (time, clock) = clock_at(DEFAULT_FPS);
+    let config = DFAConfig {
+        policy: MatchPolicy::Longest,
+        continuation_window: TimingWindow::Frames(6).duration(clock.fps()),
+        ..DFAConfig::default()
+    };
+
+    // The follow-up lands on the last frame of the window
+    let mut recognizer = Recognizer::new(&dfa);
+    let claw = recognizer.feed_on(&dfa, 'o', "[BP]", &config, &clock);
+    assert!(claw.matches.is_empty(), "Claw Slam waits for Saibot Blast");
+    time.advance(clock.duration_of(6));
+    assert!(recognizer.poll(&dfa, &config, clock.now()).is_empty());
+    let blast = recognizer.feed_on(&dfa, 'l', "[FP]", &config, &clock);
+    assert_eq!(blast.frame, Some(6));
+    assert_eq!(blast.matches, vec!["Saibot Blast".to_string()]);
+
+    // One frame later the held-back move is reported on its own
+    let mut recognizer = Recognizer::new(&dfa);
+    recognizer.feed_on(&dfa, 'o', "[BP]", &config, &clock);
+    time.advance(clock.duration_of(7));
+    assert_eq!(
+        recognizer.poll(&dfa, &config, clock.now()),
+        vec!["Claw Slam".to_string()]
+    );
+}
+
+#[test]
+fn grammars_declare_their_continuation_window() {
+    let text = "\
+l, [FP]
+o, [BP]
+
+window 6f
+
+Claw Slam:    o
+Saibot Blast: o l
+";
+    let grammar = parse_grammar_text(text, "noob.gmr").expect("valid grammar");
+    assert_eq!(grammar.leniency.window, Some(TimingWindow::Frames(6)));
+    assert_eq!(format_gmr(text).unwrap(), text);
+    assert_eq!(
+        parse_grammar_text(&grammar.to_gmr(), "noob.gmr").unwrap(),
+        grammar
+    );
+
+    let document = parse_grammar_document(
+        r#"{ "mappings": { "o": "[BP]" }, "leniency": { "window": "100ms" } }"#,
+        GrammarFormat::Json,
+    )
+    .expect("valid json");
+    assert_eq!(document.leniency.window, Some(TimingWindow::Millis(100)));
+
+    for (text, expected) in [
+        (
+            "window soon",
+            "1: window must be milliseconds or frames like 20f: 'soon'",
+        ),
+        ("window 1.5f", "window must be milliseconds or frames"),
+    ] {
+        let err = parse_grammar_text(text, "bad.gmr").expect_err(text);
+        assert!(err.contains(expected), "{text}: {err}");
+    }
+    let err = parse_grammar_document(
+        "[mappings]\n[leniency]\nwindow = \"later\"\n",
+        GrammarFormat::Toml,
+    )
+    .unwrap_err();
+    assert!(
+        err.contains("window must be milliseconds or frames"),
+        "{err}"
+    );
+}
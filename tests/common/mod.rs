@@ -0,0 +1,97 @@
+//! Helpers shared by the integration tests: temporary directories, runs
+//! of the binary, and the seeds of the property and fuzz tests, which must
+//! run offline and replay exactly from a printed seed.
+
+// Each test crate uses only part of this module
+#![allow(dead_code)]
+
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// A directory under the system temporary directory, removed on drop
+pub struct TestTempDir {
+    path: PathBuf,
+}
+
+impl TestTempDir {
+    pub fn new(prefix: &str) -> Self {
+        static CREATED: AtomicUsize = AtomicUsize::new(0);
+        let unique = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_nanos();
+        let dir = env::temp_dir().join(format!(
+            "{}_{}_{}_{}",
+            prefix,
+            std::process::id(),
+            CREATED.fetch_add(1, Ordering::Relaxed),
+            unique
+        ));
+        fs::create_dir(&dir).expect("create temp dir");
+        Self { path: dir }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Write `contents` to the file `name` of the directory
+    pub fn file(&self, name: &str, contents: &str) -> PathBuf {
+        let path = self.path.join(name);
+        fs::write(&path, contents).expect("write temp file");
+        path
+    }
+}
+
+impl Drop for TestTempDir {
+    fn drop(&mut self) {
+        if self.path.exists() {
+            let _ = fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// Run the binary with `args` and no input
+pub fn run_binary(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_automate_refuse_de_nier"))
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .expect("run binary")
+}
+
+/// Run the binary with `args`, typing `input` on its standard input
+pub fn run_binary_with_input(args: &[&str], input: &[u8]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_automate_refuse_de_nier"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .and_then(|mut child| {
+            child.stdin.take().expect("stdin").write_all(input)?;
+            child.wait_with_output()
+        })
+        .expect("run binary")
+}
+
+/// Cases to run, overridden by the environment variable `name` so that
+/// longer runs can be made locally
+pub fn cases(name: &str, default: usize) -> usize {
+    env::var(name)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
+
+/// First seed of a run, overridden by `FT_ALITY_SEED` to replay a failure
+pub fn first_seed() -> u64 {
+    env::var("FT_ALITY_SEED")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(0x5eed)
+}
@@ -0,0 +1,126 @@
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::document::{parse_grammar_document, GrammarFormat};
+use automate_refuse_de_nier::tools::parsing::{
+    parse_grammar_file, Grammar, MoveCategory, MoveMetadata,
+};
+
+#[test]
+fn documents_load_the_same_grammar_as_gmr() {
+    let gmr = parse_grammar_file("grammars/mk9_with_moves.gmr").expect("valid gmr");
+    let json = parse_grammar_file("grammars/mk9_with_moves.json").expect("valid json");
+    assert_eq!(json, gmr);
+
+    let toml = parse_grammar_file("grammars/mk9_with_moves.toml").expect("valid toml");
+    assert_eq!(toml.mappings, gmr.mappings);
+    let names = |g: &Grammar| {
+        g.moves
+            .iter()
+            .map(|m| (m.name.clone(), m.sequence.clone()))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(names(&toml), names(&gmr));
+    assert_eq!(toml.moves[5].metadata.category, Some(MoveCategory::Special));
+}
+
+#[test]
+fn format_is_told_by_the_extension() {
+    assert_eq!(GrammarFormat::from_path("a/mk9.toml"), GrammarFormat::Toml);
+    assert_eq!(GrammarFormat::from_path("MK9.JSON"), GrammarFormat::Json);
+    assert_eq!(GrammarFormat::from_path("mk9.gmr"), GrammarFormat::Gmr);
+    assert_eq!(GrammarFormat::from_path("mk9"), GrammarFormat::Gmr);
+}
+
+#[test]
+fn documents_carry_players_characters_and_metadata() {
+    let text = r#"
+        [mappings]
+        s = "Down"
+        o = "[BP]"
+
+        [players.2]
+        5 = "Down"
+
+        [[moves]]
+        name = "Poke"
+        sequence = ["o"]
+
+        [[characters]]
+        name = "Ermac"
+
+        [[characters.moves]]
+        name = "Butt slam"
+        sequence = ["s", "s", "o"]
+        priority = 2
+        damage = 12
+        startup = 14
+        active = 3
+        recovery = 30
+        meter = 1
+        category = "x-ray"
+        notes = "Hits overhead"
+    "#;
+    let grammar = parse_grammar_document(text, GrammarFormat::Toml).expect("valid document");
+    assert_eq!(grammar.resolve_key("5"), Some((2, &Symbol::from('s'))));
+    assert_eq!(grammar.moves[0].name, "Poke");
+    let slam = &grammar.moves[1];
+    assert_eq!(slam.name, "Butt slam (Ermac)");
+    assert_eq!(slam.character(), Some("Ermac"));
+    assert_eq!(slam.priority, 2);
+    assert_eq!(
+        slam.metadata,
+        MoveMetadata {
+            damage: Some(12),
+            startup: Some(14),
+            active: Some(3),
+            recovery: Some(30),
+            meter: Some(1),
+            category: Some(MoveCategory::XRay),
+            notes: Some("Hits overhead".to_string()),
+        }
+    );
+    assert!(grammar.moves[0].metadata.is_empty());
+}
+
+#[test]
+fn invalid_documents_are_rejected() {
+    for (text, format, expected) in [
+        (
+            r#"{"mappings": {"s s": "Down"}}"#,
+            GrammarFormat::Json,
+            "mapping: invalid key 's s'",
+        ),
+        (
+            r#"{"mappings": {"s": "Down"}, "moves": [{"name": "Slam", "sequence": ["s s"]}]}"#,
+            GrammarFormat::Json,
+            "move 'Slam': invalid key 's s'",
+        ),
+        (
+            r#"{"mappings": {"s": "Down"}, "moves": [{"name": "Slam", "sequence": ["s"], "damge": 3}]}"#,
+            GrammarFormat::Json,
+            "unknown field `damge`",
+        ),
+        (
+            "[mappings]\ns = \"Down\"\n[players.9]\n5 = \"Down\"\n",
+            GrammarFormat::Toml,
+            "player must be a number from 2 to 4",
+        ),
+        (
+            "[mappings]\ns = \"Down\"\n[players.2]\n5 = \"Jump\"\n",
+            GrammarFormat::Toml,
+            "player 1 does not define",
+        ),
+        (
+            "moves = []\n",
+            GrammarFormat::Toml,
+            "missing field `mappings`",
+        ),
+        (
+            r#"{"mappings": {"s": "Down"}, "moves": [{"name": "", "sequence": ["s"]}]}"#,
+            GrammarFormat::Json,
+            "move name must not be empty",
+        ),
+    ] {
+        let err = parse_grammar_document(text, format).expect_err("invalid document");
+        assert!(err.contains(expected), "unexpected error message: {err}");
+    }
+}
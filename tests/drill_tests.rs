@@ -0,0 +1,217 @@
+use automate_refuse_de_nier::tools::cli::{parse_args, Command, DEFAULT_DRILL_COUNT, EXIT_USAGE};
+use automate_refuse_de_nier::tools::clock::{FrameClock, ManualTime, DEFAULT_FPS};
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::drill::{Drill, DrillOrder, Outcome, WrongInput, SETTLE_TIME};
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, MoveDef};
+use std::time::{Duration, Instant};
+
+fn moves() -> Vec<MoveDef> {
+    [
+        ("Claw Slam", "o"),
+        ("Saibot Blast", "ol"),
+        ("Butt slam", "sso"),
+    ]
+    .iter()
+    .map(|(name, keys)| MoveDef {
+        name: name.to_string(),
+        sequence: keys.chars().map(Symbol::from).collect(),
+        ..MoveDef::default()
+    })
+    .collect()
+}
+
+fn attempt(drill: &mut Drill, keys: &str, start: Instant) {
+    for (i, key) in keys.chars().enumerate() {
+        drill.input(key, start + Duration::from_millis(50 * (i as u64 + 1)));
+    }
+}
+
+#[test]
+fn sequential_drill_cycles_through_moves_until_count() {
+    let mut drill = Drill::new(moves(), DrillOrder::Sequential, 1).with_count(4);
+    let now = Instant::now();
+    let mut names = Vec::new();
+    while let Some(m) = drill.next_prompt(now) {
+        names.push(m.name.clone());
+        drill.finish_attempt();
+    }
+    assert_eq!(
+        names,
+        vec!["Claw Slam", "Saibot Blast", "Butt slam", "Claw Slam"]
+    );
+    assert_eq!(drill.prompted(), 4);
+}
+
+#[test]
+fn attempts_are_scored_clean_sloppy_or_incomplete() {
+    let mut drill = Drill::new(moves(), DrillOrder::Sequential, 1);
+    let start = Instant::now();
+
+    // Claw Slam, entered exactly
+    drill.next_prompt(start);
+    attempt(&mut drill, "o", start);
+    let clean = drill.finish_attempt().expect("prompted");
+    assert_eq!(clean.outcome, Outcome::Clean);
+    assert_eq!(clean.reaction, Some(Duration::from_millis(50)));
+    assert_eq!(clean.duration, Some(Duration::ZERO));
+
+    // Saibot Blast with a stray input in the middle and one after
+    drill.next_prompt(start);
+    attempt(&mut drill, "osoll", start);
+    let sloppy = drill.finish_attempt().expect("prompted");
+    assert_eq!(sloppy.outcome, Outcome::Sloppy);
+    assert_eq!(
+        sloppy.wrong_inputs,
+        vec![WrongInput {
+            step: 1,
+            expected: 'l'.into(),
+            got: 's'.into()
+        }]
+    );
+    assert_eq!(sloppy.extra_inputs, 1);
+    assert_eq!(sloppy.duration, Some(Duration::from_millis(150)));
+
+    // Butt slam: one "s" too many still ends on the sequence
+    drill.next_prompt(start);
+    attempt(&mut drill, "ssso", start);
+    assert!(drill.is_complete());
+    assert_eq!(
+        drill.finish_attempt().map(|r| r.outcome),
+        Some(Outcome::Sloppy)
+    );
+
+    // Claw Slam again, never entered
+    drill.next_prompt(start);
+    attempt(&mut drill, "s", start);
+    let missed = drill.finish_attempt().expect("prompted");
+    assert_eq!(missed.outcome, Outcome::Incomplete { entered: 0 });
+    assert!(!missed.is_completed());
+
+    let summary = drill.summary();
+    assert_eq!((summary.clean(), summary.attempts()), (1, 4));
+    let (claw, tally) = &summary.moves[0];
+    assert_eq!(claw.name, "Claw Slam");
+    assert_eq!((tally.clean, tally.attempts), (1, 2));
+    assert_eq!(tally.average_clean_time(), Some(Duration::from_millis(50)));
+}
+
+#[test]
+fn attempts_end_when_they_settle_or_time_out_on_the_clock() {
+    let time = ManualTime::new();
+    let clock = FrameClock::with_source(time.clone(), DEFAULT_FPS);
+    let time_limit = Duration::from_secs(1);
+    let mut drill = Drill::new(moves(), DrillOrder::Sequential, 1).with_count(3);
+    drill.next_prompt(clock.now());
+
+    // Claw Slam stays open for extra inputs until it settles
+    time.advance(clock.duration_of(12));
+    drill.input('o', clock.now());
+    time.advance(SETTLE_TIME - clock.duration_of(1));
+    assert_eq!(drill.tick(clock.now(), time_limit), None);
+    time.advance(clock.duration_of(1));
+    let clean = drill.tick(clock.now(), time_limit).expect("settled");
+    assert_eq!(clean.outcome, Outcome::Clean);
+    assert_eq!(clean.reaction, Some(clock.duration_of(12)));
+    assert_eq!(
+        drill.prompt().map(|m| m.name.as_str()),
+        Some("Saibot Blast")
+    );
+
+    // Saibot Blast is left half entered until the time limit runs out
+    drill.input('o', clock.now());
+    time.advance(time_limit - clock.duration_of(1));
+    assert_eq!(drill.tick(clock.now(), time_limit), None);
+    time.advance(clock.duration_of(1));
+    let incomplete = drill.tick(clock.now(), time_limit).expect("timed out");
+    assert_eq!(incomplete.outcome, Outcome::Incomplete { entered: 1 });
+
+    // Nothing times out before the first input
+    time.advance(time_limit * 2);
+    assert_eq!(drill.tick(clock.now(), time_limit), None);
+    drill.input('s', clock.now());
+    drill.input('s', clock.now());
+    drill.input('o', clock.now());
+    time.advance(SETTLE_TIME);
+    assert!(drill.tick(clock.now(), time_limit).is_some());
+    assert_eq!(drill.prompt(), None, "the session is over");
+    time.advance(time_limit);
+    assert_eq!(drill.tick(clock.now(), time_limit), None);
+}
+
+#[test]
+fn weighted_drill_favours_failed_moves() {
+    let mut drill = Drill::new(moves(), DrillOrder::Weighted, 7);
+    let now = Instant::now();
+    // Fail Butt slam a few times, get the others right once
+    for _ in 0..3 {
+        while drill.next_prompt(now).map(|m| m.name.clone()) != Some("Butt slam".into()) {
+            let keys: String = drill.prompt().unwrap().sequence.concat();
+            attempt(&mut drill, &keys, now);
+            drill.finish_attempt();
+        }
+        drill.finish_attempt();
+    }
+
+    let mut butt_slam = 0;
+    for _ in 0..300 {
+        if drill.next_prompt(now).unwrap().name == "Butt slam" {
+            butt_slam += 1;
+        }
+    }
+    // Weight 7 out of 9: far more often than the uniform third
+    assert!(
+        butt_slam > 150,
+        "Butt slam prompted {butt_slam} times out of 300"
+    );
+}
+
+#[test]
+fn seeded_random_drills_repeat() {
+    let picks = |seed| {
+        let mut drill = Drill::new(moves(), DrillOrder::Random, seed).with_count(20);
+        let mut names = Vec::new();
+        while let Some(m) = drill.next_prompt(Instant::now()) {
+            names.push(m.name.clone());
+        }
+        names
+    };
+    assert_eq!(picks(42), picks(42));
+    assert_eq!(picks(42).len(), 20);
+}
+
+#[test]
+fn describe_uses_token_names() {
+    let grammar = parse_grammar_file("grammars/mk9_with_moves.gmr").expect("parse grammar");
+    let butt_slam = grammar
+        .moves
+        .iter()
+        .find(|m| m.short_name() == "Butt slam")
+        .cloned()
+        .expect("Butt slam");
+    let mut drill = Drill::new(vec![butt_slam], DrillOrder::Sequential, 1);
+    let start = Instant::now();
+    drill.next_prompt(start);
+    attempt(&mut drill, "sl", start);
+    let text = drill.finish_attempt().expect("prompted").describe(&grammar);
+    assert!(text.starts_with("MISSED (0/3 inputs)"), "{text}");
+    assert!(text.contains("expected Down got [FP]"), "{text}");
+}
+
+#[test]
+fn drill_command_line_is_validated() {
+    match parse_args(["drill", "g.gmr", "--order", "weighted", "--seed", "5"]) {
+        Ok(Command::Drill(options)) => {
+            assert_eq!(options.order, DrillOrder::Weighted);
+            assert_eq!(options.seed, Some(5));
+            assert_eq!(options.count, DEFAULT_DRILL_COUNT);
+        }
+        other => panic!("unexpected parse: {other:?}"),
+    }
+    for args in [
+        vec!["drill", "g.gmr", "--count", "0"],
+        vec!["drill", "g.gmr", "--count", "ten"],
+        vec!["drill", "g.gmr", "--order", "shuffled"],
+    ] {
+        assert_eq!(parse_args(args).unwrap_err().code, EXIT_USAGE);
+    }
+}
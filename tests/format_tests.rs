@@ -0,0 +1,105 @@
+mod common;
+
+use automate_refuse_de_nier::tools::cli::{EXIT_OK, EXIT_UNFORMATTED, EXIT_USAGE};
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, parse_grammar_text, Grammar};
+use common::{run_binary, TestTempDir};
+use std::fs;
+use std::path::Path;
+use std::process::Output;
+
+fn reparse(text: &str) -> Grammar {
+    parse_grammar_text(text, Path::new("grammars/formatted.gmr")).expect("formatted text parses")
+}
+
+#[test]
+fn grammars_survive_a_round_trip_through_gmr() {
+    for path in [
+        "grammars/mk9.gmr",
+        "grammars/mk9_with_moves.gmr",
+        "grammars/mk9_two_players.gmr",
+        "grammars/mk9_with_moves.json",
+        "grammars/mk9_with_moves.toml",
+    ] {
+        let grammar = parse_grammar_file(path).expect("valid grammar");
+        assert_eq!(reparse(&grammar.to_gmr()), grammar, "{path}");
+    }
+
+    let grammar = reparse(
+        "s, Down\no [BP]\nplayer 3\n5, Down\nSlam: s s o | priority=-2 notes=Low, fast\n\
+         Idle:|category=normal startup=7 meter=0\n",
+    );
+    let text = grammar.to_gmr();
+    assert_eq!(
+        text,
+        "o, [BP]\ns, Down\n\nplayer 3\n5, Down\n\n\
+         Slam: s s o | priority=-2 notes=Low, fast\n\
+         Idle: | startup=7 meter=0 category=normal\n"
+    );
+    assert_eq!(reparse(&text), grammar);
+    assert_eq!(Grammar::new().to_gmr(), "");
+}
+
+#[test]
+fn formatting_keeps_comments_and_sorts_mapping_blocks() {
+    let text = "\n\n# Keys\ns Down\n  o,[BP]  \n\n\n# Player two\nplayer   2\nb, [BP]\n5,Down\n\
+                include mk9.gmr\n# Moves\nButt slam (Ermac):s   s o\nSlide: s o|priority=2\n\n";
+    let formatted = format_gmr(text).expect("valid grammar");
+    assert_eq!(
+        formatted,
+        "# Keys\no, [BP]\ns, Down\n\n# Player two\nplayer 2\n5, Down\nb, [BP]\n\
+         include \"mk9.gmr\"\n# Moves\nButt slam (Ermac): s s o\nSlide:             s o | priority=2\n"
+    );
+    assert_eq!(format_gmr(&formatted).expect("valid grammar"), formatted);
+
+    for path in [
+        "grammars/mk9.gmr",
+        "grammars/mk9_with_moves.gmr",
+        "grammars/mk9_two_players.gmr",
+    ] {
+        // Shipped grammars keep their own layout; formatting them is stable
+        // and keeps what they define
+        let text = fs::read_to_string(path).expect("read grammar");
+        let formatted = format_gmr(&text).expect("valid grammar");
+        assert_eq!(format_gmr(&formatted).as_ref(), Ok(&formatted), "{path}");
+        assert_eq!(
+            parse_grammar_text(&formatted, path),
+            parse_grammar_file(path),
+            "{path}"
+        );
+    }
+
+    let err = format_gmr("s, Down\nwhat is this\n").expect_err("invalid line");
+    assert!(
+        err.starts_with("2: unrecognized line"),
+        "unexpected error: {err}"
+    );
+}
+
+fn run_fmt(args: &[&str]) -> Output {
+    run_binary(&[&["fmt"], args].concat())
+}
+
+#[test]
+fn fmt_command_checks_and_rewrites_files() {
+    let temp_dir = TestTempDir::new("ardn_fmt");
+    let path = temp_dir.file("fmt.gmr", "s Down\no, [BP]\nSlam: s o\n");
+    let file = path.to_str().expect("utf-8 path");
+
+    assert_eq!(
+        run_fmt(&[file, "--check"]).status.code(),
+        Some(EXIT_UNFORMATTED as i32)
+    );
+    assert_eq!(run_fmt(&[file]).status.code(), Some(EXIT_OK as i32));
+    let formatted = fs::read_to_string(&path).expect("read grammar");
+    assert_eq!(formatted, "o, [BP]\ns, Down\nSlam: s o\n");
+    assert_eq!(
+        run_fmt(&[file, "--check"]).status.code(),
+        Some(EXIT_OK as i32)
+    );
+
+    let document = run_fmt(&["grammars/mk9_with_moves.toml"]);
+    assert_eq!(document.status.code(), Some(EXIT_USAGE as i32));
+    let both = run_fmt(&["grammars/mk9.gmr", "--check", "-o", "out.gmr"]);
+    assert_eq!(both.status.code(), Some(EXIT_USAGE as i32));
+}
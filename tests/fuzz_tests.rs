@@ -0,0 +1,162 @@
+//! Fuzzing of the `.gmr` parser: the bundled grammars are mutated at random
+//! and every result must parse or fail with an error, never panic. Whatever
+//! parses must survive `to_gmr` and `fmt`. `FT_ALITY_SEED` replays a failing
+//! case and `FT_ALITY_FUZZ_CASES` runs more of them.
+
+mod common;
+
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::parse_grammar_text;
+use automate_refuse_de_nier::tools::rng::Rng;
+use common::{cases, first_seed};
+use std::fs;
+use std::panic::{self, AssertUnwindSafe};
+
+/// Text the mutations insert: separators, quotes, escapes, keywords of the
+/// format and keys of any width
+const FRAGMENTS: [&str; 29] = [
+    ",",
+    ":",
+    "|",
+    "#",
+    " #",
+    "\"",
+    "\\",
+    "=",
+    " ",
+    "\t",
+    "\n",
+    "é",
+    "🎮",
+    "0",
+    "-1",
+    "x",
+    "LeftShift",
+    "include ",
+    "player 2\n",
+    "ignore ",
+    "optional ",
+    "buffer ",
+    "window ",
+    "20f",
+    "priority=",
+    "notes=",
+    "category=",
+    "damage=",
+    "\"a, b: c\"",
+];
+
+/// The bundled `.gmr` grammars, plus lines of each kind
+fn corpus() -> Vec<String> {
+    let mut corpus: Vec<String> = fs::read_dir("grammars")
+        .expect("grammars directory")
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "gmr"))
+        .map(|path| fs::read_to_string(path).expect("readable grammar"))
+        .collect();
+    corpus.sort();
+    corpus.push(
+        "\":\", Colon  # a separator\n\\,, Comma\nplayer 2\n5, Down\n\
+         ignore p\noptional n\nbuffer 300ms\nwindow 15f\n\
+         \"Kano: Ball\": \":\" \\, | priority=2 notes=\"Hits #1\"\n"
+            .to_string(),
+    );
+    corpus
+}
+
+/// Character boundary of `text` at or before a random byte offset
+fn boundary(rng: &mut Rng, text: &str) -> usize {
+    let mut at = rng.below(text.len() + 1);
+    while !text.is_char_boundary(at) {
+        at -= 1;
+    }
+    at
+}
+
+/// `text` with a few random edits
+fn mutate(rng: &mut Rng, text: &str, corpus: &[String]) -> String {
+    let mut text = text.to_string();
+    for _ in 0..rng.between(1, 4) {
+        match rng.below(5) {
+            0 | 1 => {
+                let at = boundary(rng, &text);
+                let fragment = *rng.pick(&FRAGMENTS);
+                text.insert_str(at, fragment);
+            }
+            2 => {
+                let start = boundary(rng, &text);
+                let len = rng.below(8);
+                let mut end = (start + len).min(text.len());
+                while !text.is_char_boundary(end) {
+                    end += 1;
+                }
+                text.replace_range(start..end, "");
+            }
+            3 => {
+                // A line of another grammar
+                let other = rng.pick(corpus);
+                let lines: Vec<&str> = other.lines().collect();
+                if !lines.is_empty() {
+                    let line = format!("{}\n", rng.pick(&lines));
+                    let at = boundary(rng, &text);
+                    text.insert_str(at, &line);
+                }
+            }
+            _ => {
+                // Swap two lines
+                let mut lines: Vec<&str> = text.lines().collect();
+                if lines.len() > 1 {
+                    let (a, b) = (rng.below(lines.len()), rng.below(lines.len()));
+                    lines.swap(a, b);
+                    text = lines.join("\n");
+                }
+            }
+        }
+    }
+    text
+}
+
+/// Parse `text` as if it sat next to the bundled grammars, so that includes
+/// resolve, and check what it gives
+fn check(text: &str) {
+    let path = "grammars/fuzz.gmr";
+    let Ok(grammar) = parse_grammar_text(text, path) else {
+        return;
+    };
+    let gmr = grammar.to_gmr();
+    assert_eq!(
+        parse_grammar_text(&gmr, path).as_ref(),
+        Ok(&grammar),
+        "to_gmr does not round-trip:\n{gmr}"
+    );
+    let formatted = format_gmr(text).expect("text that parses formats");
+    assert_eq!(
+        parse_grammar_text(&formatted, path).as_ref(),
+        Ok(&grammar),
+        "formatting changes the grammar:\n{formatted}"
+    );
+    assert_eq!(
+        format_gmr(&formatted).as_ref(),
+        Ok(&formatted),
+        "formatting twice differs"
+    );
+}
+
+#[test]
+fn mutated_grammars_never_panic_and_round_trip() {
+    let corpus = corpus();
+    for text in &corpus {
+        check(text);
+    }
+    let first = first_seed();
+    for seed in first..first + cases("FT_ALITY_FUZZ_CASES", 2000) as u64 {
+        let mut rng = Rng::new(seed);
+        let original = rng.pick(&corpus);
+        let text = mutate(&mut rng, original, &corpus);
+        if let Err(panic) = panic::catch_unwind(AssertUnwindSafe(|| check(&text))) {
+            eprintln!("fuzz case FT_ALITY_SEED={} failed on:\n{}", seed, text);
+            panic::resume_unwind(panic);
+        }
+    }
+}
@@ -0,0 +1,118 @@
+mod common;
+
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::generate::{
+    generate_grammar, generate_input, GeneratorConfig, SEPARATOR_KEY,
+};
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_text, Grammar};
+use automate_refuse_de_nier::tools::{feed_lines, parse_grammar_file, DFAConfig, Recognizer, DFA};
+use common::TestTempDir;
+
+/// Moves whose first three keys start an earlier move
+fn sharing_moves(grammar: &Grammar) -> usize {
+    (1..grammar.moves.len())
+        .filter(|&i| {
+            let first = &grammar.moves[i].sequence[..3];
+            grammar.moves[..i]
+                .iter()
+                .any(|m| m.sequence.starts_with(first))
+        })
+        .count()
+}
+
+#[test]
+fn generated_grammars_have_the_requested_shape() {
+    let config = GeneratorConfig {
+        characters: 40,
+        moves_per_character: 25,
+        keys: 20,
+        min_length: 3,
+        max_length: 6,
+        shared_prefixes: 50,
+        seed: 7,
+    };
+    let grammar = generate_grammar(&config).unwrap();
+    assert_eq!(grammar.moves.len(), 1000);
+    assert_eq!(grammar.moves_by_character().len(), 40);
+    // The keys of moves, and the key that separates them
+    assert_eq!(grammar.mappings.len(), 21);
+    for m in &grammar.moves {
+        assert!((3..=6).contains(&m.sequence.len()), "{}", m.name);
+        assert!(m
+            .sequence
+            .iter()
+            .all(|key| grammar.mappings.contains_key(key)));
+        assert!(!m.sequence.contains(&Symbol::new(SEPARATOR_KEY).unwrap()));
+    }
+
+    // Moves start like earlier ones far more often than by chance
+    let unshared = GeneratorConfig {
+        shared_prefixes: 0,
+        ..config
+    };
+    let (shared, by_chance) = (
+        sharing_moves(&grammar),
+        sharing_moves(&generate_grammar(&unshared).unwrap()),
+    );
+    assert!(shared > 3 * by_chance, "{shared} against {by_chance}");
+
+    assert_eq!(generate_grammar(&config).unwrap(), grammar);
+    let reseeded = GeneratorConfig { seed: 8, ..config };
+    assert_ne!(generate_grammar(&reseeded).unwrap(), grammar);
+
+    for invalid in [
+        GeneratorConfig { keys: 0, ..config },
+        GeneratorConfig { keys: 37, ..config },
+        GeneratorConfig {
+            min_length: 0,
+            ..config
+        },
+        GeneratorConfig {
+            min_length: 7,
+            ..config
+        },
+        GeneratorConfig {
+            moves_per_character: 0,
+            ..config
+        },
+    ] {
+        assert!(generate_grammar(&invalid).is_err(), "{invalid:?}");
+    }
+}
+
+#[test]
+fn full_rosters_round_trip_through_gmr_files() {
+    let grammar = generate_grammar(&GeneratorConfig::default()).unwrap();
+    let temp_dir = TestTempDir::new("ardn_roster");
+    let path = temp_dir.file("roster.gmr", &grammar.to_gmr());
+    assert_eq!(parse_grammar_file(&path), Ok(grammar));
+}
+
+#[test]
+fn recognizers_report_exactly_the_expected_matches() {
+    let config = GeneratorConfig {
+        characters: 60,
+        moves_per_character: 40,
+        ..GeneratorConfig::default()
+    };
+    let grammar = generate_grammar(&config).unwrap();
+    let input = generate_input(&grammar, 2000, 3).unwrap();
+    assert!(input.expected.len() >= 2000);
+
+    let dfa = DFA::from_grammar(&grammar);
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut matches = Vec::new();
+    feed_lines(&grammar, input.text.as_bytes(), |key, token| {
+        matches.extend(recognizer.feed(&dfa, key, token, &config).matches);
+    })
+    .unwrap();
+    assert_eq!(matches, input.expected);
+}
+
+#[test]
+fn inputs_need_a_key_that_breaks_every_move() {
+    let grammar = parse_grammar_text("a, Jump\nHop: a\n", "hop.gmr").unwrap();
+    let err = generate_input(&grammar, 10, 1).unwrap_err();
+    assert!(err.contains(SEPARATOR_KEY), "{err}");
+}
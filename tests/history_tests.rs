@@ -0,0 +1,155 @@
+use automate_refuse_de_nier::tools::dfa::MatchPolicy;
+use automate_refuse_de_nier::tools::history::{
+    InputHistory, InputRecord, DEFAULT_HISTORY_CAPACITY,
+};
+use automate_refuse_de_nier::tools::parsing::parse_grammar_text;
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use std::time::{Duration, Instant};
+
+fn history_of(keys: &str, capacity: usize) -> (InputHistory, Instant) {
+    let t0 = Instant::now();
+    let mut history = InputHistory::new(capacity);
+    for (i, key) in keys.chars().enumerate() {
+        let at = t0 + Duration::from_millis(100 * i as u64);
+        history.push(InputRecord::new(
+            1,
+            key,
+            &key.to_uppercase().to_string(),
+            at,
+        ));
+    }
+    (history, t0)
+}
+
+fn keys<'a>(records: impl Iterator<Item = &'a InputRecord>) -> String {
+    records.map(|r| r.key.as_str()).collect()
+}
+
+#[test]
+fn the_oldest_inputs_drop_out_once_full() {
+    let (history, _) = history_of("abcdef", 4);
+    assert_eq!(history.capacity(), 4);
+    assert_eq!(history.len(), 4);
+    assert_eq!(keys(history.iter()), "cdef");
+    assert_eq!(keys(history.iter().rev()), "fedc");
+    assert_eq!(
+        history.latest().map(|r| (r.index, r.key.as_str())),
+        Some((5, "f"))
+    );
+    assert_eq!(history.next_index(), 6);
+
+    // Indices stay put as inputs drop out
+    assert_eq!(history.get(1), None);
+    assert_eq!(history.get(2).map(|r| r.key.as_str()), Some("c"));
+    assert_eq!(history.get(6), None);
+
+    assert_eq!(InputHistory::default().capacity(), DEFAULT_HISTORY_CAPACITY);
+    assert_eq!(InputHistory::new(0).capacity(), 1);
+}
+
+#[test]
+fn inputs_can_be_sliced_by_index_count_and_time() {
+    let (mut history, t0) = history_of("abcdef", 4);
+    assert_eq!(keys(history.range(3..5)), "de");
+    assert_eq!(keys(history.range(..=3)), "cd");
+    assert_eq!(keys(history.range(4..)), "ef");
+    assert_eq!(keys(history.range(0..20)), "cdef");
+    let (start, end) = (5, 3);
+    assert_eq!(keys(history.range(start..end)), "");
+    assert_eq!(history.tokens(0..4), vec!["C", "D"]);
+
+    assert_eq!(keys(history.recent(2)), "ef");
+    assert_eq!(keys(history.recent(10)), "cdef");
+    assert_eq!(keys(history.since(t0 + Duration::from_millis(350))), "ef");
+
+    assert_eq!(history.gap(3), Some(Duration::from_millis(100)));
+    assert_eq!(history.gap(2), None, "input 1 dropped out");
+    assert_eq!(history.frame_gap(3), None, "inputs without frames");
+
+    history.clear();
+    assert!(history.is_empty());
+    assert_eq!(keys(history.range(..)), "");
+    let next = history.push(InputRecord::new(1, 'g', "G", t0));
+    assert_eq!(next, 6);
+}
+
+#[test]
+fn recognizers_record_every_input_and_the_span_of_each_match() {
+    let dfa = DFA::from_moves(vec![
+        (vec!['o'], "Claw Slam".to_string()),
+        (vec!['s', 's', 'o'], "Butt slam".to_string()),
+    ]);
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa).with_history_capacity(8);
+
+    recognizer.feed(&dfa, 'x', "?", &config);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.input, 3);
+    assert_eq!(event.matches, vec!["Butt slam".to_string()]);
+    assert_eq!(event.spans, vec![1..4]);
+
+    let history = recognizer.history();
+    assert_eq!(history.capacity(), 8);
+    assert_eq!(
+        history.tokens(event.spans[0].clone()),
+        ["Down", "Down", "[BP]"]
+    );
+    assert!(history.get(0).unwrap().reset, "x starts nothing");
+
+    // A broken sequence restarts the span from the breaking input
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert!(event.reset);
+    assert_eq!(event.spans, vec![5..6]);
+    let miss = event.near_miss.expect("Butt slam was underway");
+    assert_eq!(miss.inputs, 4..5);
+    assert_eq!(miss.entered(recognizer.history()), ["Down"]);
+}
+
+#[test]
+fn replacing_a_recognizer_can_carry_its_history_on() {
+    let dfa = DFA::from_moves(vec![(vec!['s', 'o'], "Uppercut".to_string())]);
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::for_player(&dfa, 2).with_history_capacity(4);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    recognizer.feed(&dfa, 'o', "[BP]", &config);
+
+    let reloaded = DFA::from_moves(vec![(vec!['o', 's'], "Low kick".to_string())]);
+    let mut replacement =
+        Recognizer::for_player(&reloaded, 2).with_history(recognizer.history().clone());
+    let event = replacement.feed(&reloaded, 'x', "?", &config);
+    assert_eq!(event.input, 2);
+    let history = replacement.history();
+    assert_eq!(history.capacity(), 4);
+    assert_eq!(history.tokens(0..3), ["Down", "[BP]", "?"]);
+}
+
+#[test]
+fn held_back_and_lenient_matches_keep_their_span() {
+    let grammar = parse_grammar_text(
+        "o, [BP]\nl, [FP]\np, Block\nignore p\nClaw Slam: o\nSaibot Blast: o l\n",
+        "g.gmr",
+    )
+    .unwrap();
+    let dfa = DFA::from_grammar(&grammar);
+    let config = DFAConfig {
+        policy: MatchPolicy::Longest,
+        ..DFAConfig::default()
+    };
+    let mut recognizer = Recognizer::new(&dfa);
+
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert!(event.matches.is_empty(), "Claw Slam is held back");
+    // Breaking the sequence releases the held move with its own span
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.matches, vec!["Claw Slam".to_string()]);
+    assert_eq!(event.spans, vec![0..1]);
+
+    recognizer.feed(&dfa, 'p', "Block", &config);
+    let event = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    assert_eq!(event.matches, vec!["Saibot Blast".to_string()]);
+    assert_eq!(event.spans, vec![1..4]);
+    assert_eq!(recognizer.history().tokens(1..4), ["[BP]", "Block", "[FP]"]);
+}
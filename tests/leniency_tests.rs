@@ -0,0 +1,188 @@
+use automate_refuse_de_nier::tools::clock::TimingWindow;
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::document::{parse_grammar_document, GrammarFormat};
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, parse_grammar_text, Grammar};
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use std::time::{Duration, Instant};
+
+/// Quarter circle forward punch, with its diagonal, and a block key
+const QCF: &str = "\
+d, Forward
+l, [FP]
+n, Down-Forward
+p, Block
+s, Down
+
+ignore p
+optional n
+buffer 20f
+
+Fireball: s n d l
+";
+
+fn qcf() -> (Grammar, DFA) {
+    let grammar = parse_grammar_text(QCF, "qcf.gmr").expect("valid grammar");
+    let dfa = DFA::from_grammar(&grammar);
+    (grammar, dfa)
+}
+
+fn feed_all(dfa: &DFA, config: &DFAConfig, keys: &str) -> Vec<String> {
+    let mut recognizer = Recognizer::new(dfa);
+    keys.chars()
+        .flat_map(|key| recognizer.feed(dfa, key, "", config).matches)
+        .collect()
+}
+
+#[test]
+fn leniency_rules_are_parsed_and_formatted() {
+    let (grammar, _) = qcf();
+    let leniency = &grammar.leniency;
+    assert_eq!(
+        leniency
+            .ignore
+            .iter()
+            .map(Symbol::as_str)
+            .collect::<String>(),
+        "p"
+    );
+    assert_eq!(
+        leniency
+            .optional
+            .iter()
+            .map(Symbol::as_str)
+            .collect::<String>(),
+        "n"
+    );
+    assert_eq!(leniency.buffer, Some(TimingWindow::Frames(20)));
+    assert_eq!(format_gmr(QCF).unwrap(), QCF);
+    assert_eq!(
+        parse_grammar_text(&grammar.to_gmr(), "qcf.gmr").unwrap(),
+        grammar
+    );
+
+    let toml = r#"
+        mappings = { s = "Down", n = "Down-Forward", d = "Forward", p = "Block", l = "[FP]" }
+        leniency = { ignore = ["p"], optional = ["n"], buffer = "20f" }
+        moves = [{ name = "Fireball", sequence = ["s", "n", "d", "l"] }]
+    "#;
+    let document = parse_grammar_document(toml, GrammarFormat::Toml).expect("valid toml");
+    assert_eq!(document, grammar);
+
+    let lenient = parse_grammar_file("grammars/mk9_lenient.gmr").expect("bundled grammar");
+    assert!(lenient.leniency.ignore.contains("p"));
+    assert_eq!(lenient.leniency.buffer, Some(TimingWindow::Frames(30)));
+}
+
+#[test]
+fn bad_leniency_rules_are_reported_with_their_line() {
+    for (text, expected) in [
+        ("ignore", "unrecognized line"),
+        ("ignore p\"", "1: unterminated quote in 'ignore p\"'"),
+        ("optional n \"d n\"", "optional: invalid key 'd n'"),
+        (
+            "buffer soon",
+            "1: buffer must be milliseconds or frames like 20f",
+        ),
+        ("buffer 1.5f", "buffer must be milliseconds or frames"),
+    ] {
+        let err = parse_grammar_text(text, "bad.gmr").expect_err(text);
+        assert!(err.contains(expected), "{text}: {err}");
+    }
+    let err = parse_grammar_document(
+        r#"{ "mappings": {}, "leniency": { "ignore": [""] } }"#,
+        GrammarFormat::Json,
+    )
+    .unwrap_err();
+    assert!(err.contains("ignore: key must not be empty"), "{err}");
+}
+
+#[test]
+fn ignored_keys_do_not_break_a_move() {
+    let (_, dfa) = qcf();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let block = recognizer.feed(&dfa, 'p', "Block", &config);
+    assert!(block.ignored);
+    assert!(!block.reset);
+    assert_eq!(block.to, block.from);
+    assert!(block.near_miss.is_none());
+    for key in ['p', 'n', 'd', 'p'] {
+        recognizer.feed(&dfa, key, "", &config);
+    }
+    let event = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    assert_eq!(event.matches, vec!["Fireball".to_string()]);
+
+    // Keys that are not ignored still break the sequence
+    assert!(feed_all(&dfa, &config, "snldl").is_empty());
+}
+
+#[test]
+fn optional_keys_may_be_skipped() {
+    let (_, dfa) = qcf();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let forward = recognizer.feed(&dfa, 'd', "Forward", &config);
+    assert_eq!(forward.skipped.as_ref().map(Symbol::as_str), Some("n"));
+    assert!(!forward.reset);
+    let event = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    assert_eq!(event.matches, vec!["Fireball".to_string()]);
+
+    assert_eq!(
+        feed_all(&dfa, &config, "sndl"),
+        vec!["Fireball".to_string()]
+    );
+    // Only keys between inputs may be skipped, not required ones
+    assert!(feed_all(&dfa, &config, "snl").is_empty());
+    assert!(feed_all(&dfa, &config, "ndl").is_empty());
+}
+
+#[test]
+fn moves_complete_within_the_buffer_window() {
+    let (_, dfa) = qcf();
+    let config = DFAConfig {
+        buffer_window: Some(Duration::from_millis(300)),
+        ..DFAConfig::default()
+    };
+    let t0 = Instant::now();
+    let at = |ms| t0 + Duration::from_millis(ms);
+
+    let mut recognizer = Recognizer::new(&dfa);
+    recognizer.feed_at(&dfa, 's', "Down", &config, at(0));
+    recognizer.feed_at(&dfa, 'n', "Down-Forward", &config, at(100));
+    recognizer.feed_at(&dfa, 'd', "Forward", &config, at(200));
+    let event = recognizer.feed_at(&dfa, 'l', "[FP]", &config, at(300));
+    assert_eq!(event.matches, vec!["Fireball".to_string()]);
+
+    // Too slow: the last input starts over instead of completing the move
+    recognizer.feed_at(&dfa, 's', "Down", &config, at(1000));
+    recognizer.feed_at(&dfa, 'd', "Forward", &config, at(1200));
+    let late = recognizer.feed_at(&dfa, 'l', "[FP]", &config, at(1301));
+    assert!(late.matches.is_empty());
+    assert!(late.reset);
+    assert_eq!(recognizer.state(), dfa.start_state());
+
+    // The window counts from the first input of the sequence underway
+    recognizer.feed_at(&dfa, 's', "Down", &config, at(1400));
+    recognizer.feed_at(&dfa, 'd', "Forward", &config, at(1600));
+    let event = recognizer.feed_at(&dfa, 'l', "[FP]", &config, at(1700));
+    assert_eq!(event.matches, vec!["Fireball".to_string()]);
+}
+
+#[test]
+fn grammars_without_rules_stay_strict() {
+    let (mut grammar, _) = qcf();
+    grammar.leniency = Default::default();
+    let dfa = DFA::from_grammar(&grammar);
+    let config = DFAConfig::default();
+    assert!(feed_all(&dfa, &config, "spndl").is_empty());
+    assert!(feed_all(&dfa, &config, "sdl").is_empty());
+    assert_eq!(
+        feed_all(&dfa, &config, "sndl"),
+        vec!["Fireball".to_string()]
+    );
+}
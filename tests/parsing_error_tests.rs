@@ -1,37 +1,11 @@
-use automate_refuse_de_nier::tools::parsing::parse_grammar_file;
-use std::env;
+mod common;
+
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, parse_grammar_text};
+use common::TestTempDir;
 use std::fs::{self, File};
 use std::io::Write;
-use std::path::{Path, PathBuf};
-use std::time::{SystemTime, UNIX_EPOCH};
-
-struct TestTempDir {
-    path: PathBuf,
-}
-
-impl TestTempDir {
-    fn new(prefix: &str) -> Self {
-        let unique = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_nanos();
-        let dir = env::temp_dir().join(format!("{}_{}", prefix, unique));
-        fs::create_dir(&dir).expect("create temp dir");
-        Self { path: dir }
-    }
-
-    fn path(&self) -> &Path {
-        &self.path
-    }
-}
-
-impl Drop for TestTempDir {
-    fn drop(&mut self) {
-        if self.path.exists() {
-            let _ = fs::remove_dir_all(&self.path);
-        }
-    }
-}
 
 #[test]
 fn parse_invalid_line_reports_format_error() {
@@ -94,3 +68,250 @@ fn parse_unreadable_file_surfaces_permission_error() {
     // Restore permissions so the temp file can be cleaned up without panicking.
     let _ = fs::set_permissions(&grammar_path, Permissions::from_mode(0o600));
 }
+
+#[test]
+fn move_attributes_set_priority_and_reject_unknown_names() {
+    let temp_dir = TestTempDir::new("ardn_attributes");
+    let grammar_path = temp_dir.path().join("attributes.gmr");
+    fs::write(
+        &grammar_path,
+        "o, [BP]\nl, [FP]\nClaw Slam: o\nSaibot Blast: o l | priority=2\n",
+    )
+    .expect("write grammar");
+    let grammar = parse_grammar_file(&grammar_path).expect("valid grammar");
+    assert_eq!(grammar.moves[0].priority, 0);
+    assert_eq!(grammar.moves[1].sequence, vec!['o', 'l']);
+    assert_eq!(grammar.moves[1].priority, 2);
+
+    for (line, expected) in [
+        (
+            "Saibot Blast: o l | priority=high",
+            "priority must be a whole number",
+        ),
+        ("Saibot Blast: o l | damage", "must be name=value"),
+        (
+            "Saibot Blast: o l | speed=3",
+            "unknown move attribute 'speed'",
+        ),
+    ] {
+        fs::write(&grammar_path, format!("o, [BP]\n{}\n", line)).expect("write grammar");
+        let err = parse_grammar_file(&grammar_path).expect_err("invalid attribute");
+        assert!(
+            err.starts_with("2: ") && err.contains(expected),
+            "unexpected error message: {err}"
+        );
+    }
+}
+
+#[test]
+fn player_sections_bind_keys_to_player_one_tokens() {
+    let grammar = parse_grammar_file("grammars/mk9_two_players.gmr").expect("valid grammar");
+    assert_eq!(grammar.player_count(), 2);
+    assert_eq!(grammar.resolve_key("s"), Some((1, &Symbol::from('s'))));
+    assert_eq!(grammar.resolve_key("5"), Some((2, &Symbol::from('s'))));
+    assert_eq!(grammar.get_token_for_key("b"), Some("[BP]"));
+    assert_eq!(grammar.resolve_key("x"), None);
+
+    let temp_dir = TestTempDir::new("ardn_players");
+    let grammar_path = temp_dir.path().join("players.gmr");
+    for (contents, expected) in [
+        (
+            "s, Down\nplayer 2\ns, Down\n",
+            "already bound to another player",
+        ),
+        ("s, Down\nplayer 2\nk, Jump\n", "player 1 does not define"),
+        ("s, Down\nplayer 9\n", "player must be a number from 1 to 4"),
+    ] {
+        fs::write(&grammar_path, contents).expect("write grammar");
+        let err = parse_grammar_file(&grammar_path).expect_err("invalid player section");
+        assert!(err.contains(expected), "unexpected error message: {err}");
+    }
+}
+
+#[test]
+fn includes_share_key_maps_and_report_the_include_chain() {
+    let shared = parse_grammar_file("grammars/mk9_with_moves.gmr").expect("valid grammar");
+    let keys = parse_grammar_file("grammars/mk9.gmr").expect("valid grammar");
+    assert_eq!(shared.mappings, keys.mappings);
+    assert_eq!(shared.moves.len(), 7);
+
+    let temp_dir = TestTempDir::new("ardn_include");
+    let dir = temp_dir.path();
+    fs::create_dir(dir.join("keys")).expect("create dir");
+    fs::write(dir.join("keys/base.gmr"), "s, Down\no, [BP]\n").expect("write grammar");
+    fs::write(
+        dir.join("ermac.gmr"),
+        "include \"keys/base.gmr\"\nButt slam (Ermac): s s o\n",
+    )
+    .expect("write grammar");
+    let grammar = parse_grammar_file(dir.join("ermac.gmr")).expect("relative include");
+    assert_eq!(grammar.get_token_for_key("o"), Some("[BP]"));
+    assert_eq!(grammar.moves[0].sequence, vec!['s', 's', 'o']);
+
+    // Errors name the line of every file on the way down
+    fs::write(dir.join("keys/bad.gmr"), "s, Down\n?? what\n").expect("write grammar");
+    fs::write(dir.join("middle.gmr"), "# keys\ninclude \"keys/bad.gmr\"\n").expect("write grammar");
+    fs::write(dir.join("top.gmr"), "include middle.gmr\n").expect("write grammar");
+    let err = parse_grammar_file(dir.join("top.gmr")).expect_err("bad include");
+    assert!(
+        err.starts_with("1: in '"),
+        "unexpected error message: {err}"
+    );
+    assert!(
+        err.contains("middle.gmr': 2: in '") && err.contains("bad.gmr': 2: unrecognized line"),
+        "unexpected error message: {err}"
+    );
+
+    fs::write(dir.join("a.gmr"), "include \"b.gmr\"\n").expect("write grammar");
+    fs::write(dir.join("b.gmr"), "s, Down\ninclude \"a.gmr\"\n").expect("write grammar");
+    let err = parse_grammar_file(dir.join("a.gmr")).expect_err("include cycle");
+    assert!(
+        err.contains("2: include cycle: "),
+        "unexpected error message: {err}"
+    );
+    let chain: Vec<&str> = err.rsplit(": ").next().unwrap().split(" -> ").collect();
+    assert_eq!(chain.len(), 3, "unexpected error message: {err}");
+    assert!(
+        chain[0].ends_with("a.gmr") && chain[1].ends_with("b.gmr") && chain[2].ends_with("a.gmr")
+    );
+
+    fs::write(dir.join("missing.gmr"), "include \"nowhere.gmr\"\n").expect("write grammar");
+    let err = parse_grammar_file(dir.join("missing.gmr")).expect_err("missing include");
+    assert!(
+        err.starts_with("1: Failed to read grammar file"),
+        "unexpected error message: {err}"
+    );
+}
+
+#[test]
+fn move_metadata_attributes_are_parsed_and_checked() {
+    let temp_dir = TestTempDir::new("ardn_metadata");
+    let grammar_path = temp_dir.path().join("metadata.gmr");
+    fs::write(
+        &grammar_path,
+        "s, Down\no, [BP]\n\
+         Butt slam (Ermac): s s o | damage=12 startup=14 active=3 recovery=30 meter=1 \
+         category=X-Ray notes=Hits overhead: punish on block, always\n\
+         Poke: o | notes=\n",
+    )
+    .expect("write grammar");
+    let grammar = parse_grammar_file(&grammar_path).expect("valid grammar");
+    let metadata = &grammar.moves[0].metadata;
+    assert_eq!(metadata.damage, Some(12));
+    assert_eq!(metadata.frames().as_deref(), Some("14/3/30"));
+    assert_eq!(metadata.meter, Some(1));
+    assert_eq!(
+        metadata.notes.as_deref(),
+        Some("Hits overhead: punish on block, always")
+    );
+    assert_eq!(
+        metadata.summary(),
+        "x-ray, 12% damage, 14/3/30 frames, 1 bar"
+    );
+    assert_eq!(metadata.tag(), "12% 14/3/30F");
+    assert!(grammar.moves[1].metadata.is_empty());
+
+    for (line, expected) in [
+        (
+            "Slam: s | damage=lots",
+            "damage must be a whole number: 'lots'",
+        ),
+        ("Slam: s | startup=-3", "startup must be a whole number"),
+        (
+            "Slam: s | category=brutality",
+            "unknown category 'brutality'",
+        ),
+        ("Slam: s | mynotes=x", "unknown move attribute 'mynotes'"),
+    ] {
+        fs::write(&grammar_path, format!("s, Down\n{line}\n")).expect("write grammar");
+        let err = parse_grammar_file(&grammar_path).expect_err("invalid metadata");
+        assert!(err.starts_with("2: "), "unexpected error message: {err}");
+        assert!(err.contains(expected), "unexpected error message: {err}");
+    }
+}
+
+/// Keys and names holding separators, quoted or escaped, with comments
+const QUOTED: &str = r##"# keys that are separators
+":", Colon
+\,, Comma
+"#", Hash
+C#, Sharp  # not a comment start
+x, "Up, Forward"  # diagonal
+"\"", Quote
+
+"Kano: Ball":      ":" x  # rolls
+"Hit, run \"fast\"": \, "#" | notes="Hits #1: overhead"
+"##;
+
+#[test]
+fn quoted_keys_and_names_may_hold_separators() {
+    let grammar = parse_grammar_text(QUOTED, "quoted.gmr").expect("valid grammar");
+    for (key, token) in [
+        (":", "Colon"),
+        (",", "Comma"),
+        ("#", "Hash"),
+        ("C#", "Sharp"),
+        ("x", "Up, Forward"),
+        ("\"", "Quote"),
+    ] {
+        assert_eq!(grammar.get_token_for_key(key), Some(token), "{key}");
+    }
+    assert_eq!(grammar.mappings.len(), 6);
+
+    let (ball, hit) = (&grammar.moves[0], &grammar.moves[1]);
+    assert_eq!(ball.name, "Kano: Ball");
+    assert_eq!(ball.sequence, [":", "x"]);
+    assert_eq!(hit.name, "Hit, run \"fast\"");
+    assert_eq!(hit.sequence, [",", "#"]);
+    assert_eq!(hit.metadata.notes.as_deref(), Some("Hits #1: overhead"));
+
+    // Names are quoted back where needed and comments stay on their line
+    assert_eq!(
+        parse_grammar_text(&grammar.to_gmr(), "quoted.gmr").unwrap(),
+        grammar
+    );
+    let formatted = format_gmr(QUOTED).expect("formats");
+    assert_eq!(format_gmr(&formatted).unwrap(), formatted);
+    assert!(
+        formatted.contains("x,    \"Up, Forward\"  # diagonal\n"),
+        "{formatted}"
+    );
+    assert!(formatted.contains("\",\",  Comma\n"), "{formatted}");
+    assert_eq!(
+        parse_grammar_text(&formatted, "quoted.gmr").unwrap(),
+        grammar
+    );
+}
+
+#[test]
+fn bad_quoting_is_reported_with_its_line() {
+    for (text, expected) in [
+        (
+            "\"Kano: Ball: x",
+            "1: unterminated quote in '\"Kano: Ball: x'",
+        ),
+        ("s, Down\nx, Slash\\", "2: nothing to escape at the end of"),
+        ("\"\", Empty", "1: key must not be empty"),
+        ("\"a b\", Space", "1: invalid key 'a b'"),
+        ("Slam: s \"s o", "1: unterminated quote"),
+        ("Slam: \"\" s", "1: move 'Slam': key must not be empty"),
+        (
+            ":, Colon",
+            "1: move name must not be empty in ':, Colon'; write a ':' key in quotes or \
+             escaped, as in \":\", Colon or \\:, Colon",
+        ),
+        ("s, Down\n: s", "2: move name must not be empty"),
+        ("\"\": s", "1: move name must not be empty"),
+        (
+            "Kano: Ball: a",
+            "1: move 'Kano': unquoted ':' among its keys; write names and keys holding \
+             ':' or ',' in quotes, as in \"Kano: Ball\": s d o",
+        ),
+        ("Slam: s, o", "1: move 'Slam': unquoted ',' among its keys"),
+    ] {
+        let err = parse_grammar_text(text, "bad.gmr").expect_err(text);
+        assert!(err.contains(expected), "{text}: {err}");
+    }
+    // Separators are fine in quoted keys and after the keys
+    parse_grammar_text("Slam: \",\" \\: o | notes=a: b, c", "ok.gmr").expect("valid move");
+}
@@ -0,0 +1,191 @@
+//! Properties of automata built from random move sets. Each case prints its
+//! seed on failure; `FT_ALITY_SEED` replays it and `FT_ALITY_PROPERTY_CASES`
+//! runs more cases.
+
+mod common;
+
+use automate_refuse_de_nier::tools::dfa::{MatchPolicy, Symbol};
+use automate_refuse_de_nier::tools::rng::Rng;
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use common::{cases, first_seed};
+
+/// Keys moves are made of: few, so that sequences share prefixes, with
+/// named and accented keys among them
+const KEYS: [&str; 7] = ["s", "o", "l", "é", "df", "LeftShift", ":"];
+
+fn random_key(rng: &mut Rng, keys: &[&str]) -> Symbol {
+    Symbol::new(keys[rng.below(keys.len())]).unwrap()
+}
+
+/// Random moves with distinct names; sequences may repeat or prefix each
+/// other
+fn random_moves(rng: &mut Rng) -> Vec<(Vec<Symbol>, String)> {
+    let key_count = rng.between(1, KEYS.len());
+    let keys = &KEYS[..key_count];
+    let mut moves: Vec<(Vec<Symbol>, String)> = Vec::new();
+    for i in 0..rng.between(1, 24) {
+        let sequence = match moves.len() {
+            // Extend or copy an earlier move now and then
+            n if n > 0 && rng.chance(30) => {
+                let mut sequence = rng.pick(&moves).0.clone();
+                if rng.chance(80) {
+                    sequence.push(random_key(rng, keys));
+                }
+                sequence
+            }
+            _ => (0..rng.between(1, 6))
+                .map(|_| random_key(rng, keys))
+                .collect(),
+        };
+        moves.push((sequence, format!("Move {}", i)));
+    }
+    moves
+}
+
+fn random_keys(rng: &mut Rng, count: usize) -> Vec<Symbol> {
+    // Some keys that no move uses break sequences
+    (0..count)
+        .map(|_| {
+            if rng.chance(10) {
+                Symbol::from('x')
+            } else {
+                random_key(rng, &KEYS)
+            }
+        })
+        .collect()
+}
+
+/// Run `property` on `count` seeds, naming the seed of the first failure
+fn for_each_seed(count: usize, property: impl Fn(&mut Rng)) {
+    let first = first_seed();
+    for seed in first..first + count as u64 {
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            property(&mut Rng::new(seed))
+        }));
+        if let Err(panic) = result {
+            eprintln!("property failed with FT_ALITY_SEED={}", seed);
+            std::panic::resume_unwind(panic);
+        }
+    }
+}
+
+#[test]
+fn every_move_is_recognized_from_the_start_state() {
+    for_each_seed(cases("FT_ALITY_PROPERTY_CASES", 300), |rng| {
+        let moves = random_moves(rng);
+        let dfa = DFA::from_moves(moves.clone());
+        let config = DFAConfig::default();
+        for (sequence, name) in &moves {
+            let mut state = dfa.start_state().clone();
+            for key in sequence {
+                let (next, _) = dfa.step(&state, key, "", &config);
+                state = next.expect("every prefix of a move has a state").clone();
+            }
+            let matches = dfa.get_matches(&state).expect("the sequence ends a move");
+            assert!(matches.contains(name), "{name} not matched by {sequence:?}");
+            assert_eq!(dfa.path_to(&state).as_ref(), Some(sequence));
+            assert_eq!(dfa.depth(&state), Some(sequence.len()));
+        }
+    });
+}
+
+#[test]
+fn states_accept_exactly_the_moves_spelled_by_their_path() {
+    for_each_seed(cases("FT_ALITY_PROPERTY_CASES", 300), |rng| {
+        let moves = random_moves(rng);
+        let dfa = DFA::from_moves(moves.clone());
+        for state in dfa.states() {
+            let path = dfa.path_to(state).expect("every state is reachable");
+            let mut expected: Vec<&str> = moves
+                .iter()
+                .filter(|(sequence, _)| *sequence == path)
+                .map(|(_, name)| name.as_str())
+                .collect();
+            let mut matches: Vec<&str> = dfa
+                .get_matches(state)
+                .map(|names| names.iter().map(String::as_str).collect())
+                .unwrap_or_default();
+            expected.sort_unstable();
+            matches.sort_unstable();
+            assert_eq!(matches, expected, "state {state} spelled by {path:?}");
+        }
+        // A trie: one state per distinct prefix, plus the start state
+        let mut prefixes: Vec<&[Symbol]> = moves
+            .iter()
+            .flat_map(|(sequence, _)| (1..=sequence.len()).map(|n| &sequence[..n]))
+            .collect();
+        prefixes.sort();
+        prefixes.dedup();
+        assert_eq!(dfa.states().len(), prefixes.len() + 1);
+    });
+}
+
+/// What the recognizer should report, worked out without the automaton: the
+/// inputs since the sequence last broke, and the moves they spell
+struct Model<'a> {
+    moves: &'a [(Vec<Symbol>, String)],
+    current: Vec<Symbol>,
+}
+
+impl Model<'_> {
+    fn continues(&self, keys: &[Symbol]) -> bool {
+        self.moves
+            .iter()
+            .any(|(sequence, _)| sequence.starts_with(keys))
+    }
+
+    fn feed(&mut self, key: &Symbol) -> Vec<String> {
+        self.current.push(key.clone());
+        if !self.continues(&self.current) {
+            // The recognizer retries a breaking key from the start
+            self.current = vec![key.clone()];
+            if !self.continues(&self.current) {
+                self.current.clear();
+            }
+        }
+        let mut names: Vec<String> = self
+            .moves
+            .iter()
+            .filter(|(sequence, _)| *sequence == self.current)
+            .map(|(_, name)| name.clone())
+            .collect();
+        names.sort_unstable();
+        names
+    }
+}
+
+#[test]
+fn recognizers_report_every_completed_move_and_nothing_else() {
+    for_each_seed(cases("FT_ALITY_PROPERTY_CASES", 300), |rng| {
+        let moves = random_moves(rng);
+        let dfa = DFA::from_moves(moves.clone());
+        let config = DFAConfig {
+            policy: MatchPolicy::All,
+            ..DFAConfig::default()
+        };
+        let mut recognizer = Recognizer::new(&dfa).with_history_capacity(64);
+        let mut model = Model {
+            moves: &moves,
+            current: Vec::new(),
+        };
+        for key in random_keys(rng, 200) {
+            let event = recognizer.feed(&dfa, key.clone(), "", &config);
+            let mut matches = event.matches.clone();
+            matches.sort_unstable();
+            assert_eq!(matches, model.feed(&key), "after input {}", event.input);
+
+            // Each match spans exactly the inputs of its sequence
+            for (name, span) in event.matches.iter().zip(&event.spans) {
+                let entered: Vec<Symbol> = recognizer
+                    .history()
+                    .range(span.clone())
+                    .map(|record| record.key.clone())
+                    .collect();
+                assert!(
+                    moves.iter().any(|m| &m.1 == name && m.0 == entered),
+                    "{name} reported for {entered:?}"
+                );
+            }
+        }
+    });
+}
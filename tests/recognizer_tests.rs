@@ -0,0 +1,380 @@
+use automate_refuse_de_nier::tools::dfa::{MatchPolicy, Symbol};
+use automate_refuse_de_nier::tools::generate::{generate_grammar, GeneratorConfig};
+use automate_refuse_de_nier::tools::parsing::{Grammar, MoveCategory, MoveDef, MoveMetadata};
+use automate_refuse_de_nier::tools::ui::UiModel;
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use std::time::{Duration, Instant};
+
+fn keys(text: &str) -> Vec<Symbol> {
+    text.chars().map(Symbol::from).collect()
+}
+
+fn mk9_dfa() -> DFA {
+    DFA::from_moves(vec![
+        (vec!['o'], "Claw Slam".to_string()),
+        (vec!['o', 'l'], "Saibot Blast".to_string()),
+        (vec!['s', 's', 'o'], "Butt slam".to_string()),
+    ])
+}
+
+#[test]
+fn feed_follows_transitions_and_reports_matches() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    let first = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(first.from, *dfa.start_state());
+    assert_eq!(first.matches, vec!["Claw Slam".to_string()]);
+    assert!(!first.reset);
+
+    let second = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    assert_eq!(second.from, first.to);
+    assert_eq!(second.matches, vec!["Saibot Blast".to_string()]);
+    assert_eq!(recognizer.state(), &second.to);
+}
+
+#[test]
+fn feed_resets_and_retries_from_start_on_broken_sequence() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let event = recognizer.feed(&dfa, 's', "Down", &config);
+    assert!(!event.reset, "s s is a valid prefix of Butt slam");
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.matches, vec!["Butt slam".to_string()]);
+
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert!(event.reset);
+    assert_eq!(event.matches, vec!["Claw Slam".to_string()]);
+
+    let event = recognizer.feed(&dfa, 'x', "Unknown", &config);
+    assert!(event.reset);
+    assert!(event.matches.is_empty());
+    assert_eq!(recognizer.state(), dfa.start_state());
+}
+
+#[test]
+fn broken_sequences_report_near_misses() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    recognizer.feed(&dfa, 's', "Down", &config);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    let event = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    assert!(event.reset);
+    let miss = event.near_miss.expect("Butt slam was underway");
+    assert_eq!(miss.state, event.from);
+    assert_eq!(miss.expected, vec!['o']);
+    assert_eq!(miss.got, 'l');
+    assert_eq!(miss.following, vec!["Butt slam".to_string()]);
+
+    let grammar = Grammar {
+        mappings: [
+            ('o'.into(), "[BP]".to_string()),
+            ('l'.into(), "[FP]".to_string()),
+        ]
+        .into(),
+        ..Grammar::default()
+    };
+    assert_eq!(
+        miss.describe(&grammar),
+        "Butt slam: expected [BP], got [FP]"
+    );
+
+    // `o` completed Claw Slam, so nothing was missed when Saibot Blast breaks
+    recognizer.feed(&dfa, 'o', "[BP]", &config);
+    let event = recognizer.feed(&dfa, 's', "Down", &config);
+    assert!(event.reset);
+    assert_eq!(event.near_miss, None);
+
+    // A key that starts nothing from the start state is no near miss either
+    recognizer.reset(&dfa);
+    assert_eq!(recognizer.feed(&dfa, 'x', "?", &config).near_miss, None);
+}
+
+#[test]
+fn longest_match_holds_prefix_moves_back() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig {
+        policy: MatchPolicy::Longest,
+        continuation_window: Duration::from_millis(200),
+        ..DFAConfig::default()
+    };
+    let mut recognizer = Recognizer::new(&dfa);
+    let t0 = Instant::now();
+    let at = |ms| t0 + Duration::from_millis(ms);
+
+    // Saibot Blast continues Claw Slam, which is dropped
+    assert!(recognizer
+        .feed_at(&dfa, 'o', "[BP]", &config, t0)
+        .matches
+        .is_empty());
+    assert_eq!(recognizer.held(), ["Claw Slam".to_string()]);
+    let event = recognizer.feed_at(&dfa, 'l', "[FP]", &config, at(100));
+    assert_eq!(event.matches, vec!["Saibot Blast".to_string()]);
+    assert!(recognizer.held().is_empty());
+
+    // A broken continuation lets Claw Slam stand
+    recognizer.feed_at(&dfa, 'o', "[BP]", &config, at(1000));
+    let event = recognizer.feed_at(&dfa, 's', "Down", &config, at(1100));
+    assert!(event.reset);
+    assert_eq!(event.matches, vec!["Claw Slam".to_string()]);
+
+    // So does waiting out the window, after which `l` starts afresh
+    recognizer.reset(&dfa);
+    recognizer.feed_at(&dfa, 'o', "[BP]", &config, at(2000));
+    assert!(recognizer.poll(&dfa, &config, at(2150)).is_empty());
+    assert_eq!(
+        recognizer.poll(&dfa, &config, at(2250)),
+        vec!["Claw Slam".to_string()]
+    );
+    assert_eq!(recognizer.state(), dfa.start_state());
+    assert!(recognizer
+        .feed_at(&dfa, 'l', "[FP]", &config, at(2300))
+        .matches
+        .is_empty());
+
+    // The end of the input reports whatever is still held back
+    recognizer.feed_at(&dfa, 'o', "[BP]", &config, at(3000));
+    assert_eq!(recognizer.flush(&dfa), vec!["Claw Slam".to_string()]);
+}
+
+#[test]
+fn priority_policy_prefers_declared_priorities() {
+    let grammar = Grammar {
+        moves: vec![
+            MoveDef {
+                name: "Claw Slam".to_string(),
+                sequence: keys("o"),
+                priority: 1,
+                ..MoveDef::default()
+            },
+            MoveDef {
+                name: "Knockdown".to_string(),
+                sequence: keys("o"),
+                ..MoveDef::default()
+            },
+            MoveDef {
+                name: "Saibot Blast".to_string(),
+                sequence: keys("ol"),
+                ..MoveDef::default()
+            },
+            MoveDef {
+                name: "Butt slam".to_string(),
+                sequence: keys("ss"),
+                ..MoveDef::default()
+            },
+            MoveDef {
+                name: "Slide".to_string(),
+                sequence: keys("sso"),
+                priority: 3,
+                ..MoveDef::default()
+            },
+        ],
+        ..Grammar::default()
+    };
+    let dfa = DFA::from_grammar(&grammar);
+    assert_eq!(dfa.priority("Slide"), 3);
+    let config = DFAConfig {
+        policy: MatchPolicy::Priority,
+        ..DFAConfig::default()
+    };
+    let mut recognizer = Recognizer::new(&dfa);
+
+    // Claw Slam outranks both Knockdown and the longer Saibot Blast
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.matches, vec!["Claw Slam".to_string()]);
+
+    // Butt slam waits for the higher-priority Slide
+    recognizer.reset(&dfa);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    assert!(recognizer
+        .feed(&dfa, 's', "Down", &config)
+        .matches
+        .is_empty());
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.matches, vec!["Slide".to_string()]);
+}
+
+#[test]
+fn ui_model_tracks_history_gaps_and_fades_matches() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut model = UiModel::new(dfa.start_state());
+
+    let t0 = Instant::now();
+    model.record(&recognizer.feed(&dfa, 'o', "[BP]", &config), t0);
+    let t1 = t0 + Duration::from_millis(120);
+    let event = recognizer.feed(&dfa, 'l', "[FP]", &config);
+    model.record(&event, t1);
+
+    let history = model.history();
+    assert_eq!(history.len(), 2);
+    let latest = history.latest().unwrap();
+    assert_eq!(latest.token, "[FP]");
+    assert_eq!(history.gap(latest.index), Some(Duration::from_millis(120)));
+    assert_eq!(history.gap(latest.index - 1), None);
+    assert_eq!(model.state(), &event.to);
+
+    let names: Vec<_> = model.feed().map(|e| e.name.as_str()).collect();
+    assert_eq!(names, vec!["Saibot Blast", "Claw Slam"]);
+
+    model.prune(t1 + Duration::from_secs(10));
+    assert_eq!(model.feed().count(), 0);
+    assert_eq!(model.history().len(), 2);
+}
+
+#[test]
+fn progress_counts_entered_inputs_of_each_move() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    assert_eq!(dfa.progress(recognizer.state(), &keys("sso")), 0);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    recognizer.feed(&dfa, 's', "Down", &config);
+    assert_eq!(dfa.progress(recognizer.state(), &keys("sso")), 2);
+    assert_eq!(dfa.progress(recognizer.state(), &keys("ol")), 0);
+
+    recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(dfa.progress(recognizer.state(), &keys("sso")), 3);
+}
+
+#[test]
+fn dfa_states_can_be_queried_for_what_comes_next() {
+    let dfa = mk9_dfa();
+    let start = dfa.start_state();
+    assert_eq!(dfa.expected_symbols(start), vec!['o', 's']);
+    assert_eq!(dfa.depth(start), Some(0));
+
+    let after_o = dfa
+        .delta(start, &'o'.into())
+        .expect("o starts Claw Slam")
+        .clone();
+    assert_eq!(dfa.depth(&after_o), Some(1));
+    assert_eq!(dfa.path_to(&after_o), Some(keys("o")));
+    let outgoing: Vec<_> = dfa.transitions_from(&after_o).collect();
+    assert_eq!(outgoing.len(), 1);
+    assert_eq!(*outgoing[0].0, 'l');
+
+    let reachable = dfa.reachable_moves(&after_o);
+    let summary: Vec<_> = reachable
+        .iter()
+        .map(|c| (c.name, c.remaining.clone()))
+        .collect();
+    assert_eq!(
+        summary,
+        vec![("Claw Slam", vec![]), ("Saibot Blast", keys("l"))]
+    );
+    assert_eq!(dfa.get_matches(reachable[1].state).map(Vec::len), Some(1));
+    assert_eq!(dfa.pending_moves(&after_o), vec!["Saibot Blast"]);
+    assert!(dfa.has_pending_move(&after_o, |name| name == "Saibot Blast"));
+    assert!(!dfa.has_pending_move(&after_o, |name| name == "Claw Slam"));
+
+    let from_start: Vec<_> = dfa
+        .reachable_moves(start)
+        .iter()
+        .map(|c| c.remaining.len())
+        .collect();
+    assert_eq!(from_start, vec![1, 2, 3], "nearest moves come first");
+    assert_eq!(dfa.depth(&"q99".to_string()), None);
+}
+
+#[test]
+fn every_move_of_a_roster_is_reachable_once_with_its_input() {
+    let grammar = generate_grammar(&GeneratorConfig::default()).unwrap();
+    let dfa = DFA::from_grammar(&grammar);
+    let start = dfa.start_state();
+    let reachable = dfa.reachable_moves(start);
+    assert_eq!(reachable.len(), grammar.moves.len());
+    for completion in &reachable {
+        let end = completion
+            .remaining
+            .iter()
+            .try_fold(start, |state, key| dfa.delta(state, key));
+        assert_eq!(end, Some(completion.state), "{}", completion.name);
+    }
+}
+
+#[test]
+fn players_share_one_dfa_with_separate_states() {
+    let dfa = mk9_dfa();
+    let config = DFAConfig::default();
+    let mut p1 = Recognizer::new(&dfa);
+    let mut p2 = Recognizer::for_player(&dfa, 2);
+    let mut model = UiModel::new(dfa.start_state()).with_players(2);
+    let now = Instant::now();
+
+    model.record(&p1.feed(&dfa, 's', "Down", &config), now);
+    let event = p2.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.player, 2);
+    model.record(&event, now);
+    let event = p1.feed(&dfa, 's', "Down", &config);
+    assert!(!event.reset, "player 2 did not break player 1's sequence");
+    model.record(&event, now);
+
+    assert_eq!(model.states(), [p1.state().clone(), p2.state().clone()]);
+    let feed: Vec<_> = model.feed().map(|e| (e.player, e.name.as_str())).collect();
+    assert_eq!(feed, vec![(2, "Claw Slam")]);
+
+    model.release(&[(2, "Claw Slam".to_string())], dfa.start_state(), now);
+    assert_eq!(&model.states()[1], dfa.start_state());
+    assert_eq!(model.state(), p1.state());
+}
+
+#[test]
+fn move_list_scroll_stays_in_range() {
+    let dfa = mk9_dfa();
+    let mut model = UiModel::new(dfa.start_state());
+
+    model.scroll_move_list(-5, 10);
+    assert_eq!(model.move_list_scroll(), 0);
+    model.scroll_move_list(25, 10);
+    assert_eq!(model.move_list_scroll(), 10);
+    // A smaller maximum (e.g. after the window grew) clamps before scrolling
+    model.scroll_move_list(-1, 4);
+    assert_eq!(model.move_list_scroll(), 3);
+}
+
+#[test]
+fn matches_carry_move_metadata() {
+    let slam = MoveMetadata {
+        damage: Some(12),
+        category: Some(MoveCategory::Special),
+        ..MoveMetadata::default()
+    };
+    let grammar = Grammar {
+        moves: vec![
+            MoveDef {
+                name: "Butt slam".to_string(),
+                sequence: keys("so"),
+                metadata: slam.clone(),
+                ..MoveDef::default()
+            },
+            MoveDef {
+                name: "Slide".to_string(),
+                sequence: keys("so"),
+                ..MoveDef::default()
+            },
+        ],
+        ..Grammar::default()
+    };
+    let dfa = DFA::from_grammar(&grammar);
+    assert_eq!(dfa.metadata("Butt slam"), Some(&slam));
+    assert_eq!(dfa.metadata("Slide"), None);
+
+    let mut recognizer = Recognizer::new(&dfa);
+    let config = DFAConfig::default();
+    assert!(recognizer
+        .feed(&dfa, 's', "Down", &config)
+        .metadata
+        .is_empty());
+    let event = recognizer.feed(&dfa, 'o', "[BP]", &config);
+    assert_eq!(event.matches, vec!["Butt slam", "Slide"]);
+    assert_eq!(event.metadata, vec![slam, MoveMetadata::default()]);
+}
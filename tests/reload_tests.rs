@@ -0,0 +1,101 @@
+mod common;
+
+use automate_refuse_de_nier::tools::reload::GrammarWatcher;
+use common::TestTempDir;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, Instant, UNIX_EPOCH};
+
+/// Write `contents` with a fixed modification time per `stamp`, so that
+/// changes show even on file systems with coarse timestamps
+fn write_at(path: &Path, contents: &str, stamp: u64) {
+    fs::write(path, contents).expect("write grammar");
+    let time = UNIX_EPOCH + Duration::from_secs(1_600_000_000 + stamp);
+    File::options()
+        .write(true)
+        .open(path)
+        .and_then(|file| file.set_modified(time))
+        .expect("set modification time");
+}
+
+#[test]
+fn watcher_reloads_when_the_grammar_or_an_include_changes() {
+    let temp_dir = TestTempDir::new("ardn_reload");
+    let keys = temp_dir.path().join("keys.gmr");
+    let moves = temp_dir.path().join("moves.gmr");
+    write_at(&keys, "s, Down\no, [BP]\n", 1);
+    write_at(&moves, "include \"keys.gmr\"\nButt slam: s s o\n", 1);
+
+    let mut watcher = GrammarWatcher::new(&moves).with_interval(Duration::ZERO);
+    let (grammar, _) = watcher.load().expect("valid grammar");
+    assert_eq!(grammar.moves.len(), 1);
+    assert_eq!(watcher.sources().collect::<Vec<_>>(), vec![&moves, &keys]);
+    assert!(watcher.poll(Instant::now()).is_none());
+
+    // An edit of the included file is picked up
+    write_at(&keys, "s, Down\no, [BP]\nl, [FP]\n", 2);
+    let (grammar, dfa) = watcher
+        .poll(Instant::now())
+        .expect("changed")
+        .expect("valid grammar");
+    assert_eq!(grammar.get_token_for_key("l"), Some("[FP]"));
+    assert_eq!(dfa.states().len(), 4);
+
+    // A broken edit is reported once, then fixing it reloads again
+    write_at(&moves, "include \"keys.gmr\"\nButt slam: s \"s o\n", 3);
+    let err = watcher
+        .poll(Instant::now())
+        .expect("changed")
+        .expect_err("invalid grammar");
+    assert!(
+        err.starts_with("2: unterminated quote"),
+        "unexpected error: {err}"
+    );
+    assert!(watcher.poll(Instant::now()).is_none());
+    write_at(
+        &moves,
+        "include \"keys.gmr\"\nButt slam: s s o\nSlide: l\n",
+        4,
+    );
+    let (grammar, _) = watcher
+        .poll(Instant::now())
+        .expect("changed")
+        .expect("valid grammar");
+    assert_eq!(grammar.moves.len(), 2);
+}
+
+#[test]
+fn watcher_polls_at_most_once_per_interval() {
+    let temp_dir = TestTempDir::new("ardn_reload_interval");
+    let path = temp_dir.path().join("moves.gmr");
+    write_at(&path, "s, Down\n", 1);
+
+    let mut watcher = GrammarWatcher::new(&path).with_interval(Duration::from_secs(1));
+    watcher.load().expect("valid grammar");
+    let start = Instant::now();
+    assert!(watcher.poll(start).is_none());
+
+    write_at(&path, "s, Down\no, [BP]\n", 2);
+    assert!(watcher.poll(start + Duration::from_millis(500)).is_none());
+    let reloaded = watcher
+        .poll(start + Duration::from_secs(1))
+        .expect("changed");
+    assert_eq!(reloaded.expect("valid grammar").0.mappings.len(), 2);
+}
+
+#[test]
+fn watcher_follows_includes_that_are_missing_at_first() {
+    let temp_dir = TestTempDir::new("ardn_reload_missing");
+    let path = temp_dir.path().join("moves.gmr");
+    let keys = temp_dir.path().join("keys.gmr");
+    write_at(&path, "include \"keys.gmr\"\n", 1);
+
+    let mut watcher = GrammarWatcher::new(&path).with_interval(Duration::ZERO);
+    assert!(watcher.load().is_err());
+    write_at(&keys, "s, Down\n", 2);
+    let (grammar, _) = watcher
+        .poll(Instant::now())
+        .expect("include created")
+        .expect("valid grammar");
+    assert_eq!(grammar.get_token_for_key("s"), Some("Down"));
+}
@@ -0,0 +1,244 @@
+mod common;
+
+use automate_refuse_de_nier::tools::cli::{EXIT_OK, EXIT_USAGE};
+use automate_refuse_de_nier::tools::drill::{Drill, DrillOrder};
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, parse_grammar_text, MoveDef};
+use automate_refuse_de_nier::tools::stats::{AttemptRecord, StatsStore, StatsTracker};
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, DFA};
+use common::{run_binary, TestTempDir};
+use std::time::{Duration, Instant};
+
+fn butt_slam() -> MoveDef {
+    MoveDef {
+        name: "Butt slam (Ermac)".to_string(),
+        sequence: vec!['s'.into(), 's'.into(), 'o'.into()],
+        ..MoveDef::default()
+    }
+}
+
+fn completed(ms: u64) -> AttemptRecord {
+    AttemptRecord {
+        completed: true,
+        time: Some(Duration::from_millis(ms)),
+        mistake_step: None,
+    }
+}
+
+fn missed(step: usize) -> AttemptRecord {
+    AttemptRecord {
+        completed: false,
+        time: None,
+        mistake_step: Some(step),
+    }
+}
+
+#[test]
+fn statistics_add_up_per_move() {
+    let mut store = StatsStore::new();
+    let m = butt_slam();
+    store.record("mk9.gmr", &m, &completed(100));
+    store.record("mk9.gmr", &m, &completed(300));
+    store.record("mk9.gmr", &m, &missed(2));
+    store.record("mk9.gmr", &m, &missed(1));
+    store.record("mk9.gmr", &m, &missed(2));
+
+    let stats = store.move_stats("mk9.gmr", &m).expect("recorded");
+    assert_eq!(stats.attempts, 5);
+    assert_eq!(stats.completions, 2);
+    assert_eq!(stats.average_time(), Some(Duration::from_millis(200)));
+    assert_eq!(stats.most_common_mistake(), Some((2, 2)));
+    assert!((stats.completion_rate() - 0.4).abs() < 1e-9);
+
+    let grammar = store.grammar("mk9.gmr").expect("recorded");
+    assert!(grammar["Ermac"].contains_key("Butt slam"));
+    assert!(store.grammar("other.gmr").is_none());
+}
+
+#[test]
+fn statistics_survive_a_save_and_load() {
+    let temp_dir = TestTempDir::new("ardn_stats");
+    let path = temp_dir.path().join("stats.toml");
+    let mut store = StatsStore::new();
+    store.record("mk9.gmr", &butt_slam(), &completed(120));
+    store.save(&path).expect("save");
+
+    let loaded = StatsStore::load(&path).expect("load");
+    assert_eq!(loaded, store);
+
+    let missing = StatsStore::load(temp_dir.path().join("missing.toml")).expect("missing file");
+    assert_eq!(missing, StatsStore::new());
+}
+
+#[test]
+fn reset_forgets_a_grammar_or_one_character() {
+    let mut store = StatsStore::new();
+    let claw = MoveDef {
+        name: "Claw Slam (Freddy Krueger)".to_string(),
+        sequence: vec!['o'.into()],
+        ..MoveDef::default()
+    };
+    store.record("mk9.gmr", &butt_slam(), &completed(100));
+    store.record("mk9.gmr", &claw, &completed(10));
+    store.record("other.gmr", &claw, &completed(10));
+
+    assert_eq!(store.reset("mk9.gmr", Some("ermac")), 1);
+    assert!(store.move_stats("mk9.gmr", &butt_slam()).is_none());
+    assert!(store.move_stats("mk9.gmr", &claw).is_some());
+
+    assert_eq!(store.reset("mk9.gmr", None), 1);
+    assert!(store.grammar("mk9.gmr").is_none());
+    assert!(store.grammar("other.gmr").is_some());
+}
+
+#[test]
+fn tracker_turns_recognizer_steps_into_attempts() {
+    let grammar = parse_grammar_file("grammars/mk9_with_moves.gmr").expect("grammar");
+    let dfa = DFA::from_grammar(&grammar);
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut tracker = StatsTracker::new(&grammar);
+    let start = Instant::now();
+
+    let mut feed = |keys: &str, offset: u64| {
+        let mut ended = Vec::new();
+        for (i, key) in keys.chars().enumerate() {
+            let event = recognizer.feed(&dfa, key, "", &DFAConfig::default());
+            let at = start + Duration::from_millis(offset + 100 * i as u64);
+            for (m, record) in tracker.observe(&dfa, &event, at) {
+                ended.push((m.name.clone(), record));
+            }
+        }
+        ended
+    };
+
+    let slam = feed("sso", 0);
+    assert_eq!(
+        slam,
+        vec![("Butt slam (Ermac)".to_string(), completed(200))]
+    );
+
+    // Broken after two inputs
+    let broken = feed("ssl", 1000);
+    assert_eq!(broken, vec![("Butt slam (Ermac)".to_string(), missed(2))]);
+
+    // `o` completes Claw Slam, so the broken Saibot Blast is not held against
+    // the player
+    let other = feed("os", 2000);
+    assert!(other
+        .iter()
+        .all(|(name, _)| !name.starts_with("Saibot Blast")));
+}
+
+#[test]
+fn completing_a_move_is_no_miss_on_moves_sharing_its_prefix() {
+    let grammar =
+        parse_grammar_text("a, A\nb, B\nc, C\nX: a b\nY: a c\n", "xy.gmr").expect("grammar");
+    let dfa = DFA::from_grammar(&grammar);
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut tracker = StatsTracker::new(&grammar);
+    let start = Instant::now();
+
+    let mut ended = Vec::new();
+    for (i, key) in "abac".chars().enumerate() {
+        let event = recognizer.feed(&dfa, key, "", &DFAConfig::default());
+        let at = start + Duration::from_millis(100 * i as u64);
+        for (m, record) in tracker.observe(&dfa, &event, at) {
+            ended.push((m.name.clone(), record));
+        }
+    }
+    assert_eq!(
+        ended,
+        vec![
+            ("X".to_string(), completed(100)),
+            ("Y".to_string(), completed(100)),
+        ]
+    );
+}
+
+#[test]
+fn ignored_and_skipped_keys_keep_the_attempt_going() {
+    let grammar = parse_grammar_text(
+        "d, Down-Forward\nf, Forward\no, [BP]\np, Block\ns, Down\n\
+         ignore p\noptional d\nFireball: s d f o\n",
+        "qcf.gmr",
+    )
+    .expect("grammar");
+    let dfa = DFA::from_grammar(&grammar);
+    let start = Instant::now();
+
+    for keys in ["spdfo", "sfo"] {
+        let mut recognizer = Recognizer::new(&dfa);
+        let mut tracker = StatsTracker::new(&grammar);
+        let mut ended = Vec::new();
+        for (i, key) in keys.chars().enumerate() {
+            let event = recognizer.feed(&dfa, key, "", &DFAConfig::default());
+            let at = start + Duration::from_millis(100 * i as u64);
+            for (m, record) in tracker.observe(&dfa, &event, at) {
+                ended.push((m.name.clone(), record));
+            }
+        }
+        let time = 100 * (keys.len() as u64 - 1);
+        assert_eq!(
+            ended,
+            vec![("Fireball".to_string(), completed(time))],
+            "{keys}"
+        );
+    }
+}
+
+#[test]
+fn drill_attempts_become_records() {
+    let mut drill = Drill::new(vec![butt_slam()], DrillOrder::Sequential, 1);
+    let start = Instant::now();
+
+    drill.next_prompt(start);
+    for (i, key) in "sso".chars().enumerate() {
+        drill.input(key, start + Duration::from_millis(100 * (i as u64 + 1)));
+    }
+    drill.finish_attempt();
+    drill.next_prompt(start);
+    drill.input('s', start);
+    drill.input('o', start);
+    drill.finish_attempt();
+
+    let records: Vec<AttemptRecord> = drill
+        .results()
+        .map(|(_, result)| AttemptRecord::from(result))
+        .collect();
+    assert_eq!(records, vec![completed(200), missed(1)]);
+}
+
+#[test]
+fn stats_command_prints_and_resets() {
+    let temp_dir = TestTempDir::new("ardn_stats");
+    let path = temp_dir.path().join("stats.toml");
+    let file = path.to_str().expect("utf-8 path");
+    let mut store = StatsStore::new();
+    store.record("mk9_with_moves.gmr", &butt_slam(), &missed(2));
+    store.save(&path).expect("save");
+
+    let printed = run_binary(&["stats", "grammars/mk9_with_moves.gmr", "--stats-file", file]);
+    assert_eq!(printed.status.code(), Some(EXIT_OK as i32));
+    let text = String::from_utf8_lossy(&printed.stdout);
+    assert!(text.contains("Ermac:"));
+    assert!(text.contains("step 3 ([BP]) x1"));
+
+    let reset = run_binary(&[
+        "stats",
+        "grammars/mk9_with_moves.gmr",
+        "--reset",
+        "--stats-file",
+        file,
+    ]);
+    assert_eq!(reset.status.code(), Some(EXIT_OK as i32));
+    let loaded = StatsStore::load(&path).expect("load");
+    assert!(loaded.grammar("mk9_with_moves.gmr").is_none());
+
+    let conflicting = run_binary(&[
+        "run",
+        "grammars/mk9.gmr",
+        "--no-stats",
+        "--stats-file",
+        file,
+    ]);
+    assert_eq!(conflicting.status.code(), Some(EXIT_USAGE as i32));
+}
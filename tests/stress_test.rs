@@ -12,10 +12,10 @@ fn stress_test_dfa_performance() {
     ];
 
     let dfa = DFA::from_moves(moves);
-    let config = DFAConfig { debug: false };
+    let config = DFAConfig::default();
 
     let mut current_state = dfa.start_state().clone();
-    let test_sequence = vec!['a', 'b', 'c', 'd', 'd', 'd', 'a', 'b'];
+    let test_sequence = ['a', 'b', 'c', 'd', 'd', 'd', 'a', 'b'];
 
     let iterations = 100_000;
 
@@ -23,7 +23,7 @@ fn stress_test_dfa_performance() {
         let token = test_sequence[i % test_sequence.len()];
         let token_name = format!("[{}]", token);
 
-        let (next_state, _matches) = dfa.step(&current_state, token, &token_name, &config);
+        let (next_state, _matches) = dfa.step(&current_state, &token.into(), &token_name, &config);
 
         if let Some(next) = next_state {
             current_state = next.clone();
@@ -38,47 +38,3 @@ fn stress_test_dfa_performance() {
         iterations
     );
 }
-
-#[test]
-fn benchmark_dfa_lookup() {
-    use std::time::Instant;
-
-    let mut moves = Vec::new();
-    for i in 0..50 {
-        let seq = vec!['a', (b'a' + (i % 26) as u8) as char];
-        moves.push((seq, format!("Move {}", i)));
-    }
-
-    let dfa = DFA::from_moves(moves);
-    let config = DFAConfig { debug: false };
-
-    let mut current_state = dfa.start_state().clone();
-    let iterations = 1_000_000;
-
-    let start = Instant::now();
-
-    for i in 0..iterations {
-        let token = (b'a' + (i % 26) as u8) as char;
-        let token_name = format!("[{}]", token);
-
-        let (next_state, _matches) = dfa.step(&current_state, token, &token_name, &config);
-
-        if let Some(next) = next_state {
-            current_state = next.clone();
-        } else {
-            current_state = dfa.start_state().clone();
-        }
-    }
-
-    let duration = start.elapsed();
-    println!("Benchmark: {} transitions in {:?}", iterations, duration);
-    println!(
-        "Average: {:.2} ns/transition",
-        duration.as_nanos() as f64 / iterations as f64
-    );
-
-    assert!(
-        duration.as_micros() < iterations,
-        "DFA transitions too slow"
-    );
-}
@@ -0,0 +1,125 @@
+use automate_refuse_de_nier::tools::dfa::Symbol;
+use automate_refuse_de_nier::tools::document::{parse_grammar_document, GrammarFormat};
+use automate_refuse_de_nier::tools::format::format_gmr;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_text, Grammar};
+use automate_refuse_de_nier::tools::{feed_lines, DFAConfig, Recognizer, DFA};
+
+/// Accented, named and overlapping keys
+const KEYS: &str = "\
+LeftShift, Block
+d,         Forward
+df,        Dash
+s,         Down
+é,         Jump
+
+player 2
+Keypad2, Down
+
+Dash Jump: df é
+Low Dash:  s df
+";
+
+fn keys_grammar() -> Grammar {
+    parse_grammar_text(KEYS, "keys.gmr").expect("valid grammar")
+}
+
+fn names(keys: Vec<&Symbol>) -> Vec<&str> {
+    keys.into_iter().map(Symbol::as_str).collect()
+}
+
+#[test]
+fn keys_are_characters_or_names() {
+    let accented = Symbol::new("é").unwrap();
+    assert_eq!(accented.as_char(), Some('é'));
+    assert_eq!(accented.width(), 1);
+    assert_eq!(accented, 'é');
+
+    let named = Symbol::new("LeftShift").unwrap();
+    assert_eq!(named.as_char(), None);
+    assert_eq!(named.width(), 9);
+    assert_eq!(named.to_string(), "LeftShift");
+
+    assert_eq!(Symbol::new("").unwrap_err(), "key must not be empty");
+    for key in ["Left Shift", "a\tb", " "] {
+        let err = Symbol::new(key).expect_err(key);
+        assert!(err.starts_with("invalid key"), "{key}: {err}");
+    }
+}
+
+#[test]
+fn unicode_and_named_keys_are_parsed_and_formatted() {
+    let grammar = keys_grammar();
+    assert_eq!(grammar.get_token_for_key("é"), Some("Jump"));
+    assert_eq!(grammar.get_token_for_key("LeftShift"), Some("Block"));
+    assert_eq!(
+        grammar.resolve_key("Keypad2"),
+        Some((2, &Symbol::from('s')))
+    );
+    assert_eq!(grammar.moves[0].sequence, ["df", "é"]);
+    assert_eq!(format_gmr(KEYS).unwrap(), KEYS);
+    assert_eq!(
+        parse_grammar_text(&grammar.to_gmr(), "keys.gmr").unwrap(),
+        grammar
+    );
+
+    let toml = r#"
+        mappings = { "é" = "Jump", df = "Dash" }
+        moves = [{ name = "Dash Jump", sequence = ["df", "é"] }]
+    "#;
+    let document = parse_grammar_document(toml, GrammarFormat::Toml).expect("valid toml");
+    assert_eq!(document.moves[0].sequence, grammar.moves[0].sequence);
+    assert_eq!(document.get_token_for_key("é"), Some("Jump"));
+
+    for (text, expected) in [
+        ("a b, Jump", "1: invalid key 'a b'"),
+        (", Jump", "1: key must not be empty"),
+        ("Slam: s \"a b\"", "1: move 'Slam': invalid key 'a b'"),
+    ] {
+        let err = parse_grammar_text(text, "bad.gmr").expect_err(text);
+        assert!(err.contains(expected), "{text}: {err}");
+    }
+}
+
+#[test]
+fn typed_text_is_split_into_the_longest_keys() {
+    let grammar = keys_grammar();
+    assert_eq!(names(grammar.keys_in("dfé")), ["df", "é"]);
+    assert_eq!(names(grammar.keys_in("d fé")), ["d", "é"]);
+    assert_eq!(
+        names(grammar.keys_in("s LeftShift Keypad2")),
+        ["s", "LeftShift", "Keypad2"]
+    );
+    assert_eq!(names(grammar.keys_in("xyz ")), Vec::<&str>::new());
+
+    let mut tokens = Vec::new();
+    feed_lines(
+        &grammar,
+        "sdf\nquit\nLeftShift\n".as_bytes(),
+        |key, token| tokens.push(format!("{key}={token}")),
+    )
+    .unwrap();
+    assert_eq!(tokens, ["s=Down", "df=Dash"]);
+}
+
+#[test]
+fn moves_of_named_keys_are_recognized() {
+    let grammar = keys_grammar();
+    let dfa = DFA::from_grammar(&grammar);
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+
+    let matches: Vec<String> = grammar
+        .keys_in("df é s df")
+        .into_iter()
+        .flat_map(|key| recognizer.feed(&dfa, key, "", &config).matches)
+        .collect();
+    assert_eq!(matches, ["Dash Jump", "Low Dash"]);
+    assert_eq!(
+        recognizer.history().get(1).map(|r| r.key.as_str()),
+        Some("é")
+    );
+
+    let outgoing: Vec<_> = dfa.transitions_from(dfa.start_state()).collect();
+    assert_eq!(outgoing.len(), 2);
+    assert_eq!(*outgoing[0].0, "df");
+}
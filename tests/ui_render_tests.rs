@@ -0,0 +1,134 @@
+mod common;
+
+use automate_refuse_de_nier::tools::layout::Layout;
+use automate_refuse_de_nier::tools::parsing::{parse_grammar_file, Grammar};
+use automate_refuse_de_nier::tools::ui::{self, UiModel};
+use automate_refuse_de_nier::tools::{DFAConfig, Recognizer, Theme, DFA};
+use common::TestTempDir;
+use sdl2::pixels::PixelFormatEnum;
+use sdl2::render::Canvas;
+use sdl2::surface::Surface;
+use std::time::{Duration, Instant};
+
+const BACKGROUND: [u8; 3] = [20, 20, 30];
+const HEADER: [u8; 3] = [255, 200, 100];
+const PANEL: [u8; 3] = [40, 40, 60];
+
+fn pixels(canvas: &Canvas<Surface>) -> Vec<u8> {
+    canvas
+        .read_pixels(None, PixelFormatEnum::RGB24)
+        .expect("read back pixels")
+}
+
+fn pixel_at(pixels: &[u8], width: u32, x: u32, y: u32) -> [u8; 3] {
+    let i = ((y * width + x) * 3) as usize;
+    [pixels[i], pixels[i + 1], pixels[i + 2]]
+}
+
+fn mk9_with_moves() -> (Grammar, DFA) {
+    let grammar = parse_grammar_file("grammars/mk9_with_moves.gmr").expect("parse grammar");
+    let dfa = DFA::from_moves(
+        grammar
+            .moves
+            .iter()
+            .map(|m| (m.sequence.clone(), m.name.clone())),
+    );
+    (grammar, dfa)
+}
+
+#[test]
+fn key_mappings_render_one_row_per_mapping() {
+    let grammar = parse_grammar_file("grammars/mk9.gmr").expect("parse grammar");
+    let (width, height) = ui::get_window_size();
+    let mut canvas = ui::create_offscreen_canvas(width, height).expect("offscreen canvas");
+    ui::render_key_mappings(&mut canvas, &Theme::default(), &grammar);
+    let pixels = pixels(&canvas);
+
+    assert_eq!(pixel_at(&pixels, width, 2, 2), BACKGROUND);
+    assert_eq!(pixel_at(&pixels, width, 25, 25), HEADER);
+
+    // Rows start at y=100 and are 30px apart; sample the left padding of each
+    for i in 0..grammar.mappings.len() as u32 {
+        assert_eq!(pixel_at(&pixels, width, 32, 101 + i * 30), PANEL, "row {i}");
+    }
+    assert_eq!(
+        pixel_at(&pixels, width, 32, 101 + grammar.mappings.len() as u32 * 30),
+        BACKGROUND
+    );
+}
+
+#[test]
+fn many_mappings_flow_into_columns_in_a_small_window() {
+    let mut grammar = Grammar::new();
+    for i in 0..40u8 {
+        let key = (b'0' + i) as char;
+        grammar.mappings.insert(key.into(), format!("Token {i}"));
+    }
+
+    let (width, height) = (640, 480);
+    let mut canvas = ui::create_offscreen_canvas(width, height).expect("offscreen canvas");
+    ui::render_key_mappings(&mut canvas, &Theme::default(), &grammar);
+    let pixels = pixels(&canvas);
+
+    // The body is 580x300 at (30, 100). Regular 30px rows would need four
+    // columns that are too narrow, so the compact 18px rows are used: 16 rows
+    // per column, 3 columns of 186px separated by 10px. The last mapping is
+    // therefore in column 2, row 7.
+    assert_eq!(
+        pixel_at(&pixels, width, 30 + 2 * 196 + 2, 100 + 7 * 18 + 1),
+        PANEL
+    );
+    assert_eq!(
+        pixel_at(&pixels, width, 30 + 2 * 196 + 2, 100 + 8 * 18 + 1),
+        BACKGROUND
+    );
+}
+
+#[test]
+fn live_frame_rendering_is_deterministic() {
+    let (grammar, dfa) = mk9_with_moves();
+    let config = DFAConfig::default();
+    let mut recognizer = Recognizer::new(&dfa);
+    let mut model = UiModel::new(dfa.start_state());
+    let start = Instant::now();
+    for (i, (key, token)) in [('s', "Down"), ('s', "Down"), ('o', "[BP]")]
+        .iter()
+        .enumerate()
+    {
+        let event = recognizer.feed(&dfa, *key, token, &config);
+        model.record(&event, start + Duration::from_millis(100 * i as u64));
+    }
+    let now = start + Duration::from_millis(500);
+    let theme = Theme::default();
+
+    for (width, height) in [(1000, 600), (640, 480), (1600, 900)] {
+        let layout = Layout::compute(width, height);
+        let mut first = ui::create_offscreen_canvas(width, height).expect("offscreen canvas");
+        ui::render_frame(&mut first, &layout, &theme, &grammar, &dfa, &model, now);
+        let mut second = ui::create_offscreen_canvas(width, height).expect("offscreen canvas");
+        ui::render_frame(&mut second, &layout, &theme, &grammar, &dfa, &model, now);
+
+        assert_eq!(pixels(&first), pixels(&second), "{width}x{height}");
+    }
+}
+
+#[test]
+fn dumped_frame_can_be_loaded_back() {
+    let (grammar, dfa) = mk9_with_moves();
+    let model = UiModel::new(dfa.start_state());
+    let temp_dir = TestTempDir::new("ardn_frame");
+    let path = temp_dir.path().join("frame.bmp");
+
+    ui::dump_frame_bmp(
+        &path,
+        &Theme::default(),
+        &grammar,
+        &dfa,
+        &model,
+        Instant::now(),
+    )
+    .expect("dump frame");
+    let loaded = Surface::load_bmp(&path).expect("load dumped frame");
+
+    assert_eq!((loaded.width(), loaded.height()), ui::get_window_size());
+}